@@ -1,7 +1,9 @@
-use crate::token::Token;
+use crate::{bigint::BigInt, token::{Span, Token}};
 
 pub trait Node {
     fn token_literal(&self) -> String;
+    // source range covered by the node, from its first token to its last child
+    fn span(&self) -> Span;
     fn print_string(&self) -> String;
 }
 
@@ -11,25 +13,51 @@ pub enum StatementNode {
     Return(ReturnStatement),
     Expression(ExpressionStatement),
     Block(BlockStatement),
+    While(WhileStatement),
+    For(ForStatement),
+    Function(FunctionDeclaration),
+    Break(Token),
+    Continue(Token),
 }
 
 impl Node for StatementNode {
     fn token_literal(&self) -> String {
-        return match self {
+        match self {
             Self::Say(say_stmt) => say_stmt.token_literal(),
             Self::Return(ret_stmt) => ret_stmt.token_literal(),
             Self::Expression(expression) => expression.token_literal(),
             Self::Block(block_stmt) => block_stmt.token_literal(),
-        };
+            Self::While(while_stmt) => while_stmt.token_literal(),
+            Self::For(for_stmt) => for_stmt.token_literal(),
+            Self::Function(declaration) => declaration.token_literal(),
+            Self::Break(token) | Self::Continue(token) => token.literal.clone(),
+        }
+    }
+
+    fn span(&self) -> Span {
+        match self {
+            Self::Say(say_stmt) => say_stmt.span(),
+            Self::Return(ret_stmt) => ret_stmt.span(),
+            Self::Expression(expression) => expression.span(),
+            Self::Block(block_stmt) => block_stmt.span(),
+            Self::While(while_stmt) => while_stmt.span(),
+            Self::For(for_stmt) => for_stmt.span(),
+            Self::Function(declaration) => declaration.span(),
+            Self::Break(token) | Self::Continue(token) => token.span,
+        }
     }
 
     fn print_string(&self) -> String {
-        return match self {
+        match self {
             Self::Say(say_stmt) => say_stmt.print_string(),
             Self::Return(ret_stmt) => ret_stmt.print_string(),
             Self::Expression(expression) => expression.print_string(),
             Self::Block(block_stmt) => block_stmt.print_string(),
-        };
+            Self::While(while_stmt) => while_stmt.print_string(),
+            Self::For(for_stmt) => for_stmt.print_string(),
+            Self::Function(declaration) => declaration.print_string(),
+            Self::Break(token) | Self::Continue(token) => format!("{};", token.literal),
+        }
     }
 }
 
@@ -39,53 +67,91 @@ pub enum ExpressionNode {
     None,
     IdentifierNode(Identifier),
     Integer(IntegerLiteral),
+    BigInt(BigIntLiteral),
+    Float(FloatLiteral),
     Prefix(PrefixExpression),
     Infix(InfixExpression),
     BooleanNode(Boolean),
     IfExpressionNode(IfExpression),
+    Match(MatchExpression),
     Function(FunctionLiteral),
     Call(CallExpression),
     StringExp(StringLiteral),
+    InterpolatedString(InterpolatedString),
     Array(ArrayLiteral),
     Index(IndexExpression),
     Dictionary(DictLiteral),
+    Assign(AssignExpression),
 }
 
 impl Node for ExpressionNode {
     fn token_literal(&self) -> String {
-        return match self {
+        match self {
             Self::IdentifierNode(identifier) => identifier.token_literal(),
             Self::Integer(integer) => integer.token_literal(),
+            Self::BigInt(integer) => integer.token_literal(),
+            Self::Float(float) => float.token_literal(),
             Self::Prefix(prefix_exp) => prefix_exp.token_literal(),
             Self::Infix(infix_exp) => infix_exp.token_literal(),
             Self::BooleanNode(bool_exp) => bool_exp.token_literal(),
             Self::IfExpressionNode(if_exp) => if_exp.token_literal(),
+            Self::Match(match_exp) => match_exp.token_literal(),
             Self::Function(func_literal) => func_literal.token_literal(),
             Self::Call(call_exp) => call_exp.token_literal(),
             Self::StringExp(string) => string.token_literal(),
+            Self::InterpolatedString(string) => string.token_literal(),
             Self::Array(array) => array.token_literal(),
             Self::Index(index_exp) => index_exp.token_literal(),
             Self::Dictionary(dictionary) => dictionary.token_literal(),
+            Self::Assign(assign_exp) => assign_exp.token_literal(),
             Self::None => String::from(""),
-        };
+        }
+    }
+
+    fn span(&self) -> Span {
+        match self {
+            Self::IdentifierNode(identifier) => identifier.span(),
+            Self::Integer(integer) => integer.span(),
+            Self::BigInt(integer) => integer.span(),
+            Self::Float(float) => float.span(),
+            Self::Prefix(prefix_exp) => prefix_exp.span(),
+            Self::Infix(infix_exp) => infix_exp.span(),
+            Self::BooleanNode(bool_exp) => bool_exp.span(),
+            Self::IfExpressionNode(if_exp) => if_exp.span(),
+            Self::Match(match_exp) => match_exp.span(),
+            Self::Function(func_literal) => func_literal.span(),
+            Self::Call(call_exp) => call_exp.span(),
+            Self::StringExp(string) => string.span(),
+            Self::InterpolatedString(string) => string.span(),
+            Self::Array(array) => array.span(),
+            Self::Index(index_exp) => index_exp.span(),
+            Self::Dictionary(dictionary) => dictionary.span(),
+            Self::Assign(assign_exp) => assign_exp.span(),
+            Self::None => Span::default(),
+        }
     }
 
     fn print_string(&self) -> String {
-        return match self {
+        match self {
             Self::IdentifierNode(identifier) => identifier.print_string(),
             Self::Integer(integer) => integer.print_string(),
+            Self::BigInt(integer) => integer.print_string(),
+            Self::Float(float) => float.print_string(),
             Self::Prefix(prefix_exp) => prefix_exp.print_string(),
             Self::Infix(infix_exp) => infix_exp.print_string(),
             Self::BooleanNode(bool_exp) => bool_exp.print_string(),
             Self::IfExpressionNode(if_exp) => if_exp.print_string(),
+            Self::Match(match_exp) => match_exp.print_string(),
             Self::Function(func_literal) => func_literal.print_string(),
             Self::Call(call_exp) => call_exp.print_string(),
             Self::StringExp(string) => string.print_string(),
+            Self::InterpolatedString(string) => string.print_string(),
             Self::Array(array) => array.print_string(),
             Self::Index(index_exp) => index_exp.print_string(),
             Self::Dictionary(dictionary) => dictionary.print_string(),
+            Self::Assign(assign_exp) => assign_exp.print_string(),
             Self::None => String::from(""),
-        };
+        }
     }
 }
 
@@ -95,16 +161,18 @@ pub struct Program {
 
 impl Node for Program {
     fn token_literal(&self) -> String {
-        return if self.statements.len() > 0 {
-            match &self.statements[0] {
-                StatementNode::Say(say_stmt) => say_stmt.token_literal(),
-                StatementNode::Return(ret_stmt) => ret_stmt.token_literal(),
-                StatementNode::Expression(expression) => expression.token_literal(),
-                StatementNode::Block(block_stmt) => block_stmt.token_literal(),
-            }
+        if !self.statements.is_empty() {
+            self.statements[0].token_literal()
         } else {
             String::from("")
-        };
+        }
+    }
+
+    fn span(&self) -> Span {
+        match (self.statements.first(), self.statements.last()) {
+            (Some(first), Some(last)) => first.span().to(last.span()),
+            _ => Span::default(),
+        }
     }
 
     fn print_string(&self) -> String {
@@ -130,18 +198,25 @@ impl Node for SayStatement {
         self.token.literal.clone()
     }
 
+    fn span(&self) -> Span {
+        match &self.value {
+            Some(value) => self.token.span.to(value.span()),
+            None => self.token.span.to(self.name.span()),
+        }
+    }
+
     fn print_string(&self) -> String {
         let mut out = String::from("");
 
         out.push_str(self.token_literal().as_str());
-        out.push_str(" ");
+        out.push(' ');
         out.push_str(self.name.print_string().as_str());
         out.push_str(" = ");
 
         if let Some(value) = &self.value {
             out.push_str(value.print_string().as_str());
         }
-        out.push_str(";");
+        out.push(';');
 
         out
     }
@@ -158,6 +233,10 @@ impl Node for Identifier {
         self.token.literal.clone()
     }
 
+    fn span(&self) -> Span {
+        self.token.span
+    }
+
     fn print_string(&self) -> String {
         self.value.clone()
     }
@@ -174,17 +253,24 @@ impl Node for ReturnStatement {
         self.token.literal.clone()
     }
 
+    fn span(&self) -> Span {
+        match &self.return_value {
+            Some(return_value) => self.token.span.to(return_value.span()),
+            None => self.token.span,
+        }
+    }
+
     fn print_string(&self) -> String {
         let mut out = String::from("");
 
         out.push_str(self.token_literal().as_str());
-        out.push_str(" ");
+        out.push(' ');
 
         if let Some(return_value) = &self.return_value {
             out.push_str(return_value.print_string().as_str());
         }
 
-        out.push_str(";");
+        out.push(';');
 
         out
     }
@@ -201,6 +287,13 @@ impl Node for ExpressionStatement {
         self.token.literal.clone()
     }
 
+    fn span(&self) -> Span {
+        match &self.expression {
+            Some(expression) => expression.span(),
+            None => self.token.span,
+        }
+    }
+
     fn print_string(&self) -> String {
         if let Some(expression) = &self.expression {
             return expression.print_string();
@@ -220,6 +313,51 @@ impl Node for IntegerLiteral {
         self.token.literal.clone()
     }
 
+    fn span(&self) -> Span {
+        self.token.span
+    }
+
+    fn print_string(&self) -> String {
+        self.token_literal()
+    }
+}
+
+// an integer literal too large for an `i64`
+#[derive(Debug, Clone)]
+pub struct BigIntLiteral {
+    pub token: Token,
+    pub value: BigInt,
+}
+
+impl Node for BigIntLiteral {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span
+    }
+
+    fn print_string(&self) -> String {
+        self.token_literal()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FloatLiteral {
+    pub token: Token,
+    pub value: f64,
+}
+
+impl Node for FloatLiteral {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span
+    }
+
     fn print_string(&self) -> String {
         self.token_literal()
     }
@@ -237,12 +375,55 @@ impl Node for PrefixExpression {
         self.token.literal.clone()
     }
 
+    fn span(&self) -> Span {
+        self.token.span.to(self.right.span())
+    }
+
     fn print_string(&self) -> String {
         let mut out = String::from("");
-        out.push_str("(");
+        out.push('(');
         out.push_str(self.operator.as_str());
         out.push_str(self.right.print_string().as_str());
-        out.push_str(")");
+        out.push(')');
+
+        out
+    }
+}
+
+//      `x = value`, or `x += value` and friends, where `operator` is the whole token
+#[derive(Debug, Default, Clone)]
+pub struct AssignExpression {
+    pub token: Token,
+    pub target: Box<ExpressionNode>,
+    pub operator: String,
+    pub value: Box<ExpressionNode>,
+}
+
+impl AssignExpression {
+    // the infix operator of a compound assignment: `+` for `+=`
+    pub fn infix_operator(&self) -> Option<&str> {
+        self.operator.strip_suffix('=').filter(|op| !op.is_empty())
+    }
+}
+
+impl Node for AssignExpression {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn span(&self) -> Span {
+        self.target.span().to(self.value.span())
+    }
+
+    fn print_string(&self) -> String {
+        let mut out = String::from("");
+        out.push('(');
+        out.push_str(self.target.print_string().as_str());
+        out.push(' ');
+        out.push_str(self.operator.as_str());
+        out.push(' ');
+        out.push_str(self.value.print_string().as_str());
+        out.push(')');
 
         out
     }
@@ -259,6 +440,10 @@ impl Node for Boolean {
         self.token.literal.clone()
     }
 
+    fn span(&self) -> Span {
+        self.token.span
+    }
+
     fn print_string(&self) -> String {
         self.token_literal()
     }
@@ -277,13 +462,17 @@ impl Node for InfixExpression {
         self.token.literal.clone()
     }
 
+    fn span(&self) -> Span {
+        self.left.span().to(self.right.span())
+    }
+
     fn print_string(&self) -> String {
         let mut out = String::from("");
-        out.push_str("(");
+        out.push('(');
         out.push_str(self.left.print_string().as_str());
         out.push_str(format!(" {} ", self.operator).as_str());
         out.push_str(self.right.print_string().as_str());
-        out.push_str(")");
+        out.push(')');
 
         out
     }
@@ -302,12 +491,19 @@ impl Node for IfExpression {
         self.token.literal.clone()
     }
 
+    fn span(&self) -> Span {
+        match &self.alternative {
+            Some(alt) => self.token.span.to(alt.span()),
+            None => self.token.span.to(self.consequence.span()),
+        }
+    }
+
     fn print_string(&self) -> String {
         let mut out = String::from("");
 
         out.push_str("if");
         out.push_str(self.condition.print_string().as_str());
-        out.push_str(" ");
+        out.push(' ');
         out.push_str(self.consequence.print_string().as_str());
 
         if let Some(alt) = &self.alternative {
@@ -319,18 +515,158 @@ impl Node for IfExpression {
     }
 }
 
+//      match subject { pattern if guard => value, ... }; the first arm whose pattern
+//      matches and whose guard is truthy gives the value
+#[derive(Debug, Clone)]
+pub struct MatchExpression {
+    pub token: Token,       // match
+    pub subject: Box<ExpressionNode>,
+    pub arms: Vec<MatchArm>,
+    pub end: Token,         // }
+}
+
+impl Node for MatchExpression {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span.to(self.end.span)
+    }
+
+    fn print_string(&self) -> String {
+        let mut out = String::from("");
+        let mut arms = vec![];
+
+        for arm in &self.arms {
+            arms.push(arm.print_string());
+        }
+
+        out.push_str("match ");
+        out.push_str(self.subject.print_string().as_str());
+        out.push_str(" { ");
+        out.push_str(arms.join(", ").as_str());
+        out.push_str(" }");
+
+        out
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<ExpressionNode>,
+    pub value: ExpressionNode,
+}
+
+impl MatchArm {
+    pub fn print_string(&self) -> String {
+        let mut out = self.pattern.print_string();
+
+        if let Some(guard) = &self.guard {
+            out.push_str(" if ");
+            out.push_str(guard.print_string().as_str());
+        }
+        out.push_str(" => ");
+        out.push_str(self.value.print_string().as_str());
+
+        out
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Wildcard(Token),                // _
+    Binding(Identifier),            // matches anything and binds it to the name
+    Literal(ExpressionNode),        // a number, string or boolean, compared with `==`
+    Array(Token, Vec<Pattern>),     // an array of exactly this many elements
+    Dictionary(Token, Vec<(ExpressionNode, Pattern)>),  // a dictionary with at least these keys
+}
+
+impl Pattern {
+    // every name the pattern binds, left to right
+    pub fn bindings(&self) -> Vec<&Identifier> {
+        match self {
+            Self::Binding(identifier) => vec![identifier],
+            Self::Array(_, elements) => elements.iter().flat_map(|element| element.bindings()).collect(),
+            Self::Dictionary(_, pairs) => pairs.iter().flat_map(|(_, value)| value.bindings()).collect(),
+            Self::Wildcard(_) | Self::Literal(_) => vec![],
+        }
+    }
+
+    pub fn print_string(&self) -> String {
+        match self {
+            Self::Wildcard(token) => token.literal.clone(),
+            Self::Binding(identifier) => identifier.print_string(),
+            Self::Literal(literal) => literal.print_string(),
+            Self::Array(_, elements) => {
+                let elements: Vec<String> = elements.iter().map(|element| element.print_string()).collect();
+                format!("[{}]", elements.join(", "))
+            }
+            Self::Dictionary(_, pairs) => {
+                let pairs: Vec<String> = pairs
+                    .iter()
+                    .map(|(key, value)| format!("{}:{}", key.print_string(), value.print_string()))
+                    .collect();
+                format!("{{{}}}", pairs.join(", "))
+            }
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct FunctionLiteral {
     pub token: Token,
-    pub parameters: Vec<Identifier>,
+    pub parameters: Vec<Parameter>,
     pub body: BlockStatement,
 }
 
+//      `name`, `name = default` or `...name`; the default is evaluated at each call that leaves
+//      the parameter out, and a rest parameter collects the extra positional arguments
+#[derive(Debug, Default, Clone)]
+pub struct Parameter {
+    pub name: Identifier,
+    pub default: Option<ExpressionNode>,
+    pub rest: bool,
+}
+
+impl Node for Parameter {
+    fn token_literal(&self) -> String {
+        self.name.token_literal()
+    }
+
+    fn span(&self) -> Span {
+        match &self.default {
+            Some(default) => self.name.span().to(default.span()),
+            None => self.name.span(),
+        }
+    }
+
+    fn print_string(&self) -> String {
+        let mut out = String::from("");
+
+        if self.rest {
+            out.push_str("...");
+        }
+        out.push_str(self.name.print_string().as_str());
+        if let Some(default) = &self.default {
+            out.push_str(" = ");
+            out.push_str(default.print_string().as_str());
+        }
+
+        out
+    }
+}
+
 impl Node for FunctionLiteral {
     fn token_literal(&self) -> String {
         self.token.literal.clone()
     }
 
+    fn span(&self) -> Span {
+        self.token.span.to(self.body.span())
+    }
+
     fn print_string(&self) -> String {
         let mut out = String::from("");
         let mut params = vec![];
@@ -340,9 +676,9 @@ impl Node for FunctionLiteral {
         }
 
         out.push_str(self.token_literal().as_str());
-        out.push_str("(");
+        out.push('(');
         out.push_str(params.join(", ").as_str());
-        out.push_str(")");
+        out.push(')');
         out.push_str(self.body.print_string().as_str());
 
         out
@@ -354,6 +690,7 @@ pub struct CallExpression {
     pub token: Token,
     pub function: Box<ExpressionNode>,
     pub arguments: Vec<ExpressionNode>,
+    pub named: Vec<(Identifier, ExpressionNode)>,     // `name: value`, always after the positional arguments
 }
 
 impl Node for CallExpression {
@@ -361,6 +698,14 @@ impl Node for CallExpression {
         self.token.literal.clone()
     }
 
+    fn span(&self) -> Span {
+        match (self.named.last(), self.arguments.last()) {
+            (Some((_, value)), _) => self.function.span().to(value.span()),
+            (None, Some(argument)) => self.function.span().to(argument.span()),
+            (None, None) => self.function.span().to(self.token.span),
+        }
+    }
+
     fn print_string(&self) -> String {
         let mut out = String::from("");
         let mut arguments = vec![];
@@ -368,11 +713,14 @@ impl Node for CallExpression {
         for argument in &self.arguments {
             arguments.push(argument.print_string());
         }
+        for (name, value) in &self.named {
+            arguments.push(format!("{}: {}", name.print_string(), value.print_string()));
+        }
 
         out.push_str(self.function.print_string().as_str());
-        out.push_str("(");
+        out.push('(');
         out.push_str(arguments.join(", ").as_str());
-        out.push_str(")");
+        out.push(')');
 
         out
     }
@@ -389,6 +737,13 @@ impl Node for BlockStatement {
         self.token.literal.clone()
     }
 
+    fn span(&self) -> Span {
+        match self.statements.last() {
+            Some(stmt) => self.token.span.to(stmt.span()),
+            None => self.token.span,
+        }
+    }
+
     fn print_string(&self) -> String {
         let mut out = String::from("");
 
@@ -401,6 +756,102 @@ impl Node for BlockStatement {
 }
 
 
+#[derive(Debug, Default, Clone)]
+pub struct WhileStatement {
+    pub token: Token,
+    pub condition: ExpressionNode,
+    pub body: BlockStatement,
+}
+
+impl Node for WhileStatement {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span.to(self.body.span())
+    }
+
+    fn print_string(&self) -> String {
+        let mut out = String::from("");
+
+        out.push_str("while");
+        out.push_str(self.condition.print_string().as_str());
+        out.push(' ');
+        out.push_str(self.body.print_string().as_str());
+
+        out
+    }
+}
+
+//      for (variable in iterable) { body }
+#[derive(Debug, Default, Clone)]
+pub struct ForStatement {
+    pub token: Token,
+    pub variable: Identifier,
+    pub iterable: ExpressionNode,
+    pub body: BlockStatement,
+}
+
+impl Node for ForStatement {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span.to(self.body.span())
+    }
+
+    fn print_string(&self) -> String {
+        let mut out = String::from("");
+
+        out.push_str("for(");
+        out.push_str(self.variable.print_string().as_str());
+        out.push_str(" in ");
+        out.push_str(self.iterable.print_string().as_str());
+        out.push_str(") ");
+        out.push_str(self.body.print_string().as_str());
+
+        out
+    }
+}
+
+//      function name(parameters) { body }, bound before the rest of its program or block runs
+#[derive(Debug, Clone)]
+pub struct FunctionDeclaration {
+    pub token: Token,
+    pub name: Identifier,
+    pub function: FunctionLiteral,
+}
+
+impl Node for FunctionDeclaration {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span.to(self.function.span())
+    }
+
+    fn print_string(&self) -> String {
+        let mut out = String::from("");
+        let mut params = vec![];
+
+        for param in &self.function.parameters {
+            params.push(param.print_string());
+        }
+
+        out.push_str(self.token_literal().as_str());
+        out.push(' ');
+        out.push_str(self.name.print_string().as_str());
+        out.push('(');
+        out.push_str(params.join(", ").as_str());
+        out.push(')');
+        out.push_str(self.function.body.print_string().as_str());
+
+        out
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct StringLiteral {
@@ -413,11 +864,57 @@ impl Node for StringLiteral {
         self.token.literal.clone()
     }
 
+    fn span(&self) -> Span {
+        self.token.span
+    }
+
     fn print_string(&self) -> String {
         self.token_literal()
     }
 }
 
+#[derive(Debug, Clone)]
+pub enum StringPart {
+    Literal(String),
+    Expression(Box<ExpressionNode>),
+}
+
+//      `"Hello {name}!"`: the text around each embedded expression is kept as a literal part
+#[derive(Debug, Clone)]
+pub struct InterpolatedString {
+    pub token: Token,       // InterpolationStart
+    pub parts: Vec<StringPart>,
+    pub end: Token,         // InterpolationEnd
+}
+
+impl Node for InterpolatedString {
+    fn token_literal(&self) -> String {
+        self.token.literal.clone()
+    }
+
+    fn span(&self) -> Span {
+        self.token.span.to(self.end.span)
+    }
+
+    fn print_string(&self) -> String {
+        let mut out = String::from("\"");
+
+        for part in &self.parts {
+            match part {
+                StringPart::Literal(text) => out.push_str(text.replace('{', "\\{").replace('}', "\\}").as_str()),
+                StringPart::Expression(expression) => {
+                    out.push('{');
+                    out.push_str(expression.print_string().as_str());
+                    out.push('}');
+                }
+            }
+        }
+
+        out.push('"');
+        out
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ArrayLiteral {
     pub token: Token,       // [
@@ -429,6 +926,13 @@ impl Node for ArrayLiteral {
         self.token.literal.clone()
     }
 
+    fn span(&self) -> Span {
+        match self.elements.last() {
+            Some(element) => self.token.span.to(element.span()),
+            None => self.token.span,
+        }
+    }
+
     fn print_string(&self) -> String {
         let mut out = String::from("");
         let mut elements = vec![];
@@ -437,9 +941,9 @@ impl Node for ArrayLiteral {
             elements.push(el.print_string());
         }
 
-        out.push_str("[");
+        out.push('[');
         out.push_str(elements.join(", ").as_str());
-        out.push_str("]");
+        out.push(']');
 
         out
     }
@@ -457,12 +961,16 @@ impl Node for IndexExpression {
         self.token.literal.clone()
     }
 
+    fn span(&self) -> Span {
+        self.left.span().to(self.index.span())
+    }
+
     fn print_string(&self) -> String {
         let mut out = String::from("");
 
-        out.push_str("(");
+        out.push('(');
         out.push_str(self.left.print_string().as_str());
-        out.push_str("[");
+        out.push('[');
         out.push_str(self.index.print_string().as_str());
         out.push_str("])");
 
@@ -481,6 +989,13 @@ impl Node for HashLiteral {
         self.token.literal.clone()
     }
 
+    fn span(&self) -> Span {
+        match self.pairs.last() {
+            Some((_, value)) => self.token.span.to(value.span()),
+            None => self.token.span,
+        }
+    }
+
     fn print_string(&self) -> String {
         let mut out = String::from("");
         let mut pairs = vec![];
@@ -489,9 +1004,9 @@ impl Node for HashLiteral {
             pairs.push(format!("{}:{}", key.print_string(), value.print_string()))
         }
 
-        out.push_str("{");
+        out.push('{');
         out.push_str(pairs.join(", ").as_str());
-        out.push_str("}");
+        out.push('}');
 
         out
     }
@@ -509,6 +1024,13 @@ impl Node for DictLiteral {
         self.token.literal.clone()
     }
 
+    fn span(&self) -> Span {
+        match self.pairs.last() {
+            Some((_, value)) => self.token.span.to(value.span()),
+            None => self.token.span,
+        }
+    }
+
     fn print_string(&self) -> String {
         let mut out = String::from("");
         let mut pairs = vec![];
@@ -517,9 +1039,9 @@ impl Node for DictLiteral {
             pairs.push(format!("{}:{}", key.print_string(), value.print_string()))
         }
 
-        out.push_str("{");
+        out.push('{');
         out.push_str(pairs.join(", ").as_str());
-        out.push_str("}");
+        out.push('}');
 
         out
     }
@@ -542,11 +1064,13 @@ mod test {
                 token: Token {
                     kind: TokenKind::Say,
                     literal: String::from("say"),
+                    ..Default::default()
                 },
                 name: Identifier {
                     token: Token {
                         kind: TokenKind::Ident,
                         literal: String::from("myName"),
+                        ..Default::default()
                     },
                     value: String::from("myName"),
                 },
@@ -554,6 +1078,7 @@ mod test {
                     token: Token {
                         kind: TokenKind::Ident,
                         literal: String::from("anotherName"),
+                        ..Default::default()
                     },
                     value: String::from("anotherName"),
                 })),
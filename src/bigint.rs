@@ -0,0 +1,409 @@
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    ops::{Add, Mul, Neg, Sub},
+    str::FromStr,
+};
+
+const BASE: u64 = 1 << 32;
+
+//      arbitrary-precision integer: a sign and the magnitude in base 2^32, least
+//      significant limb first. The magnitude never ends in a zero limb, and zero is
+//      always non-negative, so equal values have equal representations
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> BigInt {
+        BigInt::default()
+    }
+
+    pub fn from_i64(value: i64) -> BigInt {
+        BigInt::from_magnitude(value < 0, split_u64(value.unsigned_abs()))
+    }
+
+    fn from_magnitude(negative: bool, mut magnitude: Vec<u32>) -> BigInt {
+        while magnitude.last() == Some(&0) {
+            magnitude.pop();
+        }
+        BigInt {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+        let magnitude = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0_u64, |acc, limb| (acc << 32) | *limb as u64);
+
+        if self.negative {
+            if magnitude <= i64::MAX as u64 + 1 {
+                return Some((magnitude as i64).wrapping_neg());
+            }
+            None
+        } else {
+            i64::try_from(magnitude).ok()
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0.0, |acc, limb| acc * BASE as f64 + *limb as f64);
+        if self.negative {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    // square-and-multiply
+    pub fn pow(&self, mut exponent: u32) -> BigInt {
+        let mut result = BigInt::from_i64(1);
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = &result * &base;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = &base * &base;
+            }
+        }
+        result
+    }
+
+    // quotient and remainder, truncated toward zero like `i64` division; None for a zero divisor
+    pub fn checked_div_rem(&self, divisor: &BigInt) -> Option<(BigInt, BigInt)> {
+        if divisor.is_zero() {
+            return None;
+        }
+        let (quotient, remainder) = div_rem_magnitude(&self.magnitude, &divisor.magnitude);
+        Some((
+            BigInt::from_magnitude(self.negative != divisor.negative, quotient),
+            BigInt::from_magnitude(self.negative, remainder),
+        ))
+    }
+}
+
+impl Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_magnitude(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_magnitude(self.negative, add_magnitude(&self.magnitude, &other.magnitude));
+        }
+
+        // different signs: subtract the smaller magnitude from the larger one
+        match cmp_magnitude(&self.magnitude, &other.magnitude) {
+            Ordering::Less => {
+                BigInt::from_magnitude(other.negative, sub_magnitude(&other.magnitude, &self.magnitude))
+            }
+            _ => BigInt::from_magnitude(self.negative, sub_magnitude(&self.magnitude, &other.magnitude)),
+        }
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &(-other)
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        let mut product = vec![0_u32; self.magnitude.len() + other.magnitude.len()];
+
+        for (i, a) in self.magnitude.iter().enumerate() {
+            let mut carry = 0_u64;
+            for (j, b) in other.magnitude.iter().enumerate() {
+                let current = product[i + j] as u64 + *a as u64 * *b as u64 + carry;
+                product[i + j] = current as u32;
+                carry = current >> 32;
+            }
+            product[i + other.magnitude.len()] = carry as u32;
+        }
+
+        BigInt::from_magnitude(self.negative != other.negative, product)
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.magnitude, &other.magnitude),
+            (true, true) => cmp_magnitude(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromStr for BigInt {
+    type Err = String;
+
+    // decimal digits with an optional leading `-`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s),
+        };
+        if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(format!("invalid integer literal: {}", s));
+        }
+
+        // fold in nine decimal digits at a time, most significant first
+        let mut magnitude: Vec<u32> = vec![];
+        let first_chunk = match digits.len() % 9 {
+            0 => 9,
+            len => len,
+        };
+        let mut start = 0;
+        let mut end = first_chunk;
+        while start < digits.len() {
+            let chunk: u32 = digits[start..end].parse().expect("chunk holds only digits");
+            let scale = 10_u64.pow((end - start) as u32);
+
+            let mut carry = chunk as u64;
+            for limb in magnitude.iter_mut() {
+                let current = *limb as u64 * scale + carry;
+                *limb = current as u32;
+                carry = current >> 32;
+            }
+            while carry != 0 {
+                magnitude.push(carry as u32);
+                carry >>= 32;
+            }
+
+            start = end;
+            end += 9;
+        }
+
+        Ok(BigInt::from_magnitude(negative, magnitude))
+    }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // peel off nine decimal digits at a time, least significant first
+        let mut chunks = vec![];
+        let mut magnitude = self.magnitude.clone();
+        while !magnitude.is_empty() {
+            let (quotient, remainder) = div_rem_small(&magnitude, 1_000_000_000);
+            chunks.push(remainder);
+            magnitude = quotient;
+        }
+
+        let mut out = String::new();
+        if self.negative {
+            out.push('-');
+        }
+        out.push_str(chunks.last().unwrap().to_string().as_str());
+        for chunk in chunks.iter().rev().skip(1) {
+            out.push_str(format!("{:09}", chunk).as_str());
+        }
+        write!(f, "{}", out)
+    }
+}
+
+fn split_u64(value: u64) -> Vec<u32> {
+    vec![value as u32, (value >> 32) as u32]
+}
+
+fn trim(mut magnitude: Vec<u32>) -> Vec<u32> {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+    magnitude
+}
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut sum = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0_u64;
+
+    for i in 0..a.len().max(b.len()) {
+        let current = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        sum.push(current as u32);
+        carry = current >> 32;
+    }
+    sum.push(carry as u32);
+    trim(sum)
+}
+
+// requires a >= b
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = 0_i64;
+
+    for (i, limb) in a.iter().enumerate() {
+        let mut current = *limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = 0;
+        if current < 0 {
+            current += BASE as i64;
+            borrow = 1;
+        }
+        difference.push(current as u32);
+    }
+    trim(difference)
+}
+
+fn div_rem_small(a: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0_u32; a.len()];
+    let mut remainder = 0_u64;
+
+    for i in (0..a.len()).rev() {
+        let current = (remainder << 32) | a[i] as u64;
+        quotient[i] = (current / divisor as u64) as u32;
+        remainder = current % divisor as u64;
+    }
+    (trim(quotient), remainder as u32)
+}
+
+// schoolbook binary long division; divisor must be non-zero
+fn div_rem_magnitude(a: &[u32], divisor: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_magnitude(a, divisor) == Ordering::Less {
+        return (vec![], a.to_vec());
+    }
+    if divisor.len() == 1 {
+        let (quotient, remainder) = div_rem_small(a, divisor[0]);
+        return (quotient, trim(vec![remainder]));
+    }
+
+    let mut quotient = vec![0_u32; a.len()];
+    let mut remainder: Vec<u32> = vec![];
+
+    for bit in (0..a.len() * 32).rev() {
+        // remainder = remainder * 2 + next bit of a
+        let mut carry = (a[bit / 32] >> (bit % 32)) & 1;
+        for limb in remainder.iter_mut() {
+            let next = *limb >> 31;
+            *limb = (*limb << 1) | carry;
+            carry = next;
+        }
+        if carry != 0 {
+            remainder.push(carry);
+        }
+
+        if cmp_magnitude(&remainder, divisor) != Ordering::Less {
+            remainder = sub_magnitude(&remainder, divisor);
+            quotient[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+    (trim(quotient), remainder)
+}
+
+#[cfg(test)]
+mod test {
+    use super::BigInt;
+
+    fn big(value: i64) -> BigInt {
+        BigInt::from_i64(value)
+    }
+
+    #[test]
+    fn test_i64_round_trip() {
+        for value in [0, 1, -1, 42, u32::MAX as i64, -(u32::MAX as i64) - 1, i64::MAX, i64::MIN] {
+            assert_eq!(big(value).to_i64(), Some(value));
+            assert_eq!(big(value).to_string(), value.to_string());
+        }
+
+        assert_eq!((&big(i64::MAX) + &big(1)).to_i64(), None);
+        assert_eq!((&big(i64::MIN) - &big(1)).to_i64(), None);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let max = big(i64::MAX);
+
+        assert_eq!((&max + &big(1)).to_string(), "9223372036854775808");
+        assert_eq!((&max * &max).to_string(), "85070591730234615847396907784232501249");
+        assert_eq!((&big(i64::MIN) * &big(-1)).to_string(), "9223372036854775808");
+        assert_eq!((&big(5) - &big(8)).to_string(), "-3");
+        assert_eq!((&big(-5) + &big(5)), BigInt::zero());
+        assert!(!(&big(-5) + &big(5)).is_negative());
+
+        let square = &max * &max;
+        let (quotient, remainder) = square.checked_div_rem(&max).unwrap();
+        assert_eq!(quotient, max);
+        assert!(remainder.is_zero());
+
+        let (quotient, remainder) = (&square + &big(-7)).checked_div_rem(&big(-1_000_000_007)).unwrap();
+        assert_eq!(quotient.to_string(), "-85070591134740477904213562454");
+        assert_eq!(remainder.to_string(), "737564064");
+
+        assert_eq!(big(7).checked_div_rem(&BigInt::zero()), None);
+
+        assert_eq!(big(2).pow(100).to_string(), "1267650600228229401496703205376");
+        assert_eq!(big(-3).pow(3), big(-27));
+        assert_eq!(big(12345).pow(0), big(1));
+    }
+
+    #[test]
+    fn test_from_str() {
+        for text in ["0", "7", "-42", "4294967296", "9223372036854775807", "-9223372036854775808",
+            "123456789012345678901234567890", "-1000000000000000000000000000"] {
+            assert_eq!(text.parse::<BigInt>().unwrap().to_string(), text);
+        }
+
+        assert_eq!("000123".parse::<BigInt>(), Ok(big(123)));
+        assert_eq!("-0".parse::<BigInt>(), Ok(BigInt::zero()));
+        assert!("".parse::<BigInt>().is_err());
+        assert!("12a".parse::<BigInt>().is_err());
+    }
+
+    #[test]
+    fn test_ordering() {
+        let huge = &big(i64::MAX) * &big(4);
+        assert!(huge > big(i64::MAX));
+        assert!(-&huge < big(i64::MIN));
+        assert!(big(-2) < big(1));
+        assert_eq!(huge.to_f64(), 4.0 * i64::MAX as f64);
+    }
+}
@@ -1,22 +1,65 @@
+use std::cmp::Ordering;
 
-use crate::{evaluator::NULL, object::Object};
+use crate::{bigint::BigInt, evaluator::{Evaluator, NULL}, object::{BuiltinFunction, CallContext, Object}};
+
+// the longest string, in bytes, `repeat` and the padding builtins will build
+const MAX_STRING_LENGTH: usize = 1 << 30;
+// the most elements `range` will build
+const MAX_RANGE_LENGTH: i128 = 1 << 24;
 
 pub struct Builtin ;
 
 impl Builtin {
     pub fn all_builtins(&self) -> Vec<(String, Object)> {
         vec![
-            (String::from("len"), Object::Builtin(builtin_len)),
-            (String::from("first"), Object::Builtin(builtin_first)),
-            (String::from("last"), Object::Builtin(builtin_last)),
-            (String::from("last"), Object::Builtin(builtin_rest)),
-            (String::from("push"), Object::Builtin(builtin_push)),
-            (String::from("tellme"), Object::Builtin(builtin_tellme)),
+            (String::from("len"), plain(builtin_len)),
+            (String::from("first"), plain(builtin_first)),
+            (String::from("last"), plain(builtin_last)),
+            (String::from("rest"), plain(builtin_rest)),
+            (String::from("push"), plain(builtin_push)),
+            (String::from("tellme"), plain(builtin_tellme)),
+            (String::from("map"), Object::Builtin(BuiltinFunction::new(builtin_map))),
+            (String::from("filter"), Object::Builtin(BuiltinFunction::new(builtin_filter))),
+            (String::from("reduce"), Object::Builtin(BuiltinFunction::new(builtin_reduce))),
+            (String::from("sort_by"), Object::Builtin(BuiltinFunction::new(builtin_sort_by))),
+            (String::from("sort"), Object::Builtin(BuiltinFunction::new(builtin_sort))),
+            (String::from("reverse"), plain(builtin_reverse)),
+            (String::from("zip"), plain(builtin_zip)),
+            (String::from("enumerate"), plain(builtin_enumerate)),
+            (String::from("range"), plain(builtin_range)),
+            (String::from("contains"), plain(builtin_contains)),
+            (String::from("index_of"), plain(builtin_index_of)),
+            (String::from("slice"), plain(builtin_slice)),
+            (String::from("concat"), plain(builtin_concat)),
+            (String::from("flatten"), plain(builtin_flatten)),
+            (String::from("unique"), plain(builtin_unique)),
+            (String::from("split"), plain(builtin_split)),
+            (String::from("join"), plain(builtin_join)),
+            (String::from("trim"), plain(builtin_trim)),
+            (String::from("upper"), plain(builtin_upper)),
+            (String::from("lower"), plain(builtin_lower)),
+            (String::from("replace"), plain(builtin_replace)),
+            (String::from("starts_with"), plain(builtin_starts_with)),
+            (String::from("ends_with"), plain(builtin_ends_with)),
+            (String::from("find"), plain(builtin_find)),
+            (String::from("substr"), plain(builtin_substr)),
+            (String::from("chars"), plain(builtin_chars)),
+            (String::from("repeat"), plain(builtin_repeat)),
+            (String::from("pad_left"), plain(builtin_pad_left)),
+            (String::from("pad_right"), plain(builtin_pad_right)),
+            (String::from("to_int"), plain(builtin_to_int)),
+            (String::from("to_string"), plain(builtin_to_string)),
+            (String::from("format"), plain(builtin_format)),
             
             ]
     }
 }
 
+// a builtin that does not need the interpreter
+fn plain(function: fn(Vec<Object>) -> Object) -> Object {
+    Object::Builtin(BuiltinFunction::new(move |_, arguments| function(arguments)))
+}
+
 fn builtin_len(arguments: Vec<Object>) -> Object {
     if arguments.len() != 1 {
         return Object::Error(format!(
@@ -24,9 +67,9 @@ fn builtin_len(arguments: Vec<Object>) -> Object {
             arguments.len()
         ));
     }
-    return match &arguments[0] {
-        Object::StringObject(string_literal) => Object::Integer(string_literal.len() as i64),
-        Object::Array(array) => Object::Integer(array.len() as i64),
+    match &arguments[0] {
+        Object::StringObject(string_literal) => Object::Integer(string_literal.chars().count() as i64),
+        Object::Array(array) => Object::Integer(array.borrow().len() as i64),
         other => Object::Error(format!(
             "argument to 'len' not supported, got={}",
             other.object_type()
@@ -47,7 +90,8 @@ fn builtin_first(arguments: Vec<Object>) -> Object {
     }
 
     if let Object::Array(array) = &arguments[0] {
-        if array.len() > 0 {
+        let array = array.borrow();
+        if !array.is_empty() {
             return array[0].clone();
         }
     }
@@ -67,7 +111,8 @@ fn builtin_last(arguments: Vec<Object>) -> Object {
     }
 
     if let Object::Array(array) = &arguments[0] {
-        if array.len() > 0 {
+        let array = array.borrow();
+        if !array.is_empty() {
             return array[array.len() - 1].clone();
         }
     }
@@ -84,15 +129,16 @@ fn builtin_rest(arguments: Vec<Object>) -> Object {
 
     if arguments[0].object_type() != "ARRAY" {
         return Object::Error(format!(
-            "argument to `first` must be ARRAY, got={}",
+            "argument to `rest` must be ARRAY, got={}",
             arguments[0].object_type()
         ));
     }
 
     if let Object::Array(array) = &arguments[0] {
-        if array.len() > 0 {
+        let array = array.borrow();
+        if !array.is_empty() {
             let new_elements = array[1..].to_vec();
-            return Object::Array(new_elements);
+            return Object::new_array(new_elements);
         }
     }
     NULL
@@ -108,17 +154,16 @@ fn builtin_push(arguments: Vec<Object>) -> Object {
 
     if arguments[0].object_type() != "ARRAY" {
         return Object::Error(format!(
-            "argument to `first` must be ARRAY, got={}",
+            "argument to `push` must be ARRAY, got={}",
             arguments[0].object_type()
         ));
     }
 
+    // `push` returns a new array and leaves its argument untouched
     if let Object::Array(array) = &arguments[0] {
-        if array.len() > 0 {
-            let mut new_elements = array.clone();
-            new_elements.push(arguments[1].clone());
-            return Object::Array(new_elements);
-        }
+        let mut new_elements = array.borrow().clone();
+        new_elements.push(arguments[1].clone());
+        return Object::new_array(new_elements);
     }
     NULL
 }
@@ -129,4 +174,698 @@ fn builtin_tellme(arguments: Vec<Object>) -> Object {
     }
     NULL
 }
-  
\ No newline at end of file
+  
+// the elements of the array argument of `name`, copied so callbacks may change the array
+fn array_argument(name: &str, argument: &Object) -> Result<Vec<Object>, String> {
+    match argument {
+        Object::Array(array) => Ok(array.borrow().clone()),
+        other => Err(format!(
+            "argument to `{}` must be ARRAY, got={}",
+            name,
+            other.object_type()
+        )),
+    }
+}
+
+fn builtin_map(context: &mut dyn CallContext, arguments: Vec<Object>) -> Object {
+    if arguments.len() != 2 {
+        return Object::Error(format!(
+            "wrong number of arguments. got={}, want=2",
+            arguments.len()
+        ));
+    }
+    let elements = match array_argument("map", &arguments[0]) {
+        Ok(elements) => elements,
+        Err(message) => return Object::Error(message),
+    };
+
+    let mut mapped = Vec::with_capacity(elements.len());
+    for element in elements {
+        let value = context.apply(&arguments[1], vec![element]);
+        if let Object::Error(_) = value {
+            return value;
+        }
+        mapped.push(value);
+    }
+    Object::new_array(mapped)
+}
+
+fn builtin_filter(context: &mut dyn CallContext, arguments: Vec<Object>) -> Object {
+    if arguments.len() != 2 {
+        return Object::Error(format!(
+            "wrong number of arguments. got={}, want=2",
+            arguments.len()
+        ));
+    }
+    let elements = match array_argument("filter", &arguments[0]) {
+        Ok(elements) => elements,
+        Err(message) => return Object::Error(message),
+    };
+
+    let mut kept = vec![];
+    for element in elements {
+        let keep = context.apply(&arguments[1], vec![element.clone()]);
+        if let Object::Error(_) = keep {
+            return keep;
+        }
+        if Evaluator::is_truthy(keep) {
+            kept.push(element);
+        }
+    }
+    Object::new_array(kept)
+}
+
+// `reduce(array, function)` starts from the first element, `reduce(array, function, initial)`
+// from `initial`
+fn builtin_reduce(context: &mut dyn CallContext, arguments: Vec<Object>) -> Object {
+    if arguments.len() != 2 && arguments.len() != 3 {
+        return Object::Error(format!(
+            "wrong number of arguments. got={}, want=2 or 3",
+            arguments.len()
+        ));
+    }
+    let mut elements = match array_argument("reduce", &arguments[0]) {
+        Ok(elements) => elements.into_iter(),
+        Err(message) => return Object::Error(message),
+    };
+
+    let mut accumulator = match arguments.get(2).cloned().or_else(|| elements.next()) {
+        Some(initial) => initial,
+        None => return Object::Error(String::from("reduce of an empty array with no initial value")),
+    };
+    for element in elements {
+        accumulator = context.apply(&arguments[1], vec![accumulator, element]);
+        if let Object::Error(_) = accumulator {
+            return accumulator;
+        }
+    }
+    accumulator
+}
+
+// a sorted copy of the array, ordered by the key the function gives each element.
+// Elements with equal keys keep their order
+fn builtin_sort_by(context: &mut dyn CallContext, arguments: Vec<Object>) -> Object {
+    if arguments.len() != 2 {
+        return Object::Error(format!(
+            "wrong number of arguments. got={}, want=2",
+            arguments.len()
+        ));
+    }
+    let elements = match array_argument("sort_by", &arguments[0]) {
+        Ok(elements) => elements,
+        Err(message) => return Object::Error(message),
+    };
+
+    let mut keyed = Vec::with_capacity(elements.len());
+    for element in elements {
+        let key = context.apply(&arguments[1], vec![element.clone()]);
+        if let Object::Error(_) = key {
+            return key;
+        }
+        keyed.push((key, element));
+    }
+
+    let sorted = merge_sort(keyed, &mut |(left, _), (right, _)| {
+        left.partial_cmp(right)
+            .ok_or_else(|| format!("cannot compare {} with {}", left.object_type(), right.object_type()))
+    });
+    match sorted {
+        Ok(keyed) => Object::new_array(keyed.into_iter().map(|(_, element)| element).collect()),
+        Err(message) => Object::Error(message),
+    }
+}
+
+fn wrong_number_of_arguments(got: usize, want: &str) -> Object {
+    Object::Error(arity_message(got, want))
+}
+
+fn arity_message(got: usize, want: &str) -> String {
+    format!("wrong number of arguments. got={}, want={}", got, want)
+}
+
+fn integer_argument(name: &str, argument: &Object) -> Result<i64, String> {
+    match argument {
+        Object::Integer(value) => Ok(*value),
+        other => Err(format!(
+            "argument to `{}` must be INTEGER, got={}",
+            name,
+            other.object_type()
+        )),
+    }
+}
+
+// `sort(array)` orders the elements as `<` does, `sort(array, compare)` by a function that
+// gives a negative number, zero or a positive number for each pair. Both keep equal
+// elements in their order
+fn builtin_sort(context: &mut dyn CallContext, arguments: Vec<Object>) -> Object {
+    if arguments.len() != 1 && arguments.len() != 2 {
+        return wrong_number_of_arguments(arguments.len(), "1 or 2");
+    }
+    let elements = match array_argument("sort", &arguments[0]) {
+        Ok(elements) => elements,
+        Err(message) => return Object::Error(message),
+    };
+
+    let sorted = merge_sort(elements, &mut |left, right| match arguments.get(1) {
+        None => left.partial_cmp(right).ok_or_else(|| {
+            format!("cannot compare {} with {}", left.object_type(), right.object_type())
+        }),
+        Some(compare) => match context.apply(compare, vec![left.clone(), right.clone()]) {
+            Object::Integer(value) => Ok(value.cmp(&0)),
+            Object::Float(value) => Ok(value.partial_cmp(&0.0).unwrap_or(Ordering::Equal)),
+            Object::Error(message) => Err(message),
+            other => Err(format!("comparator of `sort` must return a number, got={}", other.object_type())),
+        },
+    });
+
+    match sorted {
+        Ok(elements) => Object::new_array(elements),
+        Err(message) => Object::Error(message),
+    }
+}
+
+// a stable merge sort that stops at the first error. Unlike `slice::sort_by` it accepts a
+// comparison that is not a total order, which a program's comparator may well not be
+fn merge_sort<T>(
+    mut elements: Vec<T>,
+    compare: &mut impl FnMut(&T, &T) -> Result<Ordering, String>,
+) -> Result<Vec<T>, String> {
+    if elements.len() < 2 {
+        return Ok(elements);
+    }
+    let right = elements.split_off(elements.len() / 2);
+    let mut left = merge_sort(elements, compare)?.into_iter().peekable();
+    let mut right = merge_sort(right, compare)?.into_iter().peekable();
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    while let (Some(first), Some(second)) = (left.peek(), right.peek()) {
+        // on a tie the left element goes first, which keeps equal elements in their order
+        let next = match compare(first, second)? {
+            Ordering::Greater => right.next(),
+            _ => left.next(),
+        };
+        merged.extend(next);
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+fn builtin_reverse(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 1 {
+        return wrong_number_of_arguments(arguments.len(), "1");
+    }
+    match array_argument("reverse", &arguments[0]) {
+        Ok(elements) => Object::new_array(elements.into_iter().rev().collect()),
+        Err(message) => Object::Error(message),
+    }
+}
+
+// pairs up the elements of two arrays, as long as the shorter one
+fn builtin_zip(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 2 {
+        return wrong_number_of_arguments(arguments.len(), "2");
+    }
+    let (left, right) = match (array_argument("zip", &arguments[0]), array_argument("zip", &arguments[1])) {
+        (Ok(left), Ok(right)) => (left, right),
+        (Err(message), _) | (_, Err(message)) => return Object::Error(message),
+    };
+
+    let pairs = left
+        .into_iter()
+        .zip(right)
+        .map(|(left, right)| Object::new_array(vec![left, right]))
+        .collect();
+    Object::new_array(pairs)
+}
+
+fn builtin_enumerate(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 1 {
+        return wrong_number_of_arguments(arguments.len(), "1");
+    }
+    let elements = match array_argument("enumerate", &arguments[0]) {
+        Ok(elements) => elements,
+        Err(message) => return Object::Error(message),
+    };
+
+    let pairs = elements
+        .into_iter()
+        .enumerate()
+        .map(|(idx, element)| Object::new_array(vec![Object::Integer(idx as i64), element]))
+        .collect();
+    Object::new_array(pairs)
+}
+
+// `range(end)`, `range(start, end)` or `range(start, end, step)`; `end` is left out
+fn builtin_range(arguments: Vec<Object>) -> Object {
+    if arguments.is_empty() || arguments.len() > 3 {
+        return wrong_number_of_arguments(arguments.len(), "1 to 3");
+    }
+    let mut bounds = vec![];
+    for argument in &arguments {
+        match integer_argument("range", argument) {
+            Ok(value) => bounds.push(value),
+            Err(message) => return Object::Error(message),
+        }
+    }
+    let (start, end, step) = match bounds.as_slice() {
+        [end] => (0, *end, 1),
+        [start, end] => (*start, *end, 1),
+        [start, end, step] => (*start, *end, *step),
+        _ => unreachable!(),
+    };
+    if step == 0 {
+        return Object::Error(String::from("step of `range` must not be zero"));
+    }
+    // the number of steps, rounded up, that fit between the bounds
+    let (span, stride) = (end as i128 - start as i128, step as i128);
+    let count = if span.signum() == stride.signum() { (span.abs() + stride.abs() - 1) / stride.abs() } else { 0 };
+    if count > MAX_RANGE_LENGTH {
+        return Object::Error(format!("result of `range` is too long: {} elements", count));
+    }
+
+    let mut elements = Vec::with_capacity(count as usize);
+    let mut current = start;
+    while (step > 0 && current < end) || (step < 0 && current > end) {
+        elements.push(Object::Integer(current));
+        current = match current.checked_add(step) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+    Object::new_array(elements)
+}
+
+fn builtin_contains(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 2 {
+        return wrong_number_of_arguments(arguments.len(), "2");
+    }
+    match array_argument("contains", &arguments[0]) {
+        Ok(elements) => Object::Boolean(elements.contains(&arguments[1])),
+        Err(message) => Object::Error(message),
+    }
+}
+
+// the index of the first element equal to the value, or -1
+fn builtin_index_of(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 2 {
+        return wrong_number_of_arguments(arguments.len(), "2");
+    }
+    match array_argument("index_of", &arguments[0]) {
+        Ok(elements) => {
+            let idx = elements.iter().position(|element| *element == arguments[1]);
+            Object::Integer(idx.map_or(-1, |idx| idx as i64))
+        }
+        Err(message) => Object::Error(message),
+    }
+}
+
+// `slice(array, start)` or `slice(array, start, end)`. Negative indices count from the end,
+// and indices past either end are clamped
+fn builtin_slice(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 2 && arguments.len() != 3 {
+        return wrong_number_of_arguments(arguments.len(), "2 or 3");
+    }
+    let elements = match array_argument("slice", &arguments[0]) {
+        Ok(elements) => elements,
+        Err(message) => return Object::Error(message),
+    };
+
+    let len = elements.len() as i64;
+    let mut bounds = vec![];
+    for argument in &arguments[1..] {
+        match integer_argument("slice", argument) {
+            Ok(idx) if idx < 0 => bounds.push((len + idx).max(0)),
+            Ok(idx) => bounds.push(idx.min(len)),
+            Err(message) => return Object::Error(message),
+        }
+    }
+    let start = bounds[0] as usize;
+    let end = bounds.get(1).map_or(elements.len(), |end| *end as usize);
+
+    if start >= end {
+        return Object::new_array(vec![]);
+    }
+    Object::new_array(elements[start..end].to_vec())
+}
+
+// joins any number of arrays into a new one
+fn builtin_concat(arguments: Vec<Object>) -> Object {
+    let mut joined = vec![];
+    for argument in &arguments {
+        match array_argument("concat", argument) {
+            Ok(elements) => joined.extend(elements),
+            Err(message) => return Object::Error(message),
+        }
+    }
+    Object::new_array(joined)
+}
+
+// takes the elements of nested arrays one level up, other elements stay as they are
+fn builtin_flatten(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 1 {
+        return wrong_number_of_arguments(arguments.len(), "1");
+    }
+    let elements = match array_argument("flatten", &arguments[0]) {
+        Ok(elements) => elements,
+        Err(message) => return Object::Error(message),
+    };
+
+    let mut flat = vec![];
+    for element in elements {
+        match element {
+            Object::Array(inner) => flat.extend(inner.borrow().iter().cloned()),
+            other => flat.push(other),
+        }
+    }
+    Object::new_array(flat)
+}
+
+// the elements without the ones equal to an earlier element
+fn builtin_unique(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 1 {
+        return wrong_number_of_arguments(arguments.len(), "1");
+    }
+    let elements = match array_argument("unique", &arguments[0]) {
+        Ok(elements) => elements,
+        Err(message) => return Object::Error(message),
+    };
+
+    let mut seen: Vec<Object> = vec![];
+    for element in elements {
+        if !seen.contains(&element) {
+            seen.push(element);
+        }
+    }
+    Object::new_array(seen)
+}
+
+// strings are indexed and measured in characters, not bytes
+
+fn string_argument(name: &str, argument: &Object) -> Result<String, String> {
+    match argument {
+        Object::StringObject(string) => Ok(string.clone()),
+        other => Err(format!(
+            "argument to `{}` must be STRING, got={}",
+            name,
+            other.object_type()
+        )),
+    }
+}
+
+// the string arguments of a builtin that takes nothing else
+fn string_arguments(name: &str, arguments: &[Object], want: usize) -> Result<Vec<String>, String> {
+    if arguments.len() != want {
+        return Err(arity_message(arguments.len(), &want.to_string()));
+    }
+    arguments.iter().map(|argument| string_argument(name, argument)).collect()
+}
+
+fn strings_to_array<'a>(strings: impl Iterator<Item = &'a str>) -> Object {
+    Object::new_array(strings.map(|string| Object::StringObject(string.to_string())).collect())
+}
+
+// `split(string)` splits at runs of whitespace, `split(string, separator)` at every separator
+fn builtin_split(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 1 && arguments.len() != 2 {
+        return wrong_number_of_arguments(arguments.len(), "1 or 2");
+    }
+    let strings = match string_arguments("split", &arguments, arguments.len()) {
+        Ok(strings) => strings,
+        Err(message) => return Object::Error(message),
+    };
+
+    match strings.get(1).map(String::as_str) {
+        None => strings_to_array(strings[0].split_whitespace()),
+        Some("") => Object::Error(String::from("separator of `split` must not be empty, use `chars` instead")),
+        Some(separator) => strings_to_array(strings[0].split(separator)),
+    }
+}
+
+// `join(array)` or `join(array, separator)`; elements are written as `tellme` writes them
+fn builtin_join(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 1 && arguments.len() != 2 {
+        return wrong_number_of_arguments(arguments.len(), "1 or 2");
+    }
+    let elements = match array_argument("join", &arguments[0]) {
+        Ok(elements) => elements,
+        Err(message) => return Object::Error(message),
+    };
+    let separator = match arguments.get(1).map(|separator| string_argument("join", separator)) {
+        None => String::new(),
+        Some(Ok(separator)) => separator,
+        Some(Err(message)) => return Object::Error(message),
+    };
+
+    let parts: Vec<String> = elements.iter().map(|element| element.to_string()).collect();
+    Object::StringObject(parts.join(&separator))
+}
+
+fn builtin_trim(arguments: Vec<Object>) -> Object {
+    match string_arguments("trim", &arguments, 1) {
+        Ok(strings) => Object::StringObject(strings[0].trim().to_string()),
+        Err(message) => Object::Error(message),
+    }
+}
+
+fn builtin_upper(arguments: Vec<Object>) -> Object {
+    match string_arguments("upper", &arguments, 1) {
+        Ok(strings) => Object::StringObject(strings[0].to_uppercase()),
+        Err(message) => Object::Error(message),
+    }
+}
+
+fn builtin_lower(arguments: Vec<Object>) -> Object {
+    match string_arguments("lower", &arguments, 1) {
+        Ok(strings) => Object::StringObject(strings[0].to_lowercase()),
+        Err(message) => Object::Error(message),
+    }
+}
+
+// replaces every occurrence
+fn builtin_replace(arguments: Vec<Object>) -> Object {
+    match string_arguments("replace", &arguments, 3) {
+        Ok(strings) if strings[1].is_empty() => {
+            Object::Error(String::from("pattern of `replace` must not be empty"))
+        }
+        Ok(strings) => Object::StringObject(strings[0].replace(&strings[1], &strings[2])),
+        Err(message) => Object::Error(message),
+    }
+}
+
+fn builtin_starts_with(arguments: Vec<Object>) -> Object {
+    match string_arguments("starts_with", &arguments, 2) {
+        Ok(strings) => Object::Boolean(strings[0].starts_with(&strings[1])),
+        Err(message) => Object::Error(message),
+    }
+}
+
+fn builtin_ends_with(arguments: Vec<Object>) -> Object {
+    match string_arguments("ends_with", &arguments, 2) {
+        Ok(strings) => Object::Boolean(strings[0].ends_with(&strings[1])),
+        Err(message) => Object::Error(message),
+    }
+}
+
+// the character index of the first occurrence, or -1 like `index_of`
+fn builtin_find(arguments: Vec<Object>) -> Object {
+    match string_arguments("find", &arguments, 2) {
+        Ok(strings) => match strings[0].find(&strings[1]) {
+            Some(byte_idx) => Object::Integer(strings[0][..byte_idx].chars().count() as i64),
+            None => Object::Integer(-1),
+        },
+        Err(message) => Object::Error(message),
+    }
+}
+
+// `substr(string, start)` or `substr(string, start, length)`. A negative start counts from
+// the end, and the substring stops at the end of the string
+fn builtin_substr(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 2 && arguments.len() != 3 {
+        return wrong_number_of_arguments(arguments.len(), "2 or 3");
+    }
+    let string = match string_argument("substr", &arguments[0]) {
+        Ok(string) => string,
+        Err(message) => return Object::Error(message),
+    };
+    let mut numbers = vec![];
+    for argument in &arguments[1..] {
+        match integer_argument("substr", argument) {
+            Ok(number) => numbers.push(number),
+            Err(message) => return Object::Error(message),
+        }
+    }
+
+    let len = string.chars().count() as i64;
+    let start = if numbers[0] < 0 { (len + numbers[0]).max(0) } else { numbers[0].min(len) };
+    let length = match numbers.get(1) {
+        Some(length) if *length < 0 => return Object::Error(String::from("length of `substr` must not be negative")),
+        Some(length) => *length,
+        None => len,
+    };
+
+    let substring = string.chars().skip(start as usize).take(length as usize).collect();
+    Object::StringObject(substring)
+}
+
+fn builtin_chars(arguments: Vec<Object>) -> Object {
+    match string_arguments("chars", &arguments, 1) {
+        Ok(strings) => {
+            let chars = strings[0].chars().map(|ch| Object::StringObject(ch.to_string())).collect();
+            Object::new_array(chars)
+        }
+        Err(message) => Object::Error(message),
+    }
+}
+
+fn builtin_repeat(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 2 {
+        return wrong_number_of_arguments(arguments.len(), "2");
+    }
+    let (string, count) = match (string_argument("repeat", &arguments[0]), integer_argument("repeat", &arguments[1])) {
+        (Ok(string), Ok(count)) => (string, count),
+        (Err(message), _) | (_, Err(message)) => return Object::Error(message),
+    };
+    if count < 0 {
+        return Object::Error(String::from("count of `repeat` must not be negative"));
+    }
+    match string.len().checked_mul(count as usize) {
+        Some(length) if length <= MAX_STRING_LENGTH => Object::StringObject(string.repeat(count as usize)),
+        _ => Object::Error(format!("result of `repeat` is too long: {} * {}", string.len(), count)),
+    }
+}
+
+fn builtin_pad_left(arguments: Vec<Object>) -> Object {
+    pad("pad_left", arguments, true)
+}
+
+fn builtin_pad_right(arguments: Vec<Object>) -> Object {
+    pad("pad_right", arguments, false)
+}
+
+// `pad_left(string, width)` or `pad_left(string, width, fill)` adds `fill` (a space by
+// default) until the string is `width` characters long
+fn pad(name: &str, arguments: Vec<Object>, left: bool) -> Object {
+    if arguments.len() != 2 && arguments.len() != 3 {
+        return wrong_number_of_arguments(arguments.len(), "2 or 3");
+    }
+    let (string, width) = match (string_argument(name, &arguments[0]), integer_argument(name, &arguments[1])) {
+        (Ok(string), Ok(width)) => (string, width),
+        (Err(message), _) | (_, Err(message)) => return Object::Error(message),
+    };
+    let fill = match arguments.get(2).map(|fill| string_argument(name, fill)) {
+        None => ' ',
+        Some(Ok(fill)) if fill.chars().count() == 1 => fill.chars().next().unwrap(),
+        Some(Ok(_)) => return Object::Error(format!("fill of `{}` must be a single character", name)),
+        Some(Err(message)) => return Object::Error(message),
+    };
+
+    let missing = (width.max(0) as usize).saturating_sub(string.chars().count());
+    let length = missing.checked_mul(fill.len_utf8()).and_then(|padding| padding.checked_add(string.len()));
+    if !matches!(length, Some(length) if length <= MAX_STRING_LENGTH) {
+        return Object::Error(format!("result of `{}` is too long: width {}", name, width));
+    }
+    let padding: String = std::iter::repeat_n(fill, missing).collect();
+    if left {
+        Object::StringObject(padding + &string)
+    } else {
+        Object::StringObject(string + &padding)
+    }
+}
+
+// parses a string, or drops the fraction of a float
+fn builtin_to_int(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 1 {
+        return wrong_number_of_arguments(arguments.len(), "1");
+    }
+
+    match &arguments[0] {
+        Object::Integer(_) | Object::BigInt(_) => arguments[0].clone(),
+        Object::Float(value) if !value.is_finite() => {
+            Object::Error(format!("could not convert {} to an integer", value))
+        }
+        Object::Float(value) => match format!("{:.0}", value.trunc()).parse::<BigInt>() {
+            Ok(integer) => Object::from_bigint(integer),
+            Err(_) => Object::Error(format!("could not convert {} to an integer", value)),
+        },
+        Object::StringObject(string) => {
+            let digits = string.trim();
+            if let Ok(integer) = digits.parse::<i64>() {
+                return Object::Integer(integer);
+            }
+            match digits.parse::<BigInt>() {
+                Ok(integer) => Object::from_bigint(integer),
+                Err(_) => Object::Error(format!("could not parse {:?} as integer", string)),
+            }
+        }
+        other => Object::Error(format!(
+            "argument to `to_int` must be STRING, FLOAT or INTEGER, got={}",
+            other.object_type()
+        )),
+    }
+}
+
+fn builtin_to_string(arguments: Vec<Object>) -> Object {
+    if arguments.len() != 1 {
+        return wrong_number_of_arguments(arguments.len(), "1");
+    }
+    Object::StringObject(arguments[0].to_string())
+}
+
+// `format(template, values...)` writes the next value for each `{}` and the value at that
+// position for `{0}`, `{1}`, ...; `{{` and `}}` are literal braces. Templates are best
+// written as raw strings, where braces are not interpolation
+fn builtin_format(arguments: Vec<Object>) -> Object {
+    if arguments.is_empty() {
+        return wrong_number_of_arguments(0, "at least 1");
+    }
+    let template = match string_argument("format", &arguments[0]) {
+        Ok(template) => template,
+        Err(message) => return Object::Error(message),
+    };
+    let values = &arguments[1..];
+
+    let mut out = String::new();
+    let mut next = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let mut position = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(digit) if digit.is_ascii_digit() => position.push(digit),
+                        _ => return Object::Error(String::from("invalid placeholder in format template, expected `{}` or `{N}`")),
+                    }
+                }
+                if position.is_empty() {
+                    position = next.to_string();
+                    next += 1;
+                }
+                // an index too big for `usize` is out of range all the same
+                match position.parse::<usize>().ok().and_then(|idx| values.get(idx)) {
+                    Some(value) => out.push_str(&value.to_string()),
+                    None => {
+                        return Object::Error(format!(
+                            "not enough arguments for format template: wants argument {}, got={}",
+                            position,
+                            values.len()
+                        ))
+                    }
+                }
+            }
+            '}' => return Object::Error(String::from("unmatched `}` in format template, write `}}` for a literal brace")),
+            ch => out.push(ch),
+        }
+    }
+    Object::StringObject(out)
+}
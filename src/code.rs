@@ -0,0 +1,345 @@
+use std::fmt::Display;
+
+pub type Instructions = Vec<u8>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Opcode {
+    Constant,
+    Pop,
+
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+
+    True,
+    False,
+    Null,
+
+    Equal,
+    NotEqual,
+    LessThan,
+    GreaterThan,
+    LessEqual,
+    GreaterEqual,
+
+    Minus,
+    Bang,
+    BitNot,
+
+    JumpNotTruthy,
+    Jump,
+
+    GetIter,
+    ForIter,
+
+    GetGlobal,
+    SetGlobal,
+    GetLocal,
+    SetLocal,
+    GetFree,
+    GetBuiltin,
+    AssignGlobal,
+    AssignLocal,
+    AssignFree,
+    IsUnset,
+
+    Array,
+    Dict,
+    Interpolate,
+    Index,
+    SetIndex,
+    Dup2,
+
+    MatchArray,
+    MatchDict,
+    MatchKey,
+    NoMatch,
+
+    Call,
+    CallNamed,
+    ReturnValue,
+    Return,
+    Closure,
+}
+
+// every opcode in `repr(u8)` order, so a byte can be turned back into an `Opcode`
+const OPCODES: [Opcode; 54] = [
+    Opcode::Constant,
+    Opcode::Pop,
+    Opcode::Add,
+    Opcode::Sub,
+    Opcode::Mul,
+    Opcode::Div,
+    Opcode::Mod,
+    Opcode::Pow,
+    Opcode::BitAnd,
+    Opcode::BitOr,
+    Opcode::BitXor,
+    Opcode::ShiftLeft,
+    Opcode::ShiftRight,
+    Opcode::True,
+    Opcode::False,
+    Opcode::Null,
+    Opcode::Equal,
+    Opcode::NotEqual,
+    Opcode::LessThan,
+    Opcode::GreaterThan,
+    Opcode::LessEqual,
+    Opcode::GreaterEqual,
+    Opcode::Minus,
+    Opcode::Bang,
+    Opcode::BitNot,
+    Opcode::JumpNotTruthy,
+    Opcode::Jump,
+    Opcode::GetIter,
+    Opcode::ForIter,
+    Opcode::GetGlobal,
+    Opcode::SetGlobal,
+    Opcode::GetLocal,
+    Opcode::SetLocal,
+    Opcode::GetFree,
+    Opcode::GetBuiltin,
+    Opcode::AssignGlobal,
+    Opcode::AssignLocal,
+    Opcode::AssignFree,
+    Opcode::IsUnset,
+    Opcode::Array,
+    Opcode::Dict,
+    Opcode::Interpolate,
+    Opcode::Index,
+    Opcode::SetIndex,
+    Opcode::Dup2,
+    Opcode::MatchArray,
+    Opcode::MatchDict,
+    Opcode::MatchKey,
+    Opcode::NoMatch,
+    Opcode::Call,
+    Opcode::CallNamed,
+    Opcode::ReturnValue,
+    Opcode::Return,
+    Opcode::Closure,
+];
+
+impl Opcode {
+    pub fn from_byte(byte: u8) -> Option<Opcode> {
+        OPCODES.get(byte as usize).copied()
+    }
+
+    // byte width of each operand
+    pub fn operand_widths(&self) -> &'static [usize] {
+        match self {
+            Self::Constant
+            | Self::JumpNotTruthy
+            | Self::Jump
+            | Self::ForIter
+            | Self::GetGlobal
+            | Self::SetGlobal
+            | Self::AssignGlobal
+            | Self::Array
+            | Self::Dict
+            | Self::Interpolate
+            | Self::MatchArray
+            | Self::Closure => &[2],
+            Self::GetLocal
+            | Self::SetLocal
+            | Self::GetFree
+            | Self::GetBuiltin
+            | Self::AssignLocal
+            | Self::AssignFree
+            | Self::IsUnset
+            | Self::Call
+            | Self::CallNamed => &[1],
+            _ => &[],
+        }
+    }
+}
+
+impl Display for Opcode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Op{:?}", self)
+    }
+}
+
+pub fn make(op: Opcode, operands: &[usize]) -> Instructions {
+    let widths = op.operand_widths();
+    let mut instruction = vec![op as u8];
+
+    for (operand, width) in operands.iter().zip(widths) {
+        match width {
+            2 => instruction.extend_from_slice(&(*operand as u16).to_be_bytes()),
+            1 => instruction.push(*operand as u8),
+            _ => unreachable!("unsupported operand width {}", width),
+        }
+    }
+
+    instruction
+}
+
+// `make` keeps only the low bytes of an operand, so the compiler rejects values that do not fit
+pub fn check_operands(op: Opcode, operands: &[usize]) -> Result<(), String> {
+    for (operand, width) in operands.iter().zip(op.operand_widths()) {
+        let max = (1 << (8 * width)) - 1;
+        if *operand <= max {
+            continue;
+        }
+
+        let what = match op {
+            Opcode::Constant | Opcode::Closure => "constants",
+            Opcode::JumpNotTruthy | Opcode::Jump | Opcode::ForIter => "instructions to jump over",
+            Opcode::GetGlobal | Opcode::SetGlobal | Opcode::AssignGlobal => "global variables",
+            Opcode::GetLocal | Opcode::SetLocal | Opcode::AssignLocal | Opcode::IsUnset => "local variables in a function",
+            Opcode::GetFree | Opcode::AssignFree => "captured variables in a function",
+            Opcode::GetBuiltin => "builtins",
+            Opcode::Call | Opcode::CallNamed => "arguments in a call",
+            _ => "elements in a literal",
+        };
+        return Err(format!("too many {} for the VM: got={}, max={}", what, operand, max));
+    }
+    Ok(())
+}
+
+pub fn read_u16(instructions: &[u8], offset: usize) -> usize {
+    u16::from_be_bytes([instructions[offset], instructions[offset + 1]]) as usize
+}
+
+pub fn read_u8(instructions: &[u8], offset: usize) -> usize {
+    instructions[offset] as usize
+}
+
+// decodes the operands following an opcode, returning them and the bytes read
+pub fn read_operands(op: Opcode, instructions: &[u8]) -> (Vec<usize>, usize) {
+    let mut operands = vec![];
+    let mut offset = 0;
+
+    for width in op.operand_widths() {
+        match width {
+            2 => operands.push(read_u16(instructions, offset)),
+            1 => operands.push(read_u8(instructions, offset)),
+            _ => unreachable!("unsupported operand width {}", width),
+        }
+        offset += width;
+    }
+
+    (operands, offset)
+}
+
+// human readable listing, one instruction per line: `0003 OpConstant 1`
+pub fn disassemble(instructions: &[u8]) -> String {
+    let mut out = String::new();
+    let mut idx = 0;
+
+    while idx < instructions.len() {
+        let op = match Opcode::from_byte(instructions[idx]) {
+            Some(op) => op,
+            None => {
+                out.push_str(format!("{:04} ERROR: unknown opcode {}\n", idx, instructions[idx]).as_str());
+                idx += 1;
+                continue;
+            }
+        };
+
+        let (operands, read) = read_operands(op, &instructions[idx + 1..]);
+        let mut line = format!("{:04} {}", idx, op);
+        for operand in operands {
+            line.push_str(format!(" {}", operand).as_str());
+        }
+        out.push_str(line.as_str());
+        out.push('\n');
+
+        idx += 1 + read;
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_operands, disassemble, make, read_operands, Opcode};
+
+    #[test]
+    fn test_make() {
+        let tests = vec![
+            (Opcode::Constant, vec![65534], vec![Opcode::Constant as u8, 255, 254]),
+            (Opcode::Add, vec![], vec![Opcode::Add as u8]),
+            (Opcode::GetLocal, vec![255], vec![Opcode::GetLocal as u8, 255]),
+            (Opcode::Closure, vec![65534], vec![Opcode::Closure as u8, 255, 254]),
+        ];
+
+        for (op, operands, expected) in tests {
+            assert_eq!(make(op, &operands), expected, "wrong encoding for {}", op);
+        }
+    }
+
+    #[test]
+    fn test_check_operands() {
+        let tests = vec![
+            (Opcode::Constant, 65535, Ok(())),
+            (Opcode::Constant, 65536, Err("too many constants for the VM: got=65536, max=65535")),
+            (Opcode::Jump, 65536, Err("too many instructions to jump over for the VM: got=65536, max=65535")),
+            (Opcode::GetLocal, 255, Ok(())),
+            (Opcode::SetLocal, 256, Err("too many local variables in a function for the VM: got=256, max=255")),
+            (Opcode::Call, 256, Err("too many arguments in a call for the VM: got=256, max=255")),
+            (Opcode::Array, 70000, Err("too many elements in a literal for the VM: got=70000, max=65535")),
+        ];
+
+        for (op, operand, expected) in tests {
+            assert_eq!(check_operands(op, &[operand]), expected.map_err(String::from), "wrong check for {}", op);
+        }
+    }
+
+    #[test]
+    fn test_read_operands() {
+        let tests = vec![
+            (Opcode::Constant, vec![65535], 2),
+            (Opcode::GetLocal, vec![255], 1),
+            (Opcode::Closure, vec![65535], 2),
+        ];
+
+        for (op, operands, bytes_read) in tests {
+            let instruction = make(op, &operands);
+            let (read, n) = read_operands(op, &instruction[1..]);
+
+            assert_eq!(n, bytes_read, "wrong number of bytes read for {}", op);
+            assert_eq!(read, operands, "wrong operands for {}", op);
+        }
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let instructions = [
+            make(Opcode::Add, &[]),
+            make(Opcode::GetLocal, &[1]),
+            make(Opcode::Constant, &[2]),
+            make(Opcode::Constant, &[65535]),
+            make(Opcode::Closure, &[65535]),
+        ]
+        .concat();
+
+        let expected = "0000 OpAdd
+0001 OpGetLocal 1
+0003 OpConstant 2
+0006 OpConstant 65535
+0009 OpClosure 65535
+";
+
+        assert_eq!(disassemble(&instructions), expected);
+    }
+
+    #[test]
+    fn test_opcode_round_trip() {
+        for byte in 0..=u8::MAX {
+            if let Some(op) = Opcode::from_byte(byte) {
+                assert_eq!(op as u8, byte, "opcode table out of order at {}", op);
+            }
+        }
+    }
+}
@@ -0,0 +1,1366 @@
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use crate::{
+    ast::{BlockStatement, ExpressionNode, FunctionLiteral, Identifier, MatchArm, MatchExpression, Node, Pattern, Program, StatementNode, StringPart},
+    bigint::BigInt,
+    builtins::Builtin,
+    code::{check_operands, make, Instructions, Opcode},
+    object::{function_string, CompiledFunction, Object, ParameterSpec},
+    token::Span,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolScope {
+    Global,
+    Local,
+    Free,
+    Builtin,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub scope: SymbolScope,
+    pub index: usize,
+}
+
+//      one table per function being compiled, chained through `outer` up to the global table.
+//      `declared` holds every name the function binds with `say` anywhere in its body, so a
+//      name used before its `say` (mutual recursion) can be bound to the right scope early.
+//      `blocks` holds, for each open block, the names it binds and the symbols they hide
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    pub outer: Option<Box<SymbolTable>>,
+    store: HashMap<String, Symbol>,
+    names: Vec<String>,
+    declared: HashSet<String>,
+    blocks: Vec<Vec<(String, Option<Symbol>)>>,
+    pub free_symbols: Vec<Symbol>,
+}
+
+impl SymbolTable {
+    pub fn new_global() -> SymbolTable {
+        let mut table = SymbolTable::default();
+        for (idx, (name, _)) in Builtin.all_builtins().into_iter().enumerate() {
+            table.define_builtin(idx, name);
+        }
+        table
+    }
+
+    pub fn new_enclosed(outer: SymbolTable, declared: HashSet<String>) -> SymbolTable {
+        SymbolTable {
+            outer: Some(Box::new(outer)),
+            declared,
+            ..Default::default()
+        }
+    }
+
+    pub fn num_definitions(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn names(&self) -> &Vec<String> {
+        &self.names
+    }
+
+    // binds `name` in the innermost open block, or in this table when no block is open
+    pub fn define(&mut self, name: &str) -> Symbol {
+        let Some(block) = self.blocks.last_mut() else {
+            return self.bind(name);
+        };
+        if block.iter().any(|(bound, _)| bound == name) {
+            return self.bind(name);
+        }
+
+        let hidden = self.store.remove(name);
+        block.push((name.to_string(), hidden));
+        self.bind(name)
+    }
+
+    // names defined until the matching `leave_block` get fresh slots, and stop resolving after it
+    pub fn enter_block(&mut self) {
+        self.blocks.push(vec![]);
+    }
+
+    pub fn leave_block(&mut self) {
+        let block = self.blocks.pop().expect("leave_block without enter_block");
+        for (name, hidden) in block.into_iter().rev() {
+            match hidden {
+                Some(symbol) => self.store.insert(name, symbol),
+                None => self.store.remove(&name),
+            };
+        }
+    }
+
+    // takes the symbols out of scope until they are restored, so names resolve past them
+    pub fn hide(&mut self, symbols: &[Symbol]) {
+        for symbol in symbols {
+            self.store.remove(&symbol.name);
+        }
+    }
+
+    pub fn restore(&mut self, symbols: &[Symbol]) {
+        for symbol in symbols {
+            self.store.insert(symbol.name.clone(), symbol.clone());
+        }
+    }
+
+    // binds `name` in this table, reusing its slot when this table already owns one
+    fn bind(&mut self, name: &str) -> Symbol {
+        let scope = match self.outer {
+            Some(_) => SymbolScope::Local,
+            None => SymbolScope::Global,
+        };
+
+        if let Some(symbol) = self.store.get(name) {
+            if symbol.scope == scope {
+                return symbol.clone();
+            }
+        }
+
+        let symbol = Symbol {
+            name: name.to_string(),
+            scope,
+            index: self.names.len(),
+        };
+        self.names.push(name.to_string());
+        self.store.insert(name.to_string(), symbol.clone());
+        symbol
+    }
+
+    pub fn define_builtin(&mut self, index: usize, name: String) -> Symbol {
+        let symbol = Symbol {
+            name: name.clone(),
+            scope: SymbolScope::Builtin,
+            index,
+        };
+        self.store.insert(name, symbol.clone());
+        symbol
+    }
+
+    fn define_free(&mut self, original: Symbol) -> Symbol {
+        let symbol = Symbol {
+            name: original.name.clone(),
+            scope: SymbolScope::Free,
+            index: self.free_symbols.len(),
+        };
+        self.free_symbols.push(original);
+        self.store.insert(symbol.name.clone(), symbol.clone());
+        symbol
+    }
+
+    // the symbol `name` has in this table itself, without looking outwards
+    pub fn lookup(&self, name: &str) -> Option<&Symbol> {
+        self.store.get(name)
+    }
+
+    pub fn resolve(&mut self, name: &str) -> Option<Symbol> {
+        if let Some(symbol) = self.store.get(name) {
+            return Some(symbol.clone());
+        }
+
+        let outer = self.outer.as_mut()?;
+        match outer.resolve(name) {
+            Some(symbol) if symbol.scope == SymbolScope::Global || symbol.scope == SymbolScope::Builtin => {
+                Some(symbol)
+            }
+            Some(symbol) => Some(self.define_free(symbol)),
+            None => None,
+        }
+    }
+
+    // like `resolve`, but a name that is not bound yet is bound in the innermost function
+    // that declares it, or as a global. Reading it before it is assigned is a runtime error
+    pub fn resolve_or_declare(&mut self, name: &str) -> Symbol {
+        if let Some(symbol) = self.resolve(name) {
+            return symbol;
+        }
+        self.declare_forward(name);
+        self.resolve(name).expect("forward declared name should resolve")
+    }
+
+    // binds `name` in the global table, however deeply this table is nested
+    pub fn define_global(&mut self, name: &str) -> Symbol {
+        match self.outer.as_mut() {
+            Some(outer) => outer.define_global(name),
+            None => self.bind(name),
+        }
+    }
+
+    fn declare_forward(&mut self, name: &str) {
+        if self.declared.contains(name) {
+            self.bind(name);
+            return;
+        }
+        match self.outer.as_mut() {
+            Some(outer) => outer.declare_forward(name),
+            None => {
+                self.bind(name);
+            }
+        }
+    }
+}
+
+pub struct Bytecode {
+    pub main: Rc<CompiledFunction>,
+    pub constants: Vec<Object>,
+    pub global_names: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct EmittedInstruction {
+    opcode: Opcode,
+    position: usize,
+}
+
+// jumps out of the innermost loop, patched once the end of the loop is known
+#[derive(Default)]
+struct LoopContext {
+    continue_target: usize,
+    breaks: Vec<usize>,
+    pending: usize,     // `pending` of the scope when the body starts
+}
+
+#[derive(Default)]
+struct CompilationScope {
+    instructions: Instructions,
+    spans: Vec<(usize, Span)>,
+    last_instruction: Option<EmittedInstruction>,
+    loops: Vec<LoopContext>,
+    // values that enclosing expressions have pushed and not used yet, e.g. the left operand
+    // while the right one is compiled. `break` and `continue` drop them before jumping
+    pending: usize,
+}
+
+// literal constants that compare equal share one slot in the pool. Floats are keyed by their
+// bits, so `1` and `1.0` (equal as values) stay apart
+#[derive(PartialEq, Eq, Hash)]
+enum ConstantKey {
+    Integer(i64),
+    BigInt(BigInt),
+    Float(u64),
+    String(String),
+}
+
+impl ConstantKey {
+    fn of(object: &Object) -> Option<ConstantKey> {
+        match object {
+            Object::Integer(value) => Some(ConstantKey::Integer(*value)),
+            Object::BigInt(value) => Some(ConstantKey::BigInt(value.clone())),
+            Object::Float(value) => Some(ConstantKey::Float(value.to_bits())),
+            Object::StringObject(value) => Some(ConstantKey::String(value.clone())),
+            _ => None,
+        }
+    }
+}
+
+// how to reach a part of a `match` subject: element by element, key by key
+#[derive(Clone, Copy)]
+enum PathStep<'a> {
+    Element(usize),
+    Key(&'a ExpressionNode),
+}
+
+pub struct Compiler {
+    constants: Vec<Object>,
+    constant_slots: HashMap<ConstantKey, usize>,
+    symbol_table: SymbolTable,
+    scopes: Vec<CompilationScope>,
+    // spans of the nodes being compiled, innermost last; emitted instructions take the top one
+    spans: Vec<Span>,
+    match_depth: usize,     // `match` expressions being compiled, each keeps its subject in a hidden variable
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Compiler {
+        Compiler::new_with_state(SymbolTable::new_global(), vec![])
+    }
+
+    // the REPL keeps globals and constants alive between inputs
+    pub fn new_with_state(symbol_table: SymbolTable, constants: Vec<Object>) -> Compiler {
+        let constant_slots = constants
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, constant)| ConstantKey::of(constant).map(|key| (key, idx)))
+            .collect();
+        Compiler {
+            constants,
+            constant_slots,
+            symbol_table,
+            scopes: vec![CompilationScope::default()],
+            spans: vec![],
+            match_depth: 0,
+        }
+    }
+
+    pub fn into_state(self) -> (SymbolTable, Vec<Object>) {
+        (self.symbol_table, self.constants)
+    }
+
+    // span of the node that was being compiled when the last error was returned
+    pub fn error_span(&self) -> Option<Span> {
+        self.spans.last().copied()
+    }
+
+    pub fn compile(&mut self, program: &Program) -> Result<(), String> {
+        self.hoist_function_declarations(&program.statements)?;
+        for stmt in &program.statements {
+            self.compile_statement(stmt)?;
+        }
+        Ok(())
+    }
+
+    pub fn bytecode(&self) -> Bytecode {
+        let scope = &self.scopes[0];
+        Bytecode {
+            main: Rc::new(CompiledFunction {
+                instructions: scope.instructions.clone(),
+                spans: scope.spans.clone(),
+                num_locals: 0,
+                parameters: vec![],
+                local_names: vec![],
+                free_names: vec![],
+                captures: vec![],
+                literal: String::from("<main>"),
+            }),
+            constants: self.constants.clone(),
+            global_names: self.symbol_table.names().clone(),
+        }
+    }
+
+    fn compile_statement(&mut self, stmt: &StatementNode) -> Result<(), String> {
+        self.spans.push(stmt.span());
+
+        match stmt {
+            StatementNode::Expression(exp_stmt) => {
+                self.compile_expression(exp_stmt.expression.as_ref())?;
+                self.emit(Opcode::Pop, &[])?;
+            }
+            StatementNode::Say(say_stmt) => {
+                // the value is compiled first: `say x = x + 1;` reads the outer `x`
+                self.compile_expression(say_stmt.value.as_ref())?;
+                let symbol = self.symbol_table.define(&say_stmt.name.value);
+                match symbol.scope {
+                    SymbolScope::Global => self.emit(Opcode::SetGlobal, &[symbol.index])?,
+                    _ => self.emit(Opcode::SetLocal, &[symbol.index])?,
+                };
+                self.emit(Opcode::Pop, &[])?;
+            }
+            StatementNode::Return(return_stmt) => {
+                self.compile_expression(return_stmt.return_value.as_ref())?;
+                self.emit(Opcode::ReturnValue, &[])?;
+            }
+            // the function was bound by `hoist_function_declarations`, the statement itself is null
+            StatementNode::Block(_) | StatementNode::Function(_) => {
+                self.emit(Opcode::Null, &[])?;
+                self.emit(Opcode::Pop, &[])?;
+            }
+            StatementNode::While(while_stmt) => {
+                let loop_start = self.current_instructions().len();
+                self.compile_expression(Some(&while_stmt.condition))?;
+                let jump_not_truthy = self.emit(Opcode::JumpNotTruthy, &[9999])?;
+
+                self.compile_loop_body(loop_start, &while_stmt.body)?;
+                self.emit(Opcode::Jump, &[loop_start])?;
+
+                let after_loop = self.current_instructions().len();
+                self.change_operand(jump_not_truthy, after_loop)?;
+                self.patch_breaks(after_loop)?;
+
+                // a loop evaluates to null
+                self.emit(Opcode::Null, &[])?;
+                self.emit(Opcode::Pop, &[])?;
+            }
+            StatementNode::For(for_stmt) => {
+                // the items and the next index stay on the stack while the loop runs
+                self.compile_expression(Some(&for_stmt.iterable))?;
+                self.spans.push(for_stmt.iterable.span());
+                self.emit(Opcode::GetIter, &[])?;
+                self.spans.pop();
+                self.scopes.last_mut().unwrap().pending += 2;
+
+                let loop_start = self.current_instructions().len();
+                let for_iter = self.emit(Opcode::ForIter, &[9999])?;
+                let symbol = self.symbol_table.define(&for_stmt.variable.value);
+                match symbol.scope {
+                    SymbolScope::Global => self.emit(Opcode::SetGlobal, &[symbol.index])?,
+                    _ => self.emit(Opcode::SetLocal, &[symbol.index])?,
+                };
+                self.emit(Opcode::Pop, &[])?;
+
+                self.compile_loop_body(loop_start, &for_stmt.body)?;
+                self.emit(Opcode::Jump, &[loop_start])?;
+
+                let after_loop = self.current_instructions().len();
+                self.change_operand(for_iter, after_loop)?;
+                self.patch_breaks(after_loop)?;
+                self.emit(Opcode::Pop, &[])?;
+                self.emit(Opcode::Pop, &[])?;
+                self.scopes.last_mut().unwrap().pending -= 2;
+
+                self.emit(Opcode::Null, &[])?;
+                self.emit(Opcode::Pop, &[])?;
+            }
+            StatementNode::Break(_) => {
+                self.drop_pending("break")?;
+                let jump = self.emit(Opcode::Jump, &[9999])?;
+                let context = self.scopes.last_mut().unwrap().loops.last_mut().expect("loop context should exist");
+                context.breaks.push(jump);
+            }
+            StatementNode::Continue(_) => {
+                let target = self.drop_pending("continue")?;
+                self.emit(Opcode::Jump, &[target])?;
+            }
+        }
+
+        self.spans.pop();
+        Ok(())
+    }
+
+    fn compile_loop_body(&mut self, continue_target: usize, body: &BlockStatement) -> Result<(), String> {
+        let scope = self.scopes.last_mut().unwrap();
+        scope.loops.push(LoopContext {
+            continue_target,
+            breaks: vec![],
+            pending: scope.pending,
+        });
+
+        self.hoist_function_declarations(&body.statements)?;
+        for stmt in &body.statements {
+            self.compile_statement(stmt)?;
+        }
+        Ok(())
+    }
+
+    // pops what the expressions around a `break` or `continue` pushed since the loop body
+    // started, returning the loop's continue target
+    fn drop_pending(&mut self, keyword: &str) -> Result<usize, String> {
+        let scope = self.scopes.last().unwrap();
+        let context = match scope.loops.last() {
+            Some(context) => context,
+            None => return Err(format!("`{}` outside of a loop", keyword)),
+        };
+        let (target, count) = (context.continue_target, scope.pending - context.pending);
+        for _ in 0..count {
+            self.emit(Opcode::Pop, &[])?;
+        }
+        Ok(target)
+    }
+
+    // compiles an operand while `below` values of the same expression wait on the stack
+    fn compile_operand(&mut self, below: usize, expression: &ExpressionNode) -> Result<(), String> {
+        self.scopes.last_mut().unwrap().pending += below;
+        let result = self.compile_expression(Some(expression));
+        self.scopes.last_mut().unwrap().pending -= below;
+        result
+    }
+
+    fn patch_breaks(&mut self, target: usize) -> Result<(), String> {
+        let context = self.scopes.last_mut().unwrap().loops.pop().expect("loop context should exist");
+        for jump in context.breaks {
+            self.change_operand(jump, target)?;
+        }
+        Ok(())
+    }
+
+    fn compile_expression(&mut self, expression: Option<&ExpressionNode>) -> Result<(), String> {
+        let exp = match expression {
+            Some(exp) => exp,
+            None => {
+                self.emit(Opcode::Null, &[])?;
+                return Ok(());
+            }
+        };
+        self.spans.push(exp.span());
+
+        match exp {
+            ExpressionNode::Integer(int) => {
+                let constant = self.add_constant(Object::Integer(int.value));
+                self.emit(Opcode::Constant, &[constant])?;
+            }
+            ExpressionNode::BigInt(int) => {
+                let constant = self.add_constant(Object::BigInt(int.value.clone()));
+                self.emit(Opcode::Constant, &[constant])?;
+            }
+            ExpressionNode::Float(float) => {
+                let constant = self.add_constant(Object::Float(float.value));
+                self.emit(Opcode::Constant, &[constant])?;
+            }
+            ExpressionNode::StringExp(string_literal) => {
+                let constant = self.add_constant(Object::StringObject(string_literal.value.clone()));
+                self.emit(Opcode::Constant, &[constant])?;
+            }
+            ExpressionNode::BooleanNode(bool) => {
+                if bool.value {
+                    self.emit(Opcode::True, &[])?;
+                } else {
+                    self.emit(Opcode::False, &[])?;
+                }
+            }
+            ExpressionNode::Prefix(prefix_exp) => {
+                self.compile_expression(Some(&prefix_exp.right))?;
+                match prefix_exp.operator.as_str() {
+                    "!" => self.emit(Opcode::Bang, &[])?,
+                    "-" => self.emit(Opcode::Minus, &[])?,
+                    "~" => self.emit(Opcode::BitNot, &[])?,
+                    other => return Err(format!("unknown operator: {}", other)),
+                };
+            }
+            ExpressionNode::Infix(infix_exp) if infix_exp.operator == "&&" || infix_exp.operator == "||" => {
+                self.compile_logical_expression(&infix_exp.operator, &infix_exp.left, &infix_exp.right)?;
+            }
+            ExpressionNode::Infix(infix_exp) => {
+                self.compile_expression(Some(&infix_exp.left))?;
+                self.compile_operand(1, &infix_exp.right)?;
+                let opcode = infix_opcode(&infix_exp.operator)?;
+                self.emit(opcode, &[])?;
+            }
+            ExpressionNode::Assign(assign_exp) => {
+                let operator = assign_exp.infix_operator();
+                match assign_exp.target.as_ref() {
+                    ExpressionNode::IdentifierNode(identifier) => {
+                        let symbol = self.symbol_table.resolve_or_declare(&identifier.value);
+                        if operator.is_some() {
+                            self.load_symbol(&symbol)?;
+                        }
+                        self.compile_assigned_value(0, operator, &assign_exp.value)?;
+
+                        match symbol.scope {
+                            SymbolScope::Global => self.emit(Opcode::AssignGlobal, &[symbol.index])?,
+                            SymbolScope::Local => self.emit(Opcode::AssignLocal, &[symbol.index])?,
+                            SymbolScope::Free => self.emit(Opcode::AssignFree, &[symbol.index])?,
+                            // builtins live in the global environment, so assigning one replaces it there
+                            SymbolScope::Builtin => {
+                                let symbol = self.symbol_table.define_global(&identifier.value);
+                                self.emit(Opcode::SetGlobal, &[symbol.index])?
+                            }
+                        };
+                    }
+                    ExpressionNode::Index(index_exp) => {
+                        self.compile_expression(Some(&index_exp.left))?;
+                        self.compile_operand(1, &index_exp.index)?;
+                        if operator.is_some() {
+                            self.emit(Opcode::Dup2, &[])?;
+                            self.emit(Opcode::Index, &[])?;
+                        }
+                        self.compile_assigned_value(2, operator, &assign_exp.value)?;
+                        self.emit(Opcode::SetIndex, &[])?;
+                    }
+                    other => return Err(format!("invalid assignment target: {}", other.print_string())),
+                }
+            }
+            ExpressionNode::IfExpressionNode(if_exp) => {
+                self.compile_expression(Some(&if_exp.condition))?;
+                let jump_not_truthy = self.emit(Opcode::JumpNotTruthy, &[9999])?;
+
+                self.compile_block_value(&if_exp.consequence)?;
+                let jump = self.emit(Opcode::Jump, &[9999])?;
+
+                let after_consequence = self.current_instructions().len();
+                self.change_operand(jump_not_truthy, after_consequence)?;
+
+                match &if_exp.alternative {
+                    Some(alternative) => self.compile_block_value(alternative)?,
+                    None => {
+                        self.emit(Opcode::Null, &[])?;
+                    }
+                }
+
+                let after_alternative = self.current_instructions().len();
+                self.change_operand(jump, after_alternative)?;
+            }
+            ExpressionNode::Match(match_exp) => self.compile_match_expression(match_exp)?,
+            ExpressionNode::IdentifierNode(identifier) => {
+                let symbol = self.symbol_table.resolve_or_declare(&identifier.value);
+                self.load_symbol(&symbol)?;
+            }
+            ExpressionNode::Function(function_literal) => {
+                self.compile_function_literal(function_literal)?;
+            }
+            ExpressionNode::Call(call_exp) => {
+                self.compile_expression(Some(&call_exp.function))?;
+                for (idx, argument) in call_exp.arguments.iter().enumerate() {
+                    self.compile_operand(1 + idx, argument)?;
+                }
+                if call_exp.named.is_empty() {
+                    self.emit(Opcode::Call, &[call_exp.arguments.len()])?;
+                } else {
+                    // the named values follow the positional ones, then an array of their names
+                    let mut names = vec![];
+                    for (idx, (name, value)) in call_exp.named.iter().enumerate() {
+                        self.compile_operand(1 + call_exp.arguments.len() + idx, value)?;
+                        names.push(Object::StringObject(name.value.clone()));
+                    }
+                    let constant = self.add_constant(Object::new_array(names));
+                    self.emit(Opcode::Constant, &[constant])?;
+                    self.emit(Opcode::CallNamed, &[call_exp.arguments.len()])?;
+                }
+            }
+            ExpressionNode::InterpolatedString(interpolated) => {
+                for (idx, part) in interpolated.parts.iter().enumerate() {
+                    match part {
+                        StringPart::Literal(text) => {
+                            let constant = self.add_constant(Object::StringObject(text.clone()));
+                            self.emit(Opcode::Constant, &[constant])?;
+                        }
+                        StringPart::Expression(expression) => self.compile_operand(idx, expression)?,
+                    }
+                }
+                self.emit(Opcode::Interpolate, &[interpolated.parts.len()])?;
+            }
+            ExpressionNode::Array(array_literal) => {
+                for (idx, element) in array_literal.elements.iter().enumerate() {
+                    self.compile_operand(idx, element)?;
+                }
+                self.emit(Opcode::Array, &[array_literal.elements.len()])?;
+            }
+            ExpressionNode::Index(index_exp) => {
+                self.compile_expression(Some(&index_exp.left))?;
+                self.compile_operand(1, &index_exp.index)?;
+                self.emit(Opcode::Index, &[])?;
+            }
+            ExpressionNode::Dictionary(dictionary) => {
+                for (idx, (key, value)) in dictionary.pairs.iter().enumerate() {
+                    self.compile_operand(2 * idx, key)?;
+                    self.compile_operand(2 * idx + 1, value)?;
+                }
+                self.emit(Opcode::Dict, &[dictionary.pairs.len() * 2])?;
+            }
+            ExpressionNode::None => {
+                self.emit(Opcode::Null, &[])?;
+            }
+        }
+
+        self.spans.pop();
+        Ok(())
+    }
+
+    // the right-hand side of an assignment, above the `below` values of its target; a compound
+    // one combines it with the current value, which is already on the stack
+    fn compile_assigned_value(&mut self, below: usize, operator: Option<&str>, value: &ExpressionNode) -> Result<(), String> {
+        self.compile_operand(below + usize::from(operator.is_some()), value)?;
+        if let Some(operator) = operator {
+            let opcode = infix_opcode(operator)?;
+            self.emit(opcode, &[])?;
+        }
+        Ok(())
+    }
+
+    // `a && b` and `a || b` jump over `b` when `a` already decides the result. Both leave a boolean:
+    //   left, (&&: JumpNotTruthy F | ||: JumpNotTruthy R, Jump T, R:), right, JumpNotTruthy F,
+    //   T: True, Jump E, F: False, E:
+    fn compile_logical_expression(
+        &mut self,
+        operator: &str,
+        left: &ExpressionNode,
+        right: &ExpressionNode,
+    ) -> Result<(), String> {
+        self.compile_expression(Some(left))?;
+        let mut to_false = vec![];
+        let mut to_true = None;
+        if operator == "&&" {
+            to_false.push(self.emit(Opcode::JumpNotTruthy, &[9999])?);
+        } else {
+            let to_right = self.emit(Opcode::JumpNotTruthy, &[9999])?;
+            to_true = Some(self.emit(Opcode::Jump, &[9999])?);
+            let right_start = self.current_instructions().len();
+            self.change_operand(to_right, right_start)?;
+        }
+
+        self.compile_expression(Some(right))?;
+        to_false.push(self.emit(Opcode::JumpNotTruthy, &[9999])?);
+
+        let true_start = self.emit(Opcode::True, &[])?;
+        let to_end = self.emit(Opcode::Jump, &[9999])?;
+        let false_start = self.emit(Opcode::False, &[])?;
+        let end = self.current_instructions().len();
+
+        if let Some(to_true) = to_true {
+            self.change_operand(to_true, true_start)?;
+        }
+        for jump in to_false {
+            self.change_operand(jump, false_start)?;
+        }
+        self.change_operand(to_end, end)?;
+        Ok(())
+    }
+
+    // each arm tests its whole pattern before binding any name, then checks its guard; a failed
+    // test jumps to the next arm. The subject is kept in a variable no program can name
+    fn compile_match_expression(&mut self, match_exp: &MatchExpression) -> Result<(), String> {
+        self.compile_expression(Some(&match_exp.subject))?;
+        let subject = self.symbol_table.define(&format!("match {}", self.match_depth));
+        self.store_symbol(&subject)?;
+        self.match_depth += 1;
+
+        let mut to_end = vec![];
+        for arm in &match_exp.arms {
+            // each arm binds its names in its own block, so they never touch the enclosing ones
+            self.symbol_table.enter_block();
+            let compiled = self.compile_match_arm(arm, &subject);
+            self.symbol_table.leave_block();
+            to_end.push(compiled?);
+        }
+
+        self.load_symbol(&subject)?;
+        self.emit(Opcode::NoMatch, &[])?;
+        self.match_depth -= 1;
+
+        let end = self.current_instructions().len();
+        for jump in to_end {
+            self.change_operand(jump, end)?;
+        }
+        Ok(())
+    }
+
+    // compiles one arm, returning its jump to the end of the match
+    fn compile_match_arm(&mut self, arm: &MatchArm, subject: &Symbol) -> Result<usize, String> {
+        let mut to_next_arm = vec![];
+        let mut bindings = vec![];
+        self.compile_pattern(&arm.pattern, subject, &mut vec![], &mut to_next_arm, &mut bindings)?;
+
+        for (identifier, path) in bindings {
+            self.load_path(subject, &path)?;
+            let symbol = self.symbol_table.define(&identifier.value);
+            self.store_symbol(&symbol)?;
+        }
+
+        if let Some(guard) = &arm.guard {
+            self.compile_expression(Some(guard))?;
+            to_next_arm.push(self.emit(Opcode::JumpNotTruthy, &[9999])?);
+        }
+
+        self.compile_expression(Some(&arm.value))?;
+        let to_end = self.emit(Opcode::Jump, &[9999])?;
+
+        let next_arm = self.current_instructions().len();
+        for jump in to_next_arm {
+            self.change_operand(jump, next_arm)?;
+        }
+        Ok(to_end)
+    }
+
+    // emits the tests for the part of the subject at `path`, and collects the names to bind
+    fn compile_pattern<'a>(
+        &mut self,
+        pattern: &'a Pattern,
+        subject: &Symbol,
+        path: &mut Vec<PathStep<'a>>,
+        failures: &mut Vec<usize>,
+        bindings: &mut Vec<(&'a Identifier, Vec<PathStep<'a>>)>,
+    ) -> Result<(), String> {
+        match pattern {
+            Pattern::Wildcard(_) => {}
+            Pattern::Binding(identifier) => bindings.push((identifier, path.clone())),
+            Pattern::Literal(literal) => {
+                self.load_path(subject, path)?;
+                self.compile_expression(Some(literal))?;
+                self.emit(Opcode::Equal, &[])?;
+                failures.push(self.emit(Opcode::JumpNotTruthy, &[9999])?);
+            }
+            Pattern::Array(_, elements) => {
+                self.load_path(subject, path)?;
+                self.emit(Opcode::MatchArray, &[elements.len()])?;
+                failures.push(self.emit(Opcode::JumpNotTruthy, &[9999])?);
+
+                for (idx, element) in elements.iter().enumerate() {
+                    path.push(PathStep::Element(idx));
+                    self.compile_pattern(element, subject, path, failures, bindings)?;
+                    path.pop();
+                }
+            }
+            Pattern::Dictionary(_, pairs) => {
+                self.load_path(subject, path)?;
+                self.emit(Opcode::MatchDict, &[])?;
+                failures.push(self.emit(Opcode::JumpNotTruthy, &[9999])?);
+
+                for (key, value) in pairs {
+                    self.load_path(subject, path)?;
+                    self.compile_expression(Some(key))?;
+                    self.emit(Opcode::MatchKey, &[])?;
+                    failures.push(self.emit(Opcode::JumpNotTruthy, &[9999])?);
+
+                    path.push(PathStep::Key(key));
+                    self.compile_pattern(value, subject, path, failures, bindings)?;
+                    path.pop();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn load_path(&mut self, subject: &Symbol, path: &[PathStep]) -> Result<(), String> {
+        self.load_symbol(subject)?;
+        for step in path {
+            match step {
+                PathStep::Element(idx) => {
+                    let constant = self.add_constant(Object::Integer(*idx as i64));
+                    self.emit(Opcode::Constant, &[constant])?;
+                }
+                PathStep::Key(key) => self.compile_expression(Some(key))?,
+            }
+            self.emit(Opcode::Index, &[])?;
+        }
+        Ok(())
+    }
+
+    // a block used as a value (`if` branches) leaves its last statement's value on the stack
+    fn compile_block_value(&mut self, block: &BlockStatement) -> Result<(), String> {
+        self.hoist_function_declarations(&block.statements)?;
+        for stmt in &block.statements {
+            self.compile_statement(stmt)?;
+        }
+
+        if self.last_instruction_is(Opcode::Pop) {
+            self.remove_last_instruction();
+        } else if block.statements.is_empty() {
+            self.emit(Opcode::Null, &[])?;
+        }
+        Ok(())
+    }
+
+    // `function name() {}` declarations are bound before the other statements of their program
+    // or block. A closure captures the slots of the names it uses, not their values, so
+    // declarations can refer to each other in any order
+    fn hoist_function_declarations(&mut self, statements: &[StatementNode]) -> Result<(), String> {
+        for stmt in statements {
+            if let StatementNode::Function(declaration) = stmt {
+                self.spans.push(declaration.span());
+                let symbol = self.symbol_table.define(&declaration.name.value);
+                self.compile_function_literal(&declaration.function)?;
+                self.store_symbol(&symbol)?;
+                self.spans.pop();
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_function_literal(&mut self, function_literal: &FunctionLiteral) -> Result<(), String> {
+        let mut declared = HashSet::new();
+        collect_declarations(&function_literal.body.statements, &mut declared);
+        for parameter in &function_literal.parameters {
+            collect_expression_declarations(parameter.default.as_ref(), &mut declared);
+        }
+        self.enter_scope(declared);
+
+        let parameters: Vec<Symbol> = function_literal
+            .parameters
+            .iter()
+            .map(|parameter| self.symbol_table.define(&parameter.name.value))
+            .collect();
+
+        // a parameter the call left unset takes its default, evaluated after the ones before it.
+        // As in the tree-walker, the default sees those parameters and the enclosing scopes,
+        // not its own parameter or the ones after it
+        for (idx, (parameter, symbol)) in function_literal.parameters.iter().zip(&parameters).enumerate() {
+            if let Some(default) = &parameter.default {
+                self.emit(Opcode::IsUnset, &[symbol.index])?;
+                let skip = self.emit(Opcode::JumpNotTruthy, &[9999])?;
+                self.symbol_table.hide(&parameters[idx..]);
+                let compiled = self.compile_expression(Some(default));
+                self.symbol_table.restore(&parameters[idx..]);
+                compiled?;
+                self.store_symbol(symbol)?;
+                let after = self.current_instructions().len();
+                self.change_operand(skip, after)?;
+            }
+        }
+
+        self.hoist_function_declarations(&function_literal.body.statements)?;
+        for stmt in &function_literal.body.statements {
+            self.compile_statement(stmt)?;
+        }
+
+        if self.last_instruction_is(Opcode::Pop) {
+            let position = self.scopes.last().unwrap().last_instruction.unwrap().position;
+            self.replace_instruction(position, make(Opcode::ReturnValue, &[]));
+            self.scopes.last_mut().unwrap().last_instruction = Some(EmittedInstruction {
+                opcode: Opcode::ReturnValue,
+                position,
+            });
+        }
+        if !self.last_instruction_is(Opcode::ReturnValue) {
+            self.emit(Opcode::Return, &[])?;
+        }
+
+        let (scope, symbol_table) = self.leave_scope();
+
+        let function = CompiledFunction {
+            instructions: scope.instructions,
+            spans: scope.spans,
+            num_locals: symbol_table.num_definitions(),
+            parameters: function_literal.parameters.iter().map(ParameterSpec::from).collect(),
+            local_names: symbol_table.names().clone(),
+            free_names: symbol_table.free_symbols.iter().map(|s| s.name.clone()).collect(),
+            captures: symbol_table
+                .free_symbols
+                .iter()
+                .map(|s| (s.scope, s.index))
+                .collect(),
+            literal: function_string(&function_literal.parameters, &function_literal.body),
+        };
+
+        let constant = self.add_constant(Object::CompiledFunction(Rc::new(function)));
+        self.emit(Opcode::Closure, &[constant])?;
+        Ok(())
+    }
+
+    // like `say`: sets the symbol to the value on the stack and drops the value
+    fn store_symbol(&mut self, symbol: &Symbol) -> Result<(), String> {
+        match symbol.scope {
+            SymbolScope::Global => self.emit(Opcode::SetGlobal, &[symbol.index])?,
+            _ => self.emit(Opcode::SetLocal, &[symbol.index])?,
+        };
+        self.emit(Opcode::Pop, &[])?;
+        Ok(())
+    }
+
+    fn load_symbol(&mut self, symbol: &Symbol) -> Result<(), String> {
+        match symbol.scope {
+            SymbolScope::Global => self.emit(Opcode::GetGlobal, &[symbol.index])?,
+            SymbolScope::Local => self.emit(Opcode::GetLocal, &[symbol.index])?,
+            SymbolScope::Free => self.emit(Opcode::GetFree, &[symbol.index])?,
+            SymbolScope::Builtin => self.emit(Opcode::GetBuiltin, &[symbol.index])?,
+        };
+        Ok(())
+    }
+
+    fn enter_scope(&mut self, declared: HashSet<String>) {
+        self.scopes.push(CompilationScope::default());
+        let outer = std::mem::take(&mut self.symbol_table);
+        self.symbol_table = SymbolTable::new_enclosed(outer, declared);
+    }
+
+    fn leave_scope(&mut self) -> (CompilationScope, SymbolTable) {
+        let scope = self.scopes.pop().expect("compilation scope should exist");
+        let outer = self
+            .symbol_table
+            .outer
+            .take()
+            .expect("enclosed symbol table should have an outer table");
+        let inner = std::mem::replace(&mut self.symbol_table, *outer);
+        (scope, inner)
+    }
+
+    fn add_constant(&mut self, object: Object) -> usize {
+        let key = ConstantKey::of(&object);
+        if let Some(idx) = key.as_ref().and_then(|key| self.constant_slots.get(key)) {
+            return *idx;
+        }
+
+        self.constants.push(object);
+        let idx = self.constants.len() - 1;
+        if let Some(key) = key {
+            self.constant_slots.insert(key, idx);
+        }
+        idx
+    }
+
+    fn current_instructions(&self) -> &Instructions {
+        &self.scopes.last().unwrap().instructions
+    }
+
+    fn emit(&mut self, opcode: Opcode, operands: &[usize]) -> Result<usize, String> {
+        check_operands(opcode, operands)?;
+        let instruction = make(opcode, operands);
+        let span = self.spans.last().copied().unwrap_or_default();
+        let scope = self.scopes.last_mut().unwrap();
+
+        let position = scope.instructions.len();
+        scope.instructions.extend(instruction);
+        scope.spans.push((position, span));
+        scope.last_instruction = Some(EmittedInstruction { opcode, position });
+
+        Ok(position)
+    }
+
+    fn last_instruction_is(&self, opcode: Opcode) -> bool {
+        match self.scopes.last().unwrap().last_instruction {
+            Some(last) => last.opcode == opcode,
+            None => false,
+        }
+    }
+
+    // only used to drop a trailing `Pop`, so the instruction before it is not tracked
+    fn remove_last_instruction(&mut self) {
+        let scope = self.scopes.last_mut().unwrap();
+        if let Some(last) = scope.last_instruction.take() {
+            scope.instructions.truncate(last.position);
+            scope.spans.retain(|(offset, _)| *offset < last.position);
+        }
+    }
+
+    fn replace_instruction(&mut self, position: usize, instruction: Instructions) {
+        let scope = self.scopes.last_mut().unwrap();
+        scope.instructions[position..position + instruction.len()].copy_from_slice(&instruction);
+    }
+
+    fn change_operand(&mut self, position: usize, operand: usize) -> Result<(), String> {
+        let opcode = Opcode::from_byte(self.current_instructions()[position])
+            .expect("instruction to patch should be a valid opcode");
+        check_operands(opcode, &[operand])?;
+        self.replace_instruction(position, make(opcode, &[operand]));
+        Ok(())
+    }
+}
+
+fn infix_opcode(operator: &str) -> Result<Opcode, String> {
+    match operator {
+        "+" => Ok(Opcode::Add),
+        "-" => Ok(Opcode::Sub),
+        "*" => Ok(Opcode::Mul),
+        "/" => Ok(Opcode::Div),
+        "%" => Ok(Opcode::Mod),
+        "**" => Ok(Opcode::Pow),
+        "&" => Ok(Opcode::BitAnd),
+        "|" => Ok(Opcode::BitOr),
+        "^" => Ok(Opcode::BitXor),
+        "<<" => Ok(Opcode::ShiftLeft),
+        ">>" => Ok(Opcode::ShiftRight),
+        "==" => Ok(Opcode::Equal),
+        "!=" => Ok(Opcode::NotEqual),
+        "<" => Ok(Opcode::LessThan),
+        ">" => Ok(Opcode::GreaterThan),
+        "<=" => Ok(Opcode::LessEqual),
+        ">=" => Ok(Opcode::GreaterEqual),
+        other => Err(format!("unknown operator: {}", other)),
+    }
+}
+
+// every name bound by `say` in a function body, including inside `if` blocks, which share
+// the function's scope. Nested function literals have scopes of their own and are skipped
+fn collect_declarations(statements: &[StatementNode], declared: &mut HashSet<String>) {
+    for stmt in statements {
+        match stmt {
+            StatementNode::Say(say_stmt) => {
+                declared.insert(say_stmt.name.value.clone());
+                collect_expression_declarations(say_stmt.value.as_ref(), declared);
+            }
+            StatementNode::Return(return_stmt) => {
+                collect_expression_declarations(return_stmt.return_value.as_ref(), declared);
+            }
+            StatementNode::Expression(exp_stmt) => {
+                collect_expression_declarations(exp_stmt.expression.as_ref(), declared);
+            }
+            StatementNode::Block(block) => collect_declarations(&block.statements, declared),
+            StatementNode::While(while_stmt) => {
+                collect_expression_declarations(Some(&while_stmt.condition), declared);
+                collect_declarations(&while_stmt.body.statements, declared);
+            }
+            StatementNode::Function(declaration) => {
+                declared.insert(declaration.name.value.clone());
+            }
+            StatementNode::For(for_stmt) => {
+                declared.insert(for_stmt.variable.value.clone());
+                collect_expression_declarations(Some(&for_stmt.iterable), declared);
+                collect_declarations(&for_stmt.body.statements, declared);
+            }
+            StatementNode::Break(_) | StatementNode::Continue(_) => {}
+        }
+    }
+}
+
+fn collect_expression_declarations(expression: Option<&ExpressionNode>, declared: &mut HashSet<String>) {
+    let exp = match expression {
+        Some(exp) => exp,
+        None => return,
+    };
+
+    match exp {
+        ExpressionNode::Prefix(prefix_exp) => {
+            collect_expression_declarations(Some(&prefix_exp.right), declared)
+        }
+        ExpressionNode::Infix(infix_exp) => {
+            collect_expression_declarations(Some(&infix_exp.left), declared);
+            collect_expression_declarations(Some(&infix_exp.right), declared);
+        }
+        ExpressionNode::IfExpressionNode(if_exp) => {
+            collect_expression_declarations(Some(&if_exp.condition), declared);
+            collect_declarations(&if_exp.consequence.statements, declared);
+            if let Some(alternative) = &if_exp.alternative {
+                collect_declarations(&alternative.statements, declared);
+            }
+        }
+        ExpressionNode::Match(match_exp) => {
+            collect_expression_declarations(Some(&match_exp.subject), declared);
+            for arm in &match_exp.arms {
+                collect_expression_declarations(arm.guard.as_ref(), declared);
+                collect_expression_declarations(Some(&arm.value), declared);
+            }
+        }
+        ExpressionNode::Call(call_exp) => {
+            collect_expression_declarations(Some(&call_exp.function), declared);
+            for argument in &call_exp.arguments {
+                collect_expression_declarations(Some(argument), declared);
+            }
+            for (_, value) in &call_exp.named {
+                collect_expression_declarations(Some(value), declared);
+            }
+        }
+        ExpressionNode::InterpolatedString(interpolated) => {
+            for part in &interpolated.parts {
+                if let StringPart::Expression(expression) = part {
+                    collect_expression_declarations(Some(expression), declared);
+                }
+            }
+        }
+        ExpressionNode::Array(array_literal) => {
+            for element in &array_literal.elements {
+                collect_expression_declarations(Some(element), declared);
+            }
+        }
+        ExpressionNode::Index(index_exp) => {
+            collect_expression_declarations(Some(&index_exp.left), declared);
+            collect_expression_declarations(Some(&index_exp.index), declared);
+        }
+        ExpressionNode::Dictionary(dictionary) => {
+            for (key, value) in &dictionary.pairs {
+                collect_expression_declarations(Some(key), declared);
+                collect_expression_declarations(Some(value), declared);
+            }
+        }
+        ExpressionNode::Assign(assign_exp) => {
+            collect_expression_declarations(Some(&assign_exp.target), declared);
+            collect_expression_declarations(Some(&assign_exp.value), declared);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use crate::{code::disassemble, lexer::Lexer, object::Object, parser::Parser};
+
+    use super::{Compiler, Symbol, SymbolScope, SymbolTable};
+
+    fn compile(input: &str) -> Compiler {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+        assert!(parser.errors().is_empty(), "parser errors: {:?}", parser.errors());
+
+        let mut compiler = Compiler::new();
+        compiler.compile(&program).expect("program should compile");
+        compiler
+    }
+
+    #[test]
+    fn test_compile_main_instructions() {
+        let tests = vec![
+            (
+                "1 + 2",
+                "0000 OpConstant 0
+0003 OpConstant 1
+0006 OpAdd
+0007 OpPop
+",
+            ),
+            (
+                "if (true) { 10 }; 3333;",
+                "0000 OpTrue
+0001 OpJumpNotTruthy 10
+0004 OpConstant 0
+0007 OpJump 11
+0010 OpNull
+0011 OpPop
+0012 OpConstant 1
+0015 OpPop
+",
+            ),
+            (
+                "true && false",
+                "0000 OpTrue
+0001 OpJumpNotTruthy 12
+0004 OpFalse
+0005 OpJumpNotTruthy 12
+0008 OpTrue
+0009 OpJump 13
+0012 OpFalse
+0013 OpPop
+",
+            ),
+            (
+                "say one = 1; say two = one; -two",
+                "0000 OpConstant 0
+0003 OpSetGlobal 0
+0006 OpPop
+0007 OpGetGlobal 0
+0010 OpSetGlobal 1
+0013 OpPop
+0014 OpGetGlobal 1
+0017 OpMinus
+0018 OpPop
+",
+            ),
+            (
+                r#"len([1, 2])["a"]"#,
+                "0000 OpGetBuiltin 0
+0002 OpConstant 0
+0005 OpConstant 1
+0008 OpArray 2
+0011 OpCall 1
+0013 OpConstant 2
+0016 OpIndex
+0017 OpPop
+",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let bytecode = compile(input).bytecode();
+            assert_eq!(
+                disassemble(&bytecode.main.instructions),
+                expected,
+                "wrong instructions for {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_compile_closures() {
+        let input = "function(a) { function(b) { a + b } }";
+        let bytecode = compile(input).bytecode();
+
+        let functions: Vec<String> = bytecode
+            .constants
+            .iter()
+            .filter_map(|constant| match constant {
+                Object::CompiledFunction(function) => Some(disassemble(&function.instructions)),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(
+            functions,
+            vec![
+                "0000 OpGetFree 0
+0002 OpGetLocal 0
+0004 OpAdd
+0005 OpReturnValue
+"
+                .to_string(),
+                "0000 OpClosure 0
+0003 OpReturnValue
+"
+                .to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_forward_declared_locals() {
+        let input = "function() {
+            say isEven = function(n) { isOdd(n) };
+            say isOdd = function(n) { isEven(n) };
+        }";
+        let bytecode = compile(input).bytecode();
+
+        let is_even = match &bytecode.constants[0] {
+            Object::CompiledFunction(function) => function.clone(),
+            other => panic!("constant is not a function, got={:?}", other),
+        };
+
+        assert_eq!(is_even.free_names, vec!["isOdd".to_string()]);
+        assert_eq!(is_even.captures, vec![(SymbolScope::Local, 0)]);
+    }
+
+    #[test]
+    fn test_resolve_nested_free() {
+        let mut global = SymbolTable::new_global();
+        global.define("a");
+        let mut first = SymbolTable::new_enclosed(global, HashSet::new());
+        first.define("b");
+        let mut second = SymbolTable::new_enclosed(first, HashSet::new());
+        second.define("c");
+
+        let expected = vec![
+            ("a", SymbolScope::Global, 0),
+            ("b", SymbolScope::Free, 0),
+            ("c", SymbolScope::Local, 0),
+            ("len", SymbolScope::Builtin, 0),
+        ];
+
+        for (name, scope, index) in expected {
+            let symbol = second.resolve(name).expect("name should resolve");
+            assert_eq!(
+                symbol,
+                Symbol {
+                    name: name.to_string(),
+                    scope,
+                    index
+                }
+            );
+        }
+
+        assert_eq!(second.resolve("missing"), None);
+        let declared = second.resolve_or_declare("missing");
+        assert_eq!(declared.scope, SymbolScope::Global);
+    }
+
+    #[test]
+    fn test_operand_limits() {
+        // identifiers cannot hold digits, so locals are named `va`, `vb`, ..., `vaa`, ...
+        fn name(idx: usize) -> String {
+            let mut name = String::new();
+            let mut rest = idx + 1;
+            while rest > 0 {
+                rest -= 1;
+                name.insert(0, (b'a' + (rest % 26) as u8) as char);
+                rest /= 26;
+            }
+            format!("v{}", name)
+        }
+        let locals = |count: usize| {
+            let statements: Vec<String> = (0..count).map(|idx| format!("say {} = 0;", name(idx))).collect();
+            format!("function() {{ {} }}", statements.join(" "))
+        };
+        let call = |count: usize| format!("say f = function(...a) {{ a }}; f({})", vec!["1"; count].join(", "));
+        let constants = |count: usize| (0..count).map(|idx| format!("{};", idx)).collect::<String>();
+        let jump = |count: usize| format!("if (true) {{ {} }}", "1; ".repeat(count));
+
+        let tests = vec![
+            (locals(256), Ok(())),
+            (locals(257), Err("too many local variables in a function for the VM: got=256, max=255")),
+            (call(255), Ok(())),
+            (call(256), Err("too many arguments in a call for the VM: got=256, max=255")),
+            (constants(65536), Ok(())),
+            (constants(65537), Err("too many constants for the VM: got=65536, max=65535")),
+            (jump(16382), Ok(())),
+            (jump(16383), Err("too many instructions to jump over for the VM: got=65538, max=65535")),
+        ];
+
+        for (input, expected) in tests {
+            let program = Parser::new(Lexer::new(&input)).parse_program().unwrap();
+            let result = Compiler::new().compile(&program);
+            assert_eq!(result, expected.map_err(String::from), "wrong result for {}...", &input[..40]);
+        }
+    }
+
+    #[test]
+    fn test_constants_are_shared() {
+        let compiler = compile(r#"1; 1.0; "a"; 1; "a"; 1.0; 2"#);
+        assert_eq!(
+            format!("{:?}", compiler.constants),
+            r#"[Integer(1), Float(1.0), StringObject("a"), Integer(2)]"#
+        );
+
+        // the REPL hands the pool to the compiler of its next input
+        let (symbol_table, constants) = compiler.into_state();
+        let mut compiler = Compiler::new_with_state(symbol_table, constants);
+        let program = Parser::new(Lexer::new("2; 3; 1")).parse_program().unwrap();
+        compiler.compile(&program).unwrap();
+        assert_eq!(compiler.constants.len(), 5);
+    }
+}
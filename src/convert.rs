@@ -0,0 +1,448 @@
+use std::{collections::HashMap, fmt::Display, hash::BuildHasher};
+
+use crate::{
+    bigint::BigInt,
+    object::{DictPair, Dictado, Object},
+};
+
+//      why an `Object` could not be turned into the Rust type asked for
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConversionError {
+    WrongType { expected: &'static str, found: String },
+    OutOfRange { value: String, target: &'static str },
+    WrongLength { expected: usize, found: usize },
+    MissingKey(String),
+    At { path: String, error: Box<ConversionError> },     // failed inside an element or field
+}
+
+impl ConversionError {
+    fn wrong_type(expected: &'static str, found: &Object) -> ConversionError {
+        ConversionError::WrongType {
+            expected,
+            found: found.object_type(),
+        }
+    }
+
+    // `path` is the element or field the error happened in, like `[2]` or `.name`
+    pub fn at(self, path: String) -> ConversionError {
+        match self {
+            ConversionError::At { path: inner, error } => ConversionError::At {
+                path: format!("{}{}", path, inner),
+                error,
+            },
+            error => ConversionError::At {
+                path,
+                error: Box::new(error),
+            },
+        }
+    }
+}
+
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WrongType { expected, found } => write!(f, "expected {}, got {}", expected, found),
+            Self::OutOfRange { value, target } => write!(f, "{} does not fit in {}", value, target),
+            Self::WrongLength { expected, found } => {
+                write!(f, "expected an array of {} elements, got {}", expected, found)
+            }
+            Self::MissingKey(key) => write!(f, "missing key: {}", key),
+            Self::At { path, error } => write!(f, "at {}: {}", path, error),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+pub trait IntoObject {
+    fn into_object(self) -> Object;
+}
+
+pub trait FromObject: Sized {
+    fn from_object(object: &Object) -> Result<Self, ConversionError>;
+}
+
+impl IntoObject for Object {
+    fn into_object(self) -> Object {
+        self
+    }
+}
+
+impl FromObject for Object {
+    fn from_object(object: &Object) -> Result<Self, ConversionError> {
+        Ok(object.clone())
+    }
+}
+
+// integers that always fit in an `i64`
+macro_rules! small_integer_conversions {
+    ($($int:ty),*) => {$(
+        impl IntoObject for $int {
+            fn into_object(self) -> Object {
+                Object::Integer(self as i64)
+            }
+        }
+    )*};
+}
+
+small_integer_conversions!(i8, i16, i32, i64, u8, u16, u32);
+
+// integers that may not fit in an `i64` become an `Object::BigInt`
+macro_rules! large_integer_conversions {
+    ($($int:ty),*) => {$(
+        impl IntoObject for $int {
+            fn into_object(self) -> Object {
+                match i64::try_from(self) {
+                    Ok(value) => Object::Integer(value),
+                    Err(_) => Object::from_bigint(self.to_string().parse::<BigInt>().expect("an integer is a valid BigInt")),
+                }
+            }
+        }
+    )*};
+}
+
+large_integer_conversions!(i128, u64, u128, usize, isize);
+
+macro_rules! integer_from_object {
+    ($($int:ty),*) => {$(
+        impl FromObject for $int {
+            fn from_object(object: &Object) -> Result<Self, ConversionError> {
+                let out_of_range = |value: String| ConversionError::OutOfRange {
+                    value,
+                    target: stringify!($int),
+                };
+                match object {
+                    Object::Integer(value) => <$int>::try_from(*value).map_err(|_| out_of_range(value.to_string())),
+                    Object::BigInt(value) => value.to_string().parse::<$int>().map_err(|_| out_of_range(value.to_string())),
+                    other => Err(ConversionError::wrong_type("INTEGER", other)),
+                }
+            }
+        }
+    )*};
+}
+
+integer_from_object!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+impl IntoObject for f64 {
+    fn into_object(self) -> Object {
+        Object::Float(self)
+    }
+}
+
+impl IntoObject for f32 {
+    fn into_object(self) -> Object {
+        Object::Float(self as f64)
+    }
+}
+
+// integers are accepted where a float is expected, as arithmetic does
+impl FromObject for f64 {
+    fn from_object(object: &Object) -> Result<Self, ConversionError> {
+        match object {
+            Object::Float(value) => Ok(*value),
+            Object::Integer(value) => Ok(*value as f64),
+            Object::BigInt(value) => Ok(value.to_f64()),
+            other => Err(ConversionError::wrong_type("FLOAT", other)),
+        }
+    }
+}
+
+impl FromObject for f32 {
+    fn from_object(object: &Object) -> Result<Self, ConversionError> {
+        f64::from_object(object).map(|value| value as f32)
+    }
+}
+
+impl IntoObject for bool {
+    fn into_object(self) -> Object {
+        Object::Boolean(self)
+    }
+}
+
+impl FromObject for bool {
+    fn from_object(object: &Object) -> Result<Self, ConversionError> {
+        match object {
+            Object::Boolean(value) => Ok(*value),
+            other => Err(ConversionError::wrong_type("BOOLEAN", other)),
+        }
+    }
+}
+
+impl IntoObject for String {
+    fn into_object(self) -> Object {
+        Object::StringObject(self)
+    }
+}
+
+impl IntoObject for &str {
+    fn into_object(self) -> Object {
+        Object::StringObject(self.to_string())
+    }
+}
+
+impl IntoObject for char {
+    fn into_object(self) -> Object {
+        Object::StringObject(self.to_string())
+    }
+}
+
+impl FromObject for String {
+    fn from_object(object: &Object) -> Result<Self, ConversionError> {
+        match object {
+            Object::StringObject(value) => Ok(value.clone()),
+            other => Err(ConversionError::wrong_type("STRING", other)),
+        }
+    }
+}
+
+impl IntoObject for () {
+    fn into_object(self) -> Object {
+        Object::Null
+    }
+}
+
+impl FromObject for () {
+    fn from_object(object: &Object) -> Result<Self, ConversionError> {
+        match object {
+            Object::Null => Ok(()),
+            other => Err(ConversionError::wrong_type("NULL", other)),
+        }
+    }
+}
+
+// `None` is `null`, so `Some(())` cannot be told apart from `None`
+impl<T: IntoObject> IntoObject for Option<T> {
+    fn into_object(self) -> Object {
+        match self {
+            Some(value) => value.into_object(),
+            None => Object::Null,
+        }
+    }
+}
+
+impl<T: FromObject> FromObject for Option<T> {
+    fn from_object(object: &Object) -> Result<Self, ConversionError> {
+        match object {
+            Object::Null => Ok(None),
+            other => T::from_object(other).map(Some),
+        }
+    }
+}
+
+impl<T: IntoObject> IntoObject for Vec<T> {
+    fn into_object(self) -> Object {
+        Object::new_array(self.into_iter().map(IntoObject::into_object).collect())
+    }
+}
+
+impl<T: FromObject> FromObject for Vec<T> {
+    fn from_object(object: &Object) -> Result<Self, ConversionError> {
+        match object {
+            Object::Array(elements) => elements
+                .borrow()
+                .iter()
+                .enumerate()
+                .map(|(idx, element)| T::from_object(element).map_err(|e| e.at(format!("[{}]", idx))))
+                .collect(),
+            other => Err(ConversionError::wrong_type("ARRAY", other)),
+        }
+    }
+}
+
+impl<T: IntoObject, S: BuildHasher> IntoObject for HashMap<String, T, S> {
+    fn into_object(self) -> Object {
+        let pairs = self
+            .into_iter()
+            .map(|(key, value)| {
+                let key = Object::StringObject(key);
+                let dict_key = key.dict_key().expect("a string is a dictionary key");
+                (dict_key, DictPair { key, value: value.into_object() })
+            })
+            .collect();
+        Object::new_dict(pairs)
+    }
+}
+
+impl<T: FromObject, S: BuildHasher + Default> FromObject for HashMap<String, T, S> {
+    fn from_object(object: &Object) -> Result<Self, ConversionError> {
+        match object {
+            Object::DictObject(dictionary) => dictionary
+                .borrow()
+                .pairs
+                .values()
+                .map(|pair| {
+                    let key = String::from_object(&pair.key).map_err(|e| e.at(format!("key {}", pair.key)))?;
+                    let value = T::from_object(&pair.value).map_err(|e| e.at(format!("[{:?}]", key)))?;
+                    Ok((key, value))
+                })
+                .collect(),
+            other => Err(ConversionError::wrong_type("DICTIONARY", other)),
+        }
+    }
+}
+
+// tuples are arrays with exactly one element per field
+macro_rules! tuple_conversions {
+    ($len:expr => $($name:ident: $idx:tt),+) => {
+        impl<$($name: IntoObject),+> IntoObject for ($($name,)+) {
+            fn into_object(self) -> Object {
+                Object::new_array(vec![$(self.$idx.into_object()),+])
+            }
+        }
+
+        impl<$($name: FromObject),+> FromObject for ($($name,)+) {
+            fn from_object(object: &Object) -> Result<Self, ConversionError> {
+                let elements = match object {
+                    Object::Array(elements) => elements.borrow(),
+                    other => return Err(ConversionError::wrong_type("ARRAY", other)),
+                };
+                if elements.len() != $len {
+                    return Err(ConversionError::WrongLength { expected: $len, found: elements.len() });
+                }
+                Ok(($($name::from_object(&elements[$idx]).map_err(|e| e.at(format!("[{}]", $idx)))?,)+))
+            }
+        }
+    };
+}
+
+tuple_conversions!(1 => A: 0);
+tuple_conversions!(2 => A: 0, B: 1);
+tuple_conversions!(3 => A: 0, B: 1, C: 2);
+tuple_conversions!(4 => A: 0, B: 1, C: 2, D: 3);
+
+// the value under a string key of a dictionary, for the conversions `dict_conversions!` writes
+pub fn dict_field<T: FromObject>(object: &Object, key: &str) -> Result<T, ConversionError> {
+    let dictionary = match object {
+        Object::DictObject(dictionary) => dictionary,
+        other => return Err(ConversionError::wrong_type("DICTIONARY", other)),
+    };
+    let dict_key = Object::StringObject(key.to_string()).dict_key().expect("a string is a dictionary key");
+
+    match dictionary.borrow().pairs.get(&dict_key) {
+        Some(pair) => T::from_object(&pair.value).map_err(|e| e.at(format!(".{}", key))),
+        None => Err(ConversionError::MissingKey(key.to_string())),
+    }
+}
+
+//      maps a host struct to a TwoT dictionary with one key per listed field:
+//
+//          struct Point { x: i64, y: i64 }
+//          dict_conversions!(Point { x, y });
+//
+//      every field needs `IntoObject` and `FromObject`; keys missing from the
+//      dictionary are an error, extra keys are ignored
+#[macro_export]
+macro_rules! dict_conversions {
+    ($name:ident { $($field:ident),* $(,)? }) => {
+        impl $crate::convert::IntoObject for $name {
+            fn into_object(self) -> $crate::object::Object {
+                let mut fields = ::std::collections::HashMap::new();
+                $(fields.insert(
+                    String::from(stringify!($field)),
+                    $crate::convert::IntoObject::into_object(self.$field),
+                );)*
+                $crate::convert::IntoObject::into_object(fields)
+            }
+        }
+
+        impl $crate::convert::FromObject for $name {
+            fn from_object(object: &$crate::object::Object) -> Result<Self, $crate::convert::ConversionError> {
+                Ok($name {
+                    $($field: $crate::convert::dict_field(object, stringify!($field))?,)*
+                })
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::{interpreter::Interpreter, object::Object};
+
+    use super::{ConversionError, FromObject, IntoObject};
+
+    #[derive(Debug, PartialEq)]
+    struct Point {
+        x: i64,
+        y: i64,
+        label: Option<String>,
+    }
+
+    dict_conversions!(Point { x, y, label });
+
+    fn round_trip<T: IntoObject + FromObject + Clone + PartialEq + std::fmt::Debug>(value: T) {
+        let object = value.clone().into_object();
+        assert_eq!(T::from_object(&object), Ok(value));
+    }
+
+    #[test]
+    fn test_round_trips() {
+        round_trip(42i64);
+        round_trip(-7i8);
+        round_trip(u64::MAX);
+        round_trip(i128::MIN);
+        round_trip(1.5f64);
+        round_trip(true);
+        round_trip(String::from("TwoT"));
+        round_trip(vec![1u8, 2, 3]);
+        round_trip(Some(vec![Some(1i32), None]));
+        round_trip((1i64, String::from("a"), false));
+        round_trip(HashMap::from([(String::from("a"), vec![1i64]), (String::from("b"), vec![])]));
+
+        assert_eq!(u64::MAX.into_object().object_type(), "INTEGER");
+        assert_eq!("text".into_object(), Object::StringObject(String::from("text")));
+        assert_eq!(().into_object(), Object::Null);
+    }
+
+    #[test]
+    fn test_conversion_errors() {
+        let tests = vec![
+            (u8::from_object(&300i64.into_object()).unwrap_err(), "300 does not fit in u8"),
+            (i64::from_object(&u64::MAX.into_object()).unwrap_err(), "18446744073709551615 does not fit in i64"),
+            (String::from_object(&1i64.into_object()).unwrap_err(), "expected STRING, got INTEGER"),
+            (
+                Vec::<i64>::from_object(&vec![1i64.into_object(), "two".into_object()].into_object()).unwrap_err(),
+                "at [1]: expected INTEGER, got STRING",
+            ),
+            (
+                <(i64, i64)>::from_object(&vec![1i64].into_object()).unwrap_err(),
+                "expected an array of 2 elements, got 1",
+            ),
+            (
+                Vec::<Point>::from_object(&vec![HashMap::from([(String::from("x"), 1i64)])].into_object()).unwrap_err(),
+                "at [0]: missing key: y",
+            ),
+        ];
+
+        for (error, expected) in tests {
+            assert_eq!(error.to_string(), expected);
+        }
+        assert_eq!(
+            f64::from_object(&true.into_object()),
+            Err(ConversionError::WrongType { expected: "FLOAT", found: String::from("BOOLEAN") })
+        );
+    }
+
+    #[test]
+    fn test_dict_conversions() {
+        let point = Point { x: 1, y: 2, label: None };
+        let object = point.into_object();
+        assert_eq!(object.object_type(), "DICTIONARY");
+
+        let mut interpreter = Interpreter::new();
+        interpreter.set_global("p", object);
+        let moved = interpreter.eval_str("{\"x\": p[\"x\"] + 10, \"y\": p[\"y\"], \"label\": \"moved\", \"extra\": 0}").unwrap();
+        assert_eq!(
+            Point::from_object(&moved),
+            Ok(Point { x: 11, y: 2, label: Some(String::from("moved")) })
+        );
+
+        let nested = interpreter.eval_str("[p, {\"x\": 1, \"y\": \"two\"}]").unwrap();
+        assert_eq!(
+            Vec::<Point>::from_object(&nested).unwrap_err().to_string(),
+            "at [1].y: expected INTEGER, got STRING"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use std::fmt::Display;
+
+use crate::{lexer::LexerError, parser::ParserError, token::Span};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    IllegalCharacter,   // lexer
+    UnterminatedComment,
+    UnterminatedString,
+    InvalidEscape,
+    UnexpectedToken,    // parser
+    ExpectedExpression,
+    InvalidLiteral,
+    InvalidAssignmentTarget,
+    Runtime,            // evaluator
+    TypeMismatch,
+    UnknownOperator,
+    UnknownIdentifier,
+    NotAFunction,
+    UnusableKey,
+    WrongArgumentCount,
+    DivisionByZero,
+    IntegerOverflow,
+    NoMatchingArm,
+}
+
+impl ErrorCode {
+    // runtime errors are plain `Object::Error` strings, so they are classified by their wording
+    pub fn from_runtime_message(message: &str) -> ErrorCode {
+        if message.starts_with("type mismatch") {
+            ErrorCode::TypeMismatch
+        } else if message.starts_with("unknown operator") {
+            ErrorCode::UnknownOperator
+        } else if message.starts_with("Identifier not found")
+            || message.starts_with("cannot assign to undeclared name")
+        {
+            ErrorCode::UnknownIdentifier
+        } else if message.starts_with("not a function") {
+            ErrorCode::NotAFunction
+        } else if message.starts_with("unusable as Dictionary key") {
+            ErrorCode::UnusableKey
+        } else if message.starts_with("wrong number of arguments")
+            || message.starts_with("unknown parameter name")
+            || message.starts_with("multiple values for parameter")
+            || message.starts_with("named arguments are not supported")
+        {
+            ErrorCode::WrongArgumentCount
+        } else if message.starts_with("division by zero") {
+            ErrorCode::DivisionByZero
+        } else if message.starts_with("integer overflow") {
+            ErrorCode::IntegerOverflow
+        } else if message.starts_with("no match arm") {
+            ErrorCode::NoMatchingArm
+        } else {
+            ErrorCode::Runtime
+        }
+    }
+
+    fn help(&self) -> Option<&'static str> {
+        match self {
+            Self::UnknownIdentifier => Some("declare the name first, e.g. `say name = value;`"),
+            Self::UnusableKey => Some("only integers, booleans and strings can be dictionary keys"),
+            Self::TypeMismatch => Some("both operands of an infix operator must have the same type"),
+            Self::IntegerOverflow => Some("run without `--no-bigint` to continue with arbitrary-precision integers"),
+            Self::NoMatchingArm => Some("add a `_ => ...` arm to handle every other value"),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let code = match self {
+            Self::IllegalCharacter => "E0001",
+            Self::UnterminatedComment => "E0002",
+            Self::UnterminatedString => "E0003",
+            Self::InvalidEscape => "E0004",
+            Self::UnexpectedToken => "E0101",
+            Self::ExpectedExpression => "E0102",
+            Self::InvalidLiteral => "E0103",
+            Self::InvalidAssignmentTarget => "E0104",
+            Self::Runtime => "E0200",
+            Self::TypeMismatch => "E0201",
+            Self::UnknownOperator => "E0202",
+            Self::UnknownIdentifier => "E0203",
+            Self::NotAFunction => "E0204",
+            Self::UnusableKey => "E0205",
+            Self::WrongArgumentCount => "E0206",
+            Self::DivisionByZero => "E0207",
+            Self::IntegerOverflow => "E0208",
+            Self::NoMatchingArm => "E0209",
+        };
+        write!(f, "{}", code)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub code: ErrorCode,
+    pub message: String,
+    pub span: Span,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(code: ErrorCode, message: String, span: Span) -> Diagnostic {
+        Diagnostic {
+            code,
+            message,
+            span,
+            help: code.help().map(String::from),
+        }
+    }
+
+    pub fn runtime(message: &str, span: Span) -> Diagnostic {
+        Diagnostic::new(ErrorCode::from_runtime_message(message), message.to_string(), span)
+    }
+
+    pub fn with_help(mut self, help: &str) -> Diagnostic {
+        self.help = Some(help.to_string());
+        self
+    }
+
+    //      error[E0201]: type mismatch: INTEGER + STRING
+    //       --> script.twot:2:1
+    //        |
+    //      2 | x + "a"
+    //        | ^^^^^^^
+    //        = help: ...
+    pub fn render(&self, origin: &str, source: &str) -> String {
+        let line_number = self.span.start.line.max(1);
+        let line = source.lines().nth(line_number - 1).unwrap_or("");
+        let gutter = " ".repeat(line_number.to_string().len());
+
+        let mut out = String::new();
+        out.push_str(format!("error[{}]: {}\n", self.code, self.message).as_str());
+        out.push_str(
+            format!(
+                "{}--> {}:{}:{}\n",
+                gutter, origin, line_number, self.span.start.column
+            )
+            .as_str(),
+        );
+        out.push_str(format!("{} |\n", gutter).as_str());
+        out.push_str(format!("{} | {}", line_number, line).trim_end());
+        out.push('\n');
+        out.push_str(format!("{} | {}\n", gutter, self.underline(line)).as_str());
+
+        if let Some(help) = &self.help {
+            out.push_str(format!("{} = help: {}\n", gutter, help).as_str());
+        }
+
+        out
+    }
+
+    // carets under the span, clipped to the first line; tabs are kept so the carets stay aligned
+    fn underline(&self, line: &str) -> String {
+        let start = self.span.start.column.max(1) - 1;
+        let line_length = line.chars().count();
+
+        let end = if self.span.end.line == self.span.start.line {
+            self.span.end.column.max(1) - 1
+        } else {
+            line_length
+        };
+        let width = end.saturating_sub(start).max(1);
+
+        let padding: String = line
+            .chars()
+            .take(start)
+            .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        format!("{}{}", padding, "^".repeat(width))
+    }
+}
+
+impl From<&LexerError> for Diagnostic {
+    fn from(error: &LexerError) -> Diagnostic {
+        Diagnostic::new(error.code, error.message.clone(), error.span)
+    }
+}
+
+impl From<&ParserError> for Diagnostic {
+    fn from(error: &ParserError) -> Diagnostic {
+        Diagnostic::new(error.code, error.message.clone(), error.span)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        evaluator::Evaluator,
+        lexer::Lexer,
+        object::Object,
+        parser::Parser,
+    };
+
+    use super::{Diagnostic, ErrorCode};
+
+    fn parser_diagnostics(input: &str) -> Vec<Diagnostic> {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        parser.parse_program();
+        parser.errors().iter().map(Diagnostic::from).collect()
+    }
+
+    #[test]
+    fn test_render_parser_error() {
+        let input = "say a = 1;\nsay = 5;";
+        let diagnostics = parser_diagnostics(input);
+
+        assert_eq!(diagnostics[0].code, ErrorCode::UnexpectedToken);
+        assert_eq!(
+            diagnostics[0].render("test.twot", input),
+            "error[E0101]: expected next token to be Ident, got== instead
+ --> test.twot:2:5
+  |
+2 | say = 5;
+  |     ^
+"
+        );
+    }
+
+    #[test]
+    fn test_render_lexer_error() {
+        let input = "say a = 1 # 2;";
+        let diagnostics = parser_diagnostics(input);
+
+        assert_eq!(diagnostics[0].code, ErrorCode::IllegalCharacter);
+        assert_eq!(
+            diagnostics[0].render("<repl>", input),
+            "error[E0001]: illegal character `#`
+ --> <repl>:1:11
+  |
+1 | say a = 1 # 2;
+  |           ^
+"
+        );
+    }
+
+    #[test]
+    fn test_render_runtime_error() {
+        let input = "say a = 1;\n\tsay b = a + \"two\";";
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+        let mut evaluator = Evaluator::new();
+
+        let message = match evaluator.eval_program(program) {
+            Object::Error(message) => message,
+            other => panic!("expected an error, got={}", other),
+        };
+        let diagnostic = Diagnostic::runtime(&message, evaluator.error_span().unwrap());
+
+        assert_eq!(
+            diagnostic.render("test.twot", input),
+            "error[E0201]: type mismatch: INTEGER + STRING
+ --> test.twot:2:10
+  |
+2 | \tsay b = a + \"two\";
+  | \t        ^^^^^^^^^
+  = help: both operands of an infix operator must have the same type
+"
+        );
+    }
+
+    #[test]
+    fn test_with_help() {
+        let diagnostic = Diagnostic::runtime("Identifier not found: x", Default::default())
+            .with_help("try again");
+
+        assert_eq!(diagnostic.code, ErrorCode::UnknownIdentifier);
+        assert_eq!(diagnostic.help, Some(String::from("try again")));
+        assert!(diagnostic.render("<repl>", "x").ends_with("  = help: try again\n"));
+    }
+}
@@ -0,0 +1,174 @@
+use std::str::FromStr;
+
+use crate::{
+    ast::Program,
+    compiler::{Compiler, SymbolScope, SymbolTable},
+    evaluator::{Evaluator, IntegerOverflow},
+    object::Object,
+    token::Span,
+    vm::VM,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    #[default]
+    TreeWalker,
+    Vm,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tree" => Ok(Backend::TreeWalker),
+            "vm" => Ok(Backend::Vm),
+            other => Err(format!("unknown backend `{}`, expected `tree` or `vm`", other)),
+        }
+    }
+}
+
+//      runs programs on either backend. State is kept between `run` calls,
+//      so names defined by one REPL input are visible to the next
+pub enum Engine {
+    TreeWalker(Evaluator),
+    Vm {
+        symbol_table: SymbolTable,
+        constants: Vec<Object>,
+        vm: Box<VM>,
+        error_span: Option<Span>,
+    },
+}
+
+impl Engine {
+    pub fn new(backend: Backend) -> Engine {
+        match backend {
+            Backend::TreeWalker => Engine::TreeWalker(Evaluator::new()),
+            Backend::Vm => Engine::Vm {
+                symbol_table: SymbolTable::new_global(),
+                constants: vec![],
+                vm: Box::new(VM::new()),
+                error_span: None,
+            },
+        }
+    }
+
+    pub fn set_integer_overflow(&mut self, integer_overflow: IntegerOverflow) {
+        match self {
+            Engine::TreeWalker(evaluator) => evaluator.set_integer_overflow(integer_overflow),
+            Engine::Vm { vm, .. } => vm.set_integer_overflow(integer_overflow),
+        }
+    }
+
+    // the VM keeps its frames on the heap, so only the tree-walker has a stack to limit
+    pub fn set_stack_limit(&mut self, bytes: usize) {
+        if let Engine::TreeWalker(evaluator) = self {
+            evaluator.set_stack_limit(bytes);
+        }
+    }
+
+    pub fn run(&mut self, program: Program) -> Object {
+        match self {
+            Engine::TreeWalker(evaluator) => evaluator.eval_program(program),
+            Engine::Vm {
+                symbol_table,
+                constants,
+                vm,
+                error_span,
+            } => {
+                let mut compiler = Compiler::new_with_state(
+                    std::mem::take(symbol_table),
+                    std::mem::take(constants),
+                );
+                let compiled = compiler.compile(&program);
+                let bytecode = compiler.bytecode();
+                *error_span = compiler.error_span();
+                (*symbol_table, *constants) = compiler.into_state();
+
+                if let Err(message) = compiled {
+                    return Object::Error(message);
+                }
+
+                let result = vm.run(bytecode);
+                *error_span = vm.error_span();
+                result
+            }
+        }
+    }
+
+    // binds a global name, as if a program had declared it with `say`
+    pub fn set_global(&mut self, name: &str, value: Object) {
+        match self {
+            Engine::TreeWalker(evaluator) => evaluator.set_global(name, value),
+            Engine::Vm { symbol_table, vm, .. } => {
+                let symbol = symbol_table.define(name);
+                vm.set_global(symbol.index, value);
+            }
+        }
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Object> {
+        match self {
+            Engine::TreeWalker(evaluator) => evaluator.get_global(name),
+            Engine::Vm { symbol_table, vm, .. } => {
+                let symbol = symbol_table.lookup(name)?;
+                match symbol.scope {
+                    SymbolScope::Builtin => Some(vm.builtin(symbol.index)),
+                    _ => vm.global(symbol.index),
+                }
+            }
+        }
+    }
+
+    pub fn call_function(&mut self, function: &Object, arguments: Vec<Object>) -> Object {
+        match self {
+            Engine::TreeWalker(evaluator) => evaluator.call_function(function, arguments),
+            Engine::Vm { vm, error_span, .. } => {
+                let result = vm.call_function(function, arguments);
+                *error_span = vm.error_span();
+                result
+            }
+        }
+    }
+
+    pub fn error_span(&self) -> Option<Span> {
+        match self {
+            Engine::TreeWalker(evaluator) => evaluator.error_span(),
+            Engine::Vm { error_span, .. } => *error_span,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{lexer::Lexer, parser::Parser};
+
+    use super::{Backend, Engine};
+
+    #[test]
+    fn test_state_is_kept_between_runs() {
+        for backend in [Backend::TreeWalker, Backend::Vm] {
+            let mut engine = Engine::new(backend);
+
+            for (input, expected) in [
+                ("say a = 2;", "2"),
+                ("say double = function(x) { x * a };", "function(x) { \n(x * a)}\n"),
+                ("double(21)", "42"),
+                ("say a = 3; double(2)", "6"),
+                ("missing", "ERROR: Identifier not found: missing"),
+            ] {
+                let mut parser = Parser::new(Lexer::new(input));
+                let program = parser.parse_program().unwrap();
+                let result = engine.run(program);
+                assert_eq!(result.to_string(), expected, "wrong result for {:?} on {:?}", input, backend);
+            }
+        }
+    }
+
+    #[test]
+    fn test_backend_from_str() {
+        assert_eq!("tree".parse(), Ok(Backend::TreeWalker));
+        assert_eq!("vm".parse(), Ok(Backend::Vm));
+        assert!("jit".parse::<Backend>().is_err());
+    }
+}
@@ -1,27 +1,95 @@
-use std::{collections::HashMap, ops::Deref};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
 use crate::
-{ast::{BlockStatement, ExpressionNode, Identifier, IfExpression, Program, StatementNode}, object::{DictPair, DictStruct, Dictado, Environment, Function, Object}};
+{ast::{AssignExpression, BlockStatement, CallExpression, DictLiteral, ExpressionNode, ForStatement, Identifier, IfExpression, IndexExpression, InfixExpression, InterpolatedString, MatchExpression, Node, Pattern, PrefixExpression, Program, StatementNode, StringPart, WhileStatement}, bigint::BigInt, object::{CallContext, DictPair, Dictado, Environment, Function, Object, ParameterSpec}, token::Span};
 
 const TRUE: Object = Object::Boolean(true);
 const FALSE: Object = Object::Boolean(false);
 pub const NULL: Object = Object::Null;
 
+// nested calls deeper than this give a `stack overflow` error, like the VM's frame limit
+pub const MAX_CALL_DEPTH: usize = 1024;
+
+// the native stack, in bytes, nested calls may use before they give a `stack overflow` error.
+// Every call takes a few native stack frames, so this fits the 2 MB Rust gives a spawned
+// thread; hosts running the tree-walker on a bigger stack raise it with `set_stack_limit`
+pub const DEFAULT_STACK_LIMIT: usize = 1024 * 1024;
+
+//      what integer arithmetic does when a result does not fit in an `i64`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntegerOverflow {
+    Error,
+    #[default]
+    Promote,    // continue with an arbitrary-precision integer
+}
 
 pub struct Evaluator{
-    environment: Environment,
+    environment: Rc<RefCell<Environment>>,
+    error_span: Option<Span>,
+    integer_overflow: IntegerOverflow,
+    call_depth: usize,
+    stack_base: usize,  // the native stack address of the outermost call
+    stack_limit: usize,
 }
 
 
+impl CallContext for Evaluator {
+    fn apply(&mut self, function: &Object, arguments: Vec<Object>) -> Object {
+        self.apply_function(function.clone(), arguments, vec![])
+    }
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Evaluator{
     pub fn new() -> Evaluator {
         Evaluator {
             environment: Environment::new_environment(),
+            error_span: None,
+            integer_overflow: IntegerOverflow::default(),
+            call_depth: 0,
+            stack_base: 0,
+            stack_limit: DEFAULT_STACK_LIMIT,
         }
     }
+
+    pub fn set_stack_limit(&mut self, bytes: usize) {
+        self.stack_limit = bytes;
+    }
+
+    pub fn set_integer_overflow(&mut self, integer_overflow: IntegerOverflow) {
+        self.integer_overflow = integer_overflow;
+    }
+
+    // where the error returned by the last `eval_program` call was raised
+    pub fn error_span(&self) -> Option<Span> {
+        self.error_span
+    }
+
+    // binds a name in the top-level environment, like a `say` at the top of a program
+    pub fn set_global(&mut self, name: &str, value: Object) {
+        self.environment.borrow_mut().set(name.to_string(), value);
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Object> {
+        self.environment.borrow().get(name.to_string())
+    }
+
+    // calls a function value from outside a program, e.g. one an earlier program defined
+    pub fn call_function(&mut self, function: &Object, arguments: Vec<Object>) -> Object {
+        self.error_span = None;
+        self.apply_function(function.clone(), arguments, vec![])
+    }
+
     pub fn eval_program(&mut self, program: Program) ->Object{
         let mut result = Object::Null;
-        
+        self.error_span = None;
+        self.hoist_function_declarations(&program.statements);
+
         for stmt in program.statements {
             result = self.eval_statement(stmt);
 
@@ -40,128 +108,372 @@ impl Evaluator{
             StatementNode::Return(return_stmt) => {
                 let value = self.eval_expression(return_stmt.return_value);
 
-                if Self::is_error(&value) {
+                if Self::is_abrupt(&value) {
                     return value;
                 }
-                return Object::ReturnValue(Box::new(value));
+                Object::ReturnValue(Box::new(value))
             }
             StatementNode::Say(say_stmt) => {
                 let value = self.eval_expression(say_stmt.value);
-                if Self::is_error(&value) {
+                if Self::is_abrupt(&value) {
                     return value;
                 }
-                self.environment.set(say_stmt.name.value, value).unwrap()
+                self.environment.borrow_mut().set(say_stmt.name.value, value).unwrap()
             }
+            StatementNode::While(while_stmt) => self.eval_while_statement(while_stmt),
+            StatementNode::For(for_stmt) => self.eval_for_statement(for_stmt),
+            // already bound by `hoist_function_declarations`
+            StatementNode::Function(_) => NULL,
+            StatementNode::Break(_) => Object::Break,
+            StatementNode::Continue(_) => Object::Continue,
             _ => Object::Null,
         }
     }
 
+    fn eval_while_statement(&mut self, stmt: WhileStatement) -> Object {
+        loop {
+            let condition = self.eval_expression(Some(stmt.condition.clone()));
+            if Self::is_abrupt(&condition) {
+                return condition;
+            }
+            if !Self::is_truthy(condition) {
+                break;
+            }
+
+            match self.eval_block_statement(stmt.body.clone()) {
+                Object::Break => break,
+                result @ (Object::ReturnValue(_) | Object::Error(_)) => return result,
+                _ => {}
+            }
+        }
+        NULL
+    }
+
+    fn eval_for_statement(&mut self, stmt: ForStatement) -> Object {
+        let span = stmt.iterable.span();
+        let iterable = self.eval_expression(Some(stmt.iterable));
+        if Self::is_abrupt(&iterable) {
+            return iterable;
+        }
+
+        // the loop walks a snapshot, so the body may change the array it iterates
+        let items = match iterable {
+            Object::Array(elements) => elements.borrow().clone(),
+            Object::StringObject(string) => string
+                .chars()
+                .map(|ch| Object::StringObject(ch.to_string()))
+                .collect(),
+            other => {
+                self.error_span = Some(span);
+                return Object::Error(format!("not iterable: {}", other.object_type()));
+            }
+        };
+
+        for item in items {
+            self.environment
+                .borrow_mut()
+                .set(stmt.variable.value.clone(), item);
+
+            match self.eval_block_statement(stmt.body.clone()) {
+                Object::Break => break,
+                result @ (Object::ReturnValue(_) | Object::Error(_)) => return result,
+                _ => {}
+            }
+        }
+        NULL
+    }
+
     fn eval_expression(&mut self, expression: Option<ExpressionNode>) -> Object {
         if let Some(exp) = expression {
-            return match exp {
-                ExpressionNode::Integer(int) => Object::Integer(int.value),
-                ExpressionNode::BooleanNode(bool) => {
-                    Self::native_bool_to_boolean_object(bool.value)
+            let span = exp.span();
+            let result = self.eval_expression_node(exp);
+
+            // errors bubble up unchanged, so the first span recorded is the innermost one
+            if Self::is_error(&result) && self.error_span.is_none() {
+                self.error_span = Some(span);
+            }
+            return result;
+        }
+        Object::Null
+    }
+
+    fn eval_expression_node(&mut self, exp: ExpressionNode) -> Object {
+        // the bigger arms live in their own functions, which keeps this frame small for
+        // deeply nested calls
+        match exp {
+            ExpressionNode::Integer(int) => Object::Integer(int.value),
+            ExpressionNode::BigInt(int) => Object::BigInt(int.value),
+            ExpressionNode::Float(float) => Object::Float(float.value),
+            ExpressionNode::BooleanNode(bool) => {
+                Self::native_bool_to_boolean_object(bool.value)
+            }
+            ExpressionNode::Prefix(prefix_exp) => self.eval_prefix_node(prefix_exp),
+            ExpressionNode::Infix(infix_exp) => self.eval_infix_node(infix_exp),
+            ExpressionNode::IfExpressionNode(if_exp) => self.eval_if_expression(if_exp),
+            ExpressionNode::Match(match_exp) => self.eval_match_expression(match_exp),
+            ExpressionNode::IdentifierNode(identifier) => self.eval_identifier(identifier),
+            ExpressionNode::Function(function_literal) => Object::Function(Function{
+                parameters: function_literal.parameters,
+                body: function_literal.body,
+                environment: Rc::clone(&self.environment),
+            }),
+            ExpressionNode::Call(call_exp) => self.eval_call_expression(call_exp),
+            ExpressionNode::StringExp(string_literal) => {
+                Object::StringObject(string_literal.value)
+            }
+            ExpressionNode::InterpolatedString(interpolated) => self.eval_interpolated_string(interpolated),
+            ExpressionNode::Array(array_literal) => {
+                let elements = self.eval_expressions(array_literal.elements);
+                if elements.len() == 1 && Self::is_abrupt(&elements[0]) {
+                    return elements[0].clone();
                 }
-                ExpressionNode::Prefix(prefix_exp) => {
-                    let right = self.eval_expression(Some(*prefix_exp.right));
-                    if Self::is_error(&right) {
-                        return right;
+                Object::new_array(elements)
+            }
+            ExpressionNode::Index(index_exp) => self.eval_index_node(index_exp),
+            ExpressionNode::Dictionary(dictionary) => self.eval_dictionary_literal(dictionary),
+            ExpressionNode::Assign(assign_exp) => self.eval_assign_expression(assign_exp),
+            _ => Object::Null
+        }
+    }
+
+    fn eval_prefix_node(&mut self, prefix_exp: PrefixExpression) -> Object {
+        let right = self.eval_expression(Some(*prefix_exp.right));
+        if Self::is_abrupt(&right) {
+            return right;
+        }
+        Self::eval_prefix_expression(&prefix_exp.operator, right, self.integer_overflow)
+    }
+
+    fn eval_infix_node(&mut self, infix_exp: InfixExpression) -> Object {
+        let left = self.eval_expression(Some(*infix_exp.left));
+        if Self::is_abrupt(&left) {
+            return left;
+        }
+        if infix_exp.operator == "&&" || infix_exp.operator == "||" {
+            return self.eval_logical_expression(&infix_exp.operator, left, *infix_exp.right);
+        }
+
+        let right = self.eval_expression(Some(*infix_exp.right));
+        if Self::is_abrupt(&right) {
+            return right;
+        }
+
+        Self::eval_infix_expression(&infix_exp.operator, &left, &right, self.integer_overflow)
+    }
+
+    fn eval_call_expression(&mut self, call_exp: CallExpression) -> Object {
+        let function = self.eval_expression(Some(*call_exp.function));
+
+        if Self::is_abrupt(&function) {
+            return function;
+        }
+        let arguments = self.eval_expressions(call_exp.arguments);
+
+        if arguments.len() == 1 && Self::is_abrupt(&arguments[0]) {
+            return arguments[0].clone();
+        }
+
+        let mut named = vec![];
+        for (name, value) in call_exp.named {
+            let value = self.eval_expression(Some(value));
+            if Self::is_abrupt(&value) {
+                return value;
+            }
+            named.push((name.value, value));
+        }
+
+        self.apply_function(function, arguments, named)
+    }
+
+    fn eval_interpolated_string(&mut self, interpolated: InterpolatedString) -> Object {
+        let mut out = String::new();
+        for part in interpolated.parts {
+            match part {
+                StringPart::Literal(text) => out.push_str(text.as_str()),
+                StringPart::Expression(expression) => {
+                    let value = self.eval_expression(Some(*expression));
+                    if Self::is_abrupt(&value) {
+                        return value;
                     }
-                    return Self::eval_prefix_expression(prefix_exp.operator, right);
+                    out.push_str(value.to_string().as_str());
                 }
-                ExpressionNode::Infix(infix_exp) => {
-                    let left = self.eval_expression(Some(*infix_exp.left));
-                    if Self::is_error(&left) {
-                        return left;
-                    }
-                    
-                    let right = self.eval_expression(Some(*infix_exp.right));
-                    if Self::is_error(&right) {
-                        return right;
-                    }
+            }
+        }
+        Object::StringObject(out)
+    }
 
-                    return Self::eval_infix_expression(infix_exp.operator, &left, &right);
-                }
-                ExpressionNode::IfExpressionNode(if_exp) => self.eval_if_expression(if_exp),
-                ExpressionNode::IdentifierNode(identifier) => self.eval_identifier(identifier),
-                ExpressionNode::Function(function_literal) => Object::Function(Function{
-                    parameters: function_literal.parameters,
-                    body: function_literal.body,
-                    environment: self.environment.clone(),
-                }),
+    fn eval_index_node(&mut self, index_exp: IndexExpression) -> Object {
+        let left = self.eval_expression(Some(*index_exp.left));
+        if Self::is_abrupt(&left) {
+            return left;
+        }
 
-                ExpressionNode::Call(call_exp) => {
-                    let function = self.eval_expression(Some(call_exp.function.deref().clone()));
+        let index = self.eval_expression(Some(*index_exp.index));
+        if Self::is_abrupt(&index) {
+            return index;
+        }
 
-                    if Self::is_error(&function) {
-                        return function;
-                    }
-                    let arguments = self.eval_expressions(call_exp.arguments);
-                
-                    if arguments.len() == 1 && Self::is_error(&arguments[0]) {
-                        return arguments[0].clone();
-                    }
+        Self::eval_index_expression(left, index)
+    }
 
-                    self.apply_function(function, arguments)
-                }
-                ExpressionNode::StringExp(string_literal) => {
-                    Object::StringObject(string_literal.value)
-                }
-                ExpressionNode::Array(array_literal) => {
-                    let elements = self.eval_expressions(array_literal.elements);
-                    if elements.len() == 1 && Self::is_error(&elements[0]) {
-                        return elements[0].clone();
-                    }
-                    Object::Array(elements)
+    fn eval_dictionary_literal(&mut self, dictionary: DictLiteral) -> Object {
+        let mut pairs = HashMap::new();
+
+        for(k, v) in dictionary.pairs {
+            let key = self.eval_expression(Some(k));
+            if Self::is_abrupt(&key) {
+                return key;
+            }
+
+            let dict_key = match key.dict_key() {
+                Ok(dictionary) => dictionary,
+                Err(err) => {
+                    return Object::Error(err.to_string());
                 }
+            };
 
-                ExpressionNode::Index(index_exp) => {
-                    let left = self.eval_expression(Some(*index_exp.left));
-                    if Self::is_error(&left) {
-                        return left;
-                    }
+            let value = self.eval_expression(Some(v));
+            if Self::is_abrupt(&value) {
+                return value;
+            }
+            pairs.insert(dict_key, DictPair{key, value});
+        }
 
-                    let index = self.eval_expression(Some(*index_exp.index));
-                    if Self::is_error(&index) {
-                        return index;
-                    }
+        Object::new_dict(pairs)
+    }
 
-                    self.eval_index_expression(left, index)
+    fn eval_assign_expression(&mut self, exp: AssignExpression) -> Object {
+        let operator = exp.infix_operator().map(String::from);
+        match *exp.target {
+            ExpressionNode::IdentifierNode(identifier) => {
+                self.eval_name_assignment(identifier.value, operator, *exp.value)
+            }
+            ExpressionNode::Index(index_exp) => {
+                self.eval_element_assignment(index_exp, operator, *exp.value)
+            }
+            other => Object::Error(format!("invalid assignment target: {}", other.print_string())),
+        }
+    }
+
+    fn eval_name_assignment(&mut self, name: String, operator: Option<String>, value: ExpressionNode) -> Object {
+        // `x += v` reads `x` before evaluating `v`, like `x + v` does
+        let current = match &operator {
+            Some(_) => match self.environment.borrow().get(name.clone()) {
+                Some(current) => Some(current),
+                None => return Object::Error(format!("Identifier not found: {}", name)),
+            },
+            None => None,
+        };
+
+        let mut value = self.eval_expression(Some(value));
+        if Self::is_abrupt(&value) {
+            return value;
+        }
+
+        if let (Some(operator), Some(current)) = (operator, current) {
+            value = Self::eval_infix_expression(&operator, &current, &value, self.integer_overflow);
+            if Self::is_error(&value) {
+                return value;
+            }
+        }
+
+        if !self.environment.borrow_mut().assign(name.clone(), value.clone()) {
+            return Object::Error(format!("cannot assign to undeclared name: {}", name));
+        }
+        value
+    }
+
+    fn eval_element_assignment(&mut self, target: IndexExpression, operator: Option<String>, value: ExpressionNode) -> Object {
+        let left = self.eval_expression(Some(*target.left));
+        if Self::is_abrupt(&left) {
+            return left;
+        }
+
+        let index = self.eval_expression(Some(*target.index));
+        if Self::is_abrupt(&index) {
+            return index;
+        }
+
+        let current = match &operator {
+            Some(_) => {
+                let current = Self::eval_index_expression(left.clone(), index.clone());
+                if Self::is_error(&current) {
+                    return current;
                 }
+                Some(current)
+            }
+            None => None,
+        };
 
-                ExpressionNode::Dictionary(dictionary) => {
-                    let mut pairs = HashMap::new();
+        let mut value = self.eval_expression(Some(value));
+        if Self::is_abrupt(&value) {
+            return value;
+        }
 
-                    for(k, v) in dictionary.pairs {
-                        let key = self.eval_expression(Some(k));
-                        if Self::is_error(&key) {
-                            return key;
-                        }
+        if let (Some(operator), Some(current)) = (operator, current) {
+            value = Self::eval_infix_expression(&operator, &current, &value, self.integer_overflow);
+            if Self::is_error(&value) {
+                return value;
+            }
+        }
 
-                        let dict_key = match key.dict_key() {
-                            Ok(dictionary) => dictionary,
-                            Err(err) => {
-                                return Object::Error(err.to_string());
-                            }
-                        };
+        Self::eval_index_assignment(&left, index, value)
+    }
 
-                        let value = self.eval_expression(Some(v));
-                        if Self::is_error(&value) {
-                            return value;
-                        }
-                        pairs.insert(dict_key, DictPair{key, value});
+    // stores `value` in the array or dictionary itself, so every binding to it sees the change
+    pub(crate) fn eval_index_assignment(left: &Object, index: Object, value: Object) -> Object {
+        match left {
+            Object::Array(elements) => {
+                let idx = match index {
+                    Object::Integer(idx) => idx,
+                    Object::BigInt(idx) => {
+                        return Object::Error(format!(
+                            "index out of range: {} (length {})",
+                            idx,
+                            elements.borrow().len()
+                        ))
                     }
+                    other => {
+                        return Object::Error(format!(
+                            "array index must be INTEGER, got={}",
+                            other.object_type()
+                        ))
+                    }
+                };
 
-                    Object::DictObject(DictStruct {pairs})
+                let mut elements = elements.borrow_mut();
+                if idx < 0 || idx >= elements.len() as i64 {
+                    return Object::Error(format!(
+                        "index out of range: {} (length {})",
+                        idx,
+                        elements.len()
+                    ));
                 }
-                _ => Object::Null
-            };
+                elements[idx as usize] = value.clone();
+                value
+            }
+            Object::DictObject(dictionary) => {
+                let dict_key = match index.dict_key() {
+                    Ok(dict_key) => dict_key,
+                    Err(err) => return Object::Error(err),
+                };
+                dictionary.borrow_mut().pairs.insert(
+                    dict_key,
+                    DictPair {
+                        key: index,
+                        value: value.clone(),
+                    },
+                );
+                value
+            }
+            other => Object::Error(format!(
+                "index assignment not supported: {}",
+                other.object_type()
+            )),
         }
-        Object::Null
     }
 
-    fn eval_index_expression(&mut self, left: Object, index: Object) -> Object {
+    pub(crate) fn eval_index_expression(left: Object, index: Object) -> Object {
         if left.object_type() == "ARRAY" && index.object_type() == "INTEGER" {
             return Self::eval_array_index_expression(left, index);
         }
@@ -179,7 +491,8 @@ impl Evaluator{
     fn eval_array_index_expression(array: Object, index: Object) -> Object {
         if let Object::Array(array) = array {
             if let Object::Integer(idx) = index {
-                let max = (array.len() - 1) as i64;
+                let array = array.borrow();
+                let max = array.len() as i64 - 1;
 
                 if idx < 0 || idx > max {
                     return NULL;
@@ -196,10 +509,11 @@ impl Evaluator{
                 let key = match index.dict_key() {
                     Ok(key) => key,
                     Err(e) => {
-                        return Object::Error(format!("{}", e));
+                        return Object::Error(e.to_string());
                     }
                 };
 
+                let hash = hash.borrow();
                 let pair = match hash.pairs.get(&key) {
                     Some(pair) => pair,
                     None => {
@@ -207,39 +521,123 @@ impl Evaluator{
                     }
                 };
 
-                return pair.value.clone();
+                pair.value.clone()
             }
             _ => panic!("sorry!! \n cannot happen")
         }
     }
 
-    fn apply_function (&mut self, function: Object, arguments: Vec<Object>) -> Object {
+    fn apply_function (&mut self, function: Object, arguments: Vec<Object>, named: Vec<(String, Object)>) -> Object {
         match function {
+            Object::Function(_) if self.call_depth >= MAX_CALL_DEPTH || self.stack_exhausted() => {
+                Object::Error(String::from("stack overflow"))
+            }
             Object::Function(function) => {
-                let old_environment = self.environment.clone();
-                let extended_environment = self.extended_function_environment(function.clone(), arguments);
-                self.environment = extended_environment;
-                let evaluated = self.eval_block_statement(function.body);
-                self.environment = old_environment;
-                
-                return Self::unwarp_return_value(evaluated);
-            
+                self.call_depth += 1;
+                let result = self.apply_user_function(function, arguments, named);
+                self.call_depth -= 1;
+                result
             }
-            Object::Builtin(builtin_functions) => builtin_functions(arguments),
+            Object::Builtin(_) if !named.is_empty() => {
+                Object::Error(String::from("named arguments are not supported by builtin functions"))
+            }
+            Object::Builtin(builtin_functions) => builtin_functions.call(self, arguments),
 
 
             other => Object::Error(format!("not a function: {}", other.object_type()))
         }
     }
 
-    fn extended_function_environment(&self, function: Function, arguments: Vec<Object>) -> Environment {
-        let mut environment = Environment::new_enclosed_evironment(Box::new(function.environment));
+    // whether the calls in progress have used up the native stack they may take
+    fn stack_exhausted(&mut self) -> bool {
+        let marker = 0u8;
+        let here = std::hint::black_box(&marker) as *const u8 as usize;
+        if self.call_depth == 0 {
+            self.stack_base = here;
+        }
+        self.stack_base.abs_diff(here) > self.stack_limit
+    }
+
+    fn apply_user_function(&mut self, function: Function, arguments: Vec<Object>, named: Vec<(String, Object)>) -> Object {
+        let specs: Vec<ParameterSpec> = function.parameters.iter().map(ParameterSpec::from).collect();
+        let values = match Self::bind_arguments(&specs, arguments, named) {
+            Ok(values) => values,
+            Err(message) => return Object::Error(message),
+        };
+
+        let extended_environment = Environment::new_enclosed_evironment(Rc::clone(&function.environment));
+        let old_environment = std::mem::replace(&mut self.environment, extended_environment);
+
+        // defaults are evaluated in the new environment, so they can use earlier parameters.
+        // Later ones are not set yet, so their names still find the enclosing scopes
+        for (parameter, value) in function.parameters.iter().zip(values) {
+            let value = match (value, &parameter.default) {
+                (Some(value), _) => value,
+                (None, default) => {
+                    let value = self.eval_expression(default.clone());
+                    if Self::is_abrupt(&value) {
+                        self.environment = old_environment;
+                        return Self::unwarp_return_value(value);
+                    }
+                    value
+                }
+            };
+            self.environment.borrow_mut().set(parameter.name.value.clone(), value);
+        }
+
+        let evaluated = self.eval_block_statement(function.body);
+        self.environment = old_environment;
+        
+        Self::unwarp_return_value(evaluated)
+    
+    }
+
+    // matches call arguments to parameters. `None` means the parameter takes its default;
+    // a rest parameter always gets an array. Shared with the VM
+    pub(crate) fn bind_arguments(parameters: &[ParameterSpec], positional: Vec<Object>, named: Vec<(String, Object)>) -> Result<Vec<Option<Object>>, String> {
+        let got = positional.len() + named.len();
+        let has_rest = parameters.last().is_some_and(|parameter| parameter.rest);
+        let fixed = parameters.len() - usize::from(has_rest);
+        let required = parameters.iter().filter(|parameter| !parameter.has_default && !parameter.rest).count();
+        let arity_error = || {
+            let want = if has_rest {
+                format!("at least {}", required)
+            } else if required == fixed {
+                format!("{}", fixed)
+            } else {
+                format!("{} to {}", required, fixed)
+            };
+            format!("wrong number of arguments: want={}, got={}", want, got)
+        };
 
-        for (idx, parameter) in function.parameters.into_iter().enumerate() {
-            environment.set(parameter.value, arguments[idx].clone());
+        let mut values: Vec<Option<Object>> = vec![None; parameters.len()];
+        let mut positional = positional.into_iter();
+        for value in values.iter_mut().take(fixed) {
+            *value = positional.next();
+        }
+        let extra: Vec<Object> = positional.collect();
+        if has_rest {
+            values[fixed] = Some(Object::Array(Rc::new(RefCell::new(extra))));
+        } else if !extra.is_empty() {
+            return Err(arity_error());
         }
 
-        environment
+        for (name, value) in named {
+            let idx = match parameters.iter().position(|parameter| parameter.name == name && !parameter.rest) {
+                Some(idx) => idx,
+                None => return Err(format!("unknown parameter name: {}", name)),
+            };
+            if values[idx].is_some() {
+                return Err(format!("multiple values for parameter: {}", name));
+            }
+            values[idx] = Some(value);
+        }
+
+        if parameters.iter().zip(&values).any(|(parameter, value)| value.is_none() && !parameter.has_default) {
+            return Err(arity_error());
+        }
+
+        Ok(values)
     }
 
     fn unwarp_return_value(object: Object) -> Object {
@@ -254,7 +652,7 @@ impl Evaluator{
 
         for exp in expression {
             let evaluated = self.eval_expression(Some(exp));
-            if Self::is_error(&evaluated) {
+            if Self::is_abrupt(&evaluated) {
                 return vec![evaluated];
             }
             result.push(evaluated);
@@ -262,10 +660,26 @@ impl Evaluator{
         result
     }
 
-    fn eval_prefix_expression(operator: String, right: Object) -> Object {
-        match operator.as_str() {
+    // `&&` and `||` short-circuit: the right side is only evaluated when the left one does not
+    // decide the result. Both give a boolean
+    fn eval_logical_expression(&mut self, operator: &str, left: Object, right: ExpressionNode) -> Object {
+        let left = Self::is_truthy(left);
+        if left != (operator == "&&") {
+            return Self::native_bool_to_boolean_object(left);
+        }
+
+        let right = self.eval_expression(Some(right));
+        if Self::is_abrupt(&right) {
+            return right;
+        }
+        Self::native_bool_to_boolean_object(Self::is_truthy(right))
+    }
+
+    pub(crate) fn eval_prefix_expression(operator: &str, right: Object, overflow: IntegerOverflow) -> Object {
+        match operator {
             "!" => Self::eval_bang_operator_expression(right),
-            "-" => Self::eval_minu_prefix_operator_expression(right),
+            "-" => Self::eval_minu_prefix_operator_expression(right, overflow),
+            "~" => Self::eval_bit_not_operator_expression(right),
             _ => Object::Error(format!(
                 "unknown operator: {} {}",
                 operator,
@@ -274,7 +688,48 @@ impl Evaluator{
         }
     }
 
-    fn eval_infix_expression(operator: String, left: &Object, right: &Object) -> Object {
+    pub(crate) fn eval_infix_expression(operator: &str, left: &Object, right: &Object, overflow: IntegerOverflow) -> Object {
+        // an integer meeting a float is widened to a float
+        match (left, right) {
+            (Object::BigInt(left), Object::Float(right)) => {
+                return Self::eval_float_infix_expression(operator, left.to_f64(), *right)
+            }
+            (Object::Float(left), Object::BigInt(right)) => {
+                return Self::eval_float_infix_expression(operator, *left, right.to_f64())
+            }
+            (Object::BigInt(_), Object::Integer(_) | Object::BigInt(_)) | (Object::Integer(_), Object::BigInt(_)) => {
+                return Self::eval_bigint_infix_expression(operator, &Self::to_bigint(left), &Self::to_bigint(right))
+            }
+            (Object::Float(left), Object::Float(right)) => {
+                return Self::eval_float_infix_expression(operator, *left, *right)
+            }
+            (Object::Integer(left), Object::Float(right)) => {
+                return Self::eval_float_infix_expression(operator, *left as f64, *right)
+            }
+            (Object::Float(left), Object::Integer(right)) => {
+                return Self::eval_float_infix_expression(operator, *left, *right as f64)
+            }
+            _ => {}
+        }
+
+        // equality is defined between any two values, see `PartialEq for Object`. ordering only
+        // between strings or arrays, anything else (including mixed types) is an error
+        match operator {
+            "==" => return Self::native_bool_to_boolean_object(left == right),
+            "!=" => return Self::native_bool_to_boolean_object(left != right),
+            "<" | ">" | "<=" | ">=" if left.object_type() == right.object_type() => {
+                if let Some(ordering) = left.partial_cmp(right) {
+                    return Self::native_bool_to_boolean_object(match operator {
+                        "<" => ordering.is_lt(),
+                        ">" => ordering.is_gt(),
+                        "<=" => ordering.is_le(),
+                        _ => ordering.is_ge(),
+                    });
+                }
+            }
+            _ => {}
+        }
+
         if left.object_type() != right.object_type() {
             return Object::Error(format!(
                 "type mismatch: {} {} {}",
@@ -285,10 +740,10 @@ impl Evaluator{
         }
         match (left, right, operator) {
             (Object::Integer(left), Object::Integer(right), op) => {
-                Self::eval_integer_infix_expression(op, *left, *right)
+                Self::eval_integer_infix_expression(op, *left, *right, overflow)
             }
             (Object::StringObject(left_string), Object::StringObject(right_string), operator) => {
-                return match operator.as_str() {
+                match operator {
                     "+" => 
                         Object::StringObject(format!("{}{}", left_string, right_string)),
                     _ => Object::Error(format!(
@@ -298,21 +753,22 @@ impl Evaluator{
                         right.object_type()
                     )),
                     
-                };
+                }
             }   
                     
             
             (Object::Boolean(l), Object::Boolean(r), operator) => {         // l: left, r: right
-                return match operator.as_str() {
-                    "==" =>Self::native_bool_to_boolean_object(l == r),
-                    "!=" =>Self::native_bool_to_boolean_object(l != r),
+                match operator {
+                    "&" => Self::native_bool_to_boolean_object(l & r),
+                    "|" => Self::native_bool_to_boolean_object(l | r),
+                    "^" => Self::native_bool_to_boolean_object(l ^ r),
                     _ => Object::Error(format!(
                         "unknown operator: {} {} {}",
                         left.object_type(),
                         operator,
                         right.object_type()
                     )),
-                };
+                }
             }
             (left, right, operator) =>
                 Object::Error(format!(
@@ -324,20 +780,129 @@ impl Evaluator{
         }
     }
 
-    fn eval_integer_infix_expression(operator: String, left: i64, right: i64) -> Object {
-        match operator.as_str() {
-            "+" => Object::Integer(left + right),
-            "-" => Object::Integer(left - right),
-            "*" => Object::Integer(left * right),
-            "/" => Object::Integer(left / right),
+    fn eval_integer_infix_expression(operator: &str, left: i64, right: i64, overflow: IntegerOverflow) -> Object {
+        let result = match operator {
+            "+" => left.checked_add(right),
+            "-" => left.checked_sub(right),
+            "*" => left.checked_mul(right),
+            "/" | "%" if right == 0 => {
+                return Object::Error(format!("division by zero: {} {} {}", left, operator, right))
+            }
+            "/" => left.checked_div(right),
+            "%" => left.checked_rem(right),
+            // a negative exponent has a fractional result
+            "**" if right < 0 => return Object::Float((left as f64).powf(right as f64)),
+            "**" => u32::try_from(right).ok().and_then(|exponent| left.checked_pow(exponent)),
+            "&" => Some(left & right),
+            "|" => Some(left | right),
+            "^" => Some(left ^ right),
+            "<<" | ">>" if right < 0 => {
+                return Object::Error(format!("negative shift amount: {} {} {}", left, operator, right))
+            }
+            // shifting back must give `left` again, otherwise bits were lost
+            "<<" if right < 64 => Some(left << right).filter(|shifted| shifted >> right == left),
+            "<<" => (left == 0).then_some(0),
+            ">>" => Some(left >> right.min(63)),
+            "<" => return Self::native_bool_to_boolean_object(left < right),
+            ">" => return Self::native_bool_to_boolean_object(left > right),
+            "<=" => return Self::native_bool_to_boolean_object(left <= right),
+            ">=" => return Self::native_bool_to_boolean_object(left >= right),
+            "==" => return Self::native_bool_to_boolean_object(left == right),
+            "!=" => return Self::native_bool_to_boolean_object(left != right),
+            _ => return NULL
+        };
+
+        match (result, overflow) {
+            (Some(value), _) => Object::Integer(value),
+            (None, IntegerOverflow::Promote) => {
+                Self::eval_bigint_infix_expression(operator, &BigInt::from_i64(left), &BigInt::from_i64(right))
+            }
+            (None, IntegerOverflow::Error) => {
+                Object::Error(format!("integer overflow: {} {} {}", left, operator, right))
+            }
+        }
+    }
+
+    fn eval_bigint_infix_expression(operator: &str, left: &BigInt, right: &BigInt) -> Object {
+        match operator {
+            "+" => Object::from_bigint(left + right),
+            "-" => Object::from_bigint(left - right),
+            "*" => Object::from_bigint(left * right),
+            "/" | "%" => match left.checked_div_rem(right) {
+                Some((quotient, _)) if operator == "/" => Object::from_bigint(quotient),
+                Some((_, remainder)) => Object::from_bigint(remainder),
+                None => Object::Error(format!("division by zero: {} {} {}", left, operator, right)),
+            },
+            "**" if right.is_negative() => Object::Float(left.to_f64().powf(right.to_f64())),
+            "**" => match right.to_i64().and_then(|exponent| u32::try_from(exponent).ok()) {
+                Some(exponent) => Object::from_bigint(left.pow(exponent)),
+                None => Object::Error(format!("exponent too large: {} ** {}", left, right)),
+            },
+            "<<" | ">>" if right.is_negative() => {
+                Object::Error(format!("negative shift amount: {} {} {}", left, operator, right))
+            }
+            "<<" => match right.to_i64().and_then(|amount| u32::try_from(amount).ok()) {
+                Some(amount) => Object::from_bigint(left * &BigInt::from_i64(2).pow(amount)),
+                None => Object::Error(format!("shift amount too large: {} << {}", left, right)),
+            },
+            ">>" => Self::eval_bigint_shift_right(left, right),
+            "&" | "|" | "^" => Object::Error(format!(
+                "bitwise operator {} is not supported for integers beyond 64 bits",
+                operator
+            )),
             "<" => Self::native_bool_to_boolean_object(left < right),
             ">" => Self::native_bool_to_boolean_object(left > right),
+            "<=" => Self::native_bool_to_boolean_object(left <= right),
+            ">=" => Self::native_bool_to_boolean_object(left >= right),
             "==" => Self::native_bool_to_boolean_object(left == right),
             "!=" => Self::native_bool_to_boolean_object(left != right),
             _ => NULL
         }
     }
 
+    // an arithmetic shift rounds toward negative infinity, like `i64 >>`
+    fn eval_bigint_shift_right(left: &BigInt, right: &BigInt) -> Object {
+        let amount = match right.to_i64().and_then(|amount| u32::try_from(amount).ok()) {
+            Some(amount) => amount,
+            None if left.is_negative() => return Object::Integer(-1),
+            None => return Object::Integer(0),
+        };
+
+        let (quotient, remainder) = left
+            .checked_div_rem(&BigInt::from_i64(2).pow(amount))
+            .expect("a power of two is never zero");
+        if left.is_negative() && !remainder.is_zero() {
+            return Object::from_bigint(&quotient - &BigInt::from_i64(1));
+        }
+        Object::from_bigint(quotient)
+    }
+
+    fn to_bigint(object: &Object) -> BigInt {
+        match object {
+            Object::BigInt(big) => big.clone(),
+            Object::Integer(int) => BigInt::from_i64(*int),
+            _ => BigInt::zero(),
+        }
+    }
+
+    fn eval_float_infix_expression(operator: &str, left: f64, right: f64) -> Object {
+        match operator {
+            "+" => Object::Float(left + right),
+            "-" => Object::Float(left - right),
+            "*" => Object::Float(left * right),
+            "/" => Object::Float(left / right),
+            "%" => Object::Float(left % right),
+            "**" => Object::Float(left.powf(right)),
+            "<" => Self::native_bool_to_boolean_object(left < right),
+            ">" => Self::native_bool_to_boolean_object(left > right),
+            "<=" => Self::native_bool_to_boolean_object(left <= right),
+            ">=" => Self::native_bool_to_boolean_object(left >= right),
+            "==" => Self::native_bool_to_boolean_object(left == right),
+            "!=" => Self::native_bool_to_boolean_object(left != right),
+            _ => Object::Error(format!("unknown operator: FLOAT {} FLOAT", operator)),
+        }
+    }
+
     fn eval_bang_operator_expression(right: Object) -> Object {
         match right {
             Object::Boolean(true) => FALSE,
@@ -349,13 +914,28 @@ impl Evaluator{
             
     }
 
-    fn eval_minu_prefix_operator_expression(right: Object) -> Object {
+    fn eval_minu_prefix_operator_expression(right: Object, overflow: IntegerOverflow) -> Object {
         match right {
-            Object::Integer(int) => Object::Integer(-int),
+            Object::Integer(int) => match (int.checked_neg(), overflow) {
+                (Some(negated), _) => Object::Integer(negated),
+                (None, IntegerOverflow::Promote) => Object::from_bigint(-&BigInt::from_i64(int)),
+                (None, IntegerOverflow::Error) => Object::Error(format!("integer overflow: -({})", int)),
+            },
+            Object::BigInt(big) => Object::from_bigint(-&big),
+            Object::Float(float) => Object::Float(-float),
             _ => Object::Error(format!("unknown operator: -{}", right.object_type())),
         }
     }
 
+    // `~x` is `-x - 1`, which also holds beyond 64 bits
+    fn eval_bit_not_operator_expression(right: Object) -> Object {
+        match right {
+            Object::Integer(int) => Object::Integer(!int),
+            Object::BigInt(big) => Object::from_bigint(&(-&big) - &BigInt::from_i64(1)),
+            _ => Object::Error(format!("unknown operator: ~{}", right.object_type())),
+        }
+    }
+
     fn native_bool_to_boolean_object(bool: bool) -> Object {
         if bool {
             TRUE
@@ -364,19 +944,96 @@ impl Evaluator{
         }
     }
 
+    fn eval_match_expression(&mut self, exp: MatchExpression) -> Object {
+        let subject = self.eval_expression(Some(*exp.subject));
+        if Self::is_abrupt(&subject) {
+            return subject;
+        }
+
+        for arm in exp.arms {
+            let mut bindings = vec![];
+            if !self.match_pattern(&arm.pattern, &subject, &mut bindings) {
+                continue;
+            }
+            // the names are bound in a scope of the arm's own, so a failed guard leaves no trace
+            let arm_environment = Environment::new_enclosed_evironment(Rc::clone(&self.environment));
+            for (name, value) in bindings {
+                arm_environment.borrow_mut().set(name, value);
+            }
+            let old_environment = std::mem::replace(&mut self.environment, arm_environment);
+
+            if let Some(guard) = arm.guard {
+                let guard = self.eval_expression(Some(guard));
+                if Self::is_abrupt(&guard) {
+                    self.environment = old_environment;
+                    return guard;
+                }
+                if !Self::is_truthy(guard) {
+                    self.environment = old_environment;
+                    continue;
+                }
+            }
+            let value = self.eval_expression(Some(arm.value));
+            self.environment = old_environment;
+            return value;
+        }
+
+        Object::Error(format!("no match arm matches: {}", subject))
+    }
+
+    // whether `value` matches `pattern`, collecting the names it binds
+    fn match_pattern(&mut self, pattern: &Pattern, value: &Object, bindings: &mut Vec<(String, Object)>) -> bool {
+        match pattern {
+            Pattern::Wildcard(_) => true,
+            Pattern::Binding(identifier) => {
+                bindings.push((identifier.value.clone(), value.clone()));
+                true
+            }
+            Pattern::Literal(literal) => self.eval_expression(Some(literal.clone())) == *value,
+            Pattern::Array(_, patterns) => match value {
+                Object::Array(elements) => {
+                    let elements = elements.borrow().clone();
+                    elements.len() == patterns.len()
+                        && patterns
+                            .iter()
+                            .zip(&elements)
+                            .all(|(pattern, element)| self.match_pattern(pattern, element, bindings))
+                }
+                _ => false,
+            },
+            Pattern::Dictionary(_, pairs) => match value {
+                Object::DictObject(dictionary) => pairs.iter().all(|(key, pattern)| {
+                    let key = self.eval_expression(Some(key.clone()));
+                    let found = key
+                        .dict_key()
+                        .ok()
+                        .and_then(|key| dictionary.borrow().pairs.get(&key).map(|pair| pair.value.clone()));
+                    match found {
+                        Some(found) => self.match_pattern(pattern, &found, bindings),
+                        None => false,
+                    }
+                }),
+                _ => false,
+            },
+        }
+    }
+
     fn eval_if_expression(&mut self, exp: IfExpression) -> Object {
         let condition = self.eval_expression(Some(*exp.condition));
+        if Self::is_abrupt(&condition) {
+            return condition;
+        }
 
-        return if Self::is_truthy(condition){
+        if Self::is_truthy(condition){
             self.eval_block_statement(exp.consequence)
         } else if let Some(alternative) = exp.alternative {
             self.eval_block_statement(alternative)
         } else {
             NULL
-        } ;
+        }
     }
 
-    fn is_truthy(object: Object) -> bool {
+    pub(crate) fn is_truthy(object: Object) -> bool {
         match object {
             Object::Null => false,
             Object::Boolean(true) => true,
@@ -387,18 +1044,34 @@ impl Evaluator{
 
     fn eval_block_statement(&mut self, block: BlockStatement) -> Object {
         let mut result = NULL;
+        self.hoist_function_declarations(&block.statements);
         for stmt in block.statements {
             result = self.eval_statement(stmt);
 
-            if result.object_type() == "RETURN_VALUE" || result.object_type() == "ERROR" {
+            if Self::is_abrupt(&result) {
                 return result;
             }
         }
         result
     }
 
+    // `function name() {}` declarations are bound before any statement of their program or
+    // block runs, so they can call each other whatever order they are written in
+    fn hoist_function_declarations(&mut self, statements: &[StatementNode]) {
+        for stmt in statements {
+            if let StatementNode::Function(declaration) = stmt {
+                let function = Object::Function(Function {
+                    parameters: declaration.function.parameters.clone(),
+                    body: declaration.function.body.clone(),
+                    environment: Rc::clone(&self.environment),
+                });
+                self.environment.borrow_mut().set(declaration.name.value.clone(), function);
+            }
+        }
+    }
+
     fn eval_identifier(&self, identifier: Identifier) -> Object{
-        let value = self.environment.get(identifier.value.clone());
+        let value = self.environment.borrow().get(identifier.value.clone());
         match value {
             Some(value) => value,
             None => Object::Error(format!("Identifier not found: {}", identifier.value))
@@ -408,9 +1081,14 @@ impl Evaluator{
     fn is_error(object: &Object) -> bool {
         object.object_type() == "ERROR"
     }
+
+    // errors, `return`, `break` and `continue` stop the expressions and blocks they occur in,
+    // which hand them on unchanged up to the function or loop they belong to
+    fn is_abrupt(object: &Object) -> bool {
+        matches!(object, Object::Error(_) | Object::ReturnValue(_) | Object::Break | Object::Continue)
+    }
 }
 #[cfg(test)]
-
 mod test {
     use std::any;
 
@@ -420,7 +1098,7 @@ mod test {
         parser::Parser,
         ast::Node};
 
-    use super::{Evaluator, FALSE, NULL, TRUE};
+    use super::{Evaluator, IntegerOverflow, FALSE, NULL, TRUE};
 
 
     #[test]
@@ -501,6 +1179,9 @@ mod test {
             ("if (1 > 2) {10}", -0),
             ("if (1 > 2) {10} else {20}", 20),
             ("if (1 < 2) {10} else {20}", 10),
+            ("if (1 > 2) {10} else if (2 > 1) {20} else {30}", 20),
+            ("if (1 > 2) {10} else if (2 > 3) {20} else {30}", 30),
+            ("if (1 > 2) {10} else if (2 > 3) {20}", -0),
         ];
 
         for test in tests {
@@ -514,8 +1195,8 @@ mod test {
     }
     fn test_null_object(object: Object) {
         match object {
-            Object::Null => assert!(true),
-            _ => assert!(false)
+            Object::Null => {}
+            other => panic!("object is not null, got={:?}", other)
         }
     }
 
@@ -581,6 +1262,35 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_error_span() {
+        let tests = vec![
+            ("5 + true;", (1, 1)),
+            ("say a = 1;\nsay b = [1, 2];\n  a + b;", (3, 3)),
+            ("if (true) {\n    -false\n}", (2, 5)),
+            ("say f = function(x) { x + true };\nf(1);", (1, 23)),
+            ("len(1, 2)", (1, 1)),
+        ];
+
+        for (input, expected) in tests {
+            let lexer = Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            let program = parser.parse_program().unwrap();
+            let mut evaluator = Evaluator::new();
+
+            let evaluated = evaluator.eval_program(program);
+            assert!(Evaluator::is_error(&evaluated), "no error for {:?}, got={}", input, evaluated);
+
+            let span = evaluator.error_span().expect("error span should be recorded");
+            assert_eq!(
+                (span.start.line, span.start.column),
+                expected,
+                "wrong error location for {:?}",
+                input
+            );
+        }
+    }
+
     #[test]
     fn test_bang_operator() {
         let tests = vec![
@@ -641,6 +1351,708 @@ mod test {
         test_integer_object(test_eval(input), 50);
     }
 
+    #[test]
+    fn test_recursive_closures() {
+        let tests = vec![
+            (
+                "say fact = function(n) { if (n < 2) { return 1; } n * fact(n - 1) }; fact(10);",
+                3628800,
+            ),
+            (
+                "say isEven = function(n) { if (n == 0) { true } else { isOdd(n - 1) } };
+                say isOdd = function(n) { if (n == 0) { false } else { isEven(n - 1) } };
+                if (isEven(10)) { 1 } else { 0 }",
+                1,
+            ),
+            (
+                "say outer = function() {
+                    say fib = function(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } };
+                    fib(15)
+                };
+                outer();",
+                610,
+            ),
+            ("say x = 1; say getX = function() { x }; say x = 2; getX();", 2),
+        ];
+
+        for test in tests {
+            test_integer_object(test_eval(test.0), test.1);
+        }
+    }
+
+    #[test]
+    fn test_float_expressions() {
+        let tests = vec![
+            ("1.5", "1.5"),
+            ("-.5", "-0.5"),
+            ("1e-3", "0.001"),
+            ("2.0", "2.0"),
+            ("1.5 + 1.5", "3.0"),
+            ("0.1 + 0.2", "0.30000000000000004"),
+            ("1 + 0.5", "1.5"),
+            ("3 / 2.0", "1.5"),
+            ("2.5 * 4", "10.0"),
+            ("3 / 2", "1"),
+            ("1 / 0.0", "inf"),
+            ("1e300 * 1e300", "inf"),
+            ("1.5 < 2", "true"),
+            ("2 > 2.5", "false"),
+            ("1 == 1.0", "true"),
+            ("0.5 != .5", "false"),
+            ("say avg = function(a) { (a[0] + a[1] + a[2]) / 3.0 }; avg([1, 2, 4])", "2.3333333333333335"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "wrong result for {:?}", input);
+        }
+
+        match test_eval("1.5 + true") {
+            Object::Error(message) => assert_eq!(message, "type mismatch: FLOAT + BOOLEAN"),
+            other => panic!("expected an error, got={}", other),
+        }
+    }
+
+    #[test]
+    fn test_integer_arithmetic_errors() {
+        let tests = vec![
+            ("1 / 0", "division by zero: 1 / 0"),
+            ("say zero = 0; 10 / zero", "division by zero: 10 / 0"),
+            ("9223372036854775807 + 1", "integer overflow: 9223372036854775807 + 1"),
+            ("-9223372036854775807 - 2", "integer overflow: -9223372036854775807 - 2"),
+            ("4611686018427387904 * 2", "integer overflow: 4611686018427387904 * 2"),
+            ("say min = -9223372036854775807 - 1; min / -1", "integer overflow: -9223372036854775808 / -1"),
+            ("say min = -9223372036854775807 - 1; -min", "integer overflow: -(-9223372036854775808)"),
+            ("say x = 9223372036854775807; x += 1; x", "integer overflow: 9223372036854775807 + 1"),
+        ];
+
+        for (input, expected) in tests {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            let mut evaluator = Evaluator::new();
+            evaluator.set_integer_overflow(IntegerOverflow::Error);
+
+            match evaluator.eval_program(program) {
+                Object::Error(message) => assert_eq!(message, expected, "wrong error for {:?}", input),
+                other => panic!("expected an error for {:?}, got={}", input, other),
+            }
+        }
+
+        match test_eval("(9223372036854775807 + 1) / 0") {
+            Object::Error(message) => assert_eq!(message, "division by zero: 9223372036854775808 / 0"),
+            other => panic!("expected an error, got={}", other),
+        }
+    }
+
+    #[test]
+    fn test_comparison_logical_and_bitwise_operators() {
+        let tests = vec![
+            ("7 % 3", "1"),
+            ("-7 % 3", "-1"),
+            ("7.5 % 2", "1.5"),
+            ("2 ** 10", "1024"),
+            ("2 ** 3 ** 2", "512"),
+            ("-2 ** 2", "-4"),
+            ("2 ** -1", "0.5"),
+            ("2 ** 64", "18446744073709551616"),
+            ("2.0 ** 0.5 > 1.41", "true"),
+            ("6 & 3", "2"),
+            ("6 | 3", "7"),
+            ("6 ^ 3", "5"),
+            ("~5", "-6"),
+            ("~(9223372036854775807 + 1)", "-9223372036854775809"),
+            ("1 << 4", "16"),
+            ("-16 >> 2", "-4"),
+            ("1 << 64", "18446744073709551616"),
+            ("(1 << 64) >> 63", "2"),
+            ("-(1 << 64) >> 70", "-1"),
+            ("3 <= 3", "true"),
+            ("3 >= 4", "false"),
+            ("1.5 <= 1", "false"),
+            ("(1 << 64) >= 5", "true"),
+            ("true & false", "false"),
+            ("true ^ true", "false"),
+            ("true && 1 < 2", "true"),
+            ("1 && 0", "true"),
+            ("false || 0 == 0", "true"),
+            ("say n = 0; false && (n = 1); n", "0"),
+            ("say n = 0; true || (n = 1); n", "0"),
+            ("say n = 0; true && (n = 1); n", "1"),
+            ("false && missing", "false"),
+            ("true || missing", "true"),
+            ("true && missing", "ERROR: Identifier not found: missing"),
+            ("5 % 0", "ERROR: division by zero: 5 % 0"),
+            ("1 << -1", "ERROR: negative shift amount: 1 << -1"),
+            ("(1 << 64) & 1", "ERROR: bitwise operator & is not supported for integers beyond 64 bits"),
+            ("~true", "ERROR: unknown operator: ~BOOLEAN"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "wrong result for {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_structural_equality_and_ordering() {
+        let tests = vec![
+            (r#""a" == "a""#, "true"),
+            (r#""a" != "b""#, "true"),
+            (r#""apple" < "banana""#, "true"),
+            (r#""b" > "abc""#, "true"),
+            (r#""ab" <= "a""#, "false"),
+            (r#""Z" < "a""#, "true"),
+            ("[1, [2, 3]] == [1, [2, 3]]", "true"),
+            ("[1, 2] == [1, 2, 3]", "false"),
+            ("[1, 2.0] == [1.0, 2]", "true"),
+            ("[1, 2] < [1, 3]", "true"),
+            ("[1, 2] < [1, 2, 0]", "true"),
+            (r#"["b"] >= ["a", "z"]"#, "true"),
+            ("[] <= []", "true"),
+            (r#"{"a": 1, "b": [2]} == {"b": [2], "a": 1}"#, "true"),
+            (r#"{"a": 1} == {"a": 2}"#, "false"),
+            (r#"{"a": 1} != {"a": 1, "b": 2}"#, "true"),
+            ("{} == {}", "true"),
+            ("say a = [1]; say b = a; push(b, 2) == [1, 2]", "true"),
+            ("push([], 1)", "[1]"),
+            ("say r = []; for (x in [1, 2]) { r = push(r, x * 10) } r", "[10, 20]"),
+            ("if (false) { 1 } == if (false) { 2 }", "true"),
+            ("if (false) { 1 } == false", "false"),
+            (r#"1 == "1""#, "false"),
+            (r#"1 != "1""#, "true"),
+            ("[1] == {}", "false"),
+            ("true == 1", "false"),
+            ("(9223372036854775807 + 1) == [1]", "false"),
+            ("say f = function(x) { x }; f == f", "true"),
+            ("function(x) { x } == function(y) { y }", "false"),
+            ("len == len", "true"),
+            ("len == first", "false"),
+            (r#"1 < "1""#, "ERROR: type mismatch: INTEGER < STRING"),
+            (r#"[1] < ["a"]"#, "ERROR: unknown operator: ARRAY < ARRAY"),
+            ("{} < {}", "ERROR: unknown operator: DICTIONARY < DICTIONARY"),
+            ("true > false", "ERROR: unknown operator: BOOLEAN > BOOLEAN"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "wrong result for {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_match_expressions() {
+        let tests = vec![
+            ("match 1 { 1 => \"one\", _ => \"other\" }", "one"),
+            ("match 7 { 1 => \"one\", _ => \"other\" }", "other"),
+            ("match -3 { -3 => true, _ => false }", "true"),
+            ("match 2.0 { 2 => \"two\" }", "two"),
+            ("match \"hi\" { \"hello\" => 1, \"hi\" => 2 }", "2"),
+            ("match false { true => 1, false => 0 }", "0"),
+            ("match 5 { n => n * 2 }", "10"),
+            ("match [] { [] => \"empty\", _ => \"other\" }", "empty"),
+            ("match [1, 2] { [a] => a, [a, b] => a + b, _ => 0 }", "3"),
+            ("match [1, 2, 3] { [a, b] => a + b, _ => 0 }", "0"),
+            ("match [1, [2, 3]] { [x, [y, z]] => x + y + z }", "6"),
+            ("match [1, 2] { [1, x] => x, _ => 0 }", "2"),
+            ("match [3, 2] { [1, x] => x, _ => 0 }", "0"),
+            ("match \"ab\" { [a, b] => 1, _ => 2 }", "2"),
+            ("match {\"x\": 1, \"y\": 2} { {\"x\": x, \"y\": y} => x + y }", "3"),
+            ("match {\"x\": 1, \"z\": 2} { {\"x\": x, \"y\": y} => x + y, {\"x\": x} => x * 10 }", "10"),
+            ("match {\"kind\": \"circle\", \"r\": 2} { {\"kind\": \"square\"} => 1, {\"kind\": \"circle\", \"r\": r} => r }", "2"),
+            ("match {1: [true]} { {1: [flag]} => flag }", "true"),
+            ("match [] { {} => 1, _ => 2 }", "2"),
+            ("match 15 { n if n < 10 => \"small\", n if n < 100 => \"medium\", _ => \"large\" }", "medium"),
+            ("match [4, 4] { [a, b] if a == b => \"pair\", _ => \"no\" }", "pair"),
+            ("say x = 1; match 2 { x => x }; x", "1"),
+            ("say x = 5; match [1] { [x] if false => 0, _ => x }", "5"),
+            ("say x = 5; say y = match [1] { [x] => x + 1 }; [x, y]", "[5, 2]"),
+            ("say x = 5; match 1 { x => x = 9 }; x", "5"),
+            ("match 1 { n => 2 }; n", "ERROR: Identifier not found: n"),
+            ("say f = function(v) { match v { [h, t] => h + f(t), _ => 0 } }; f([1, [2, [3, 0]]])", "6"),
+            ("match match 1 { 1 => 2 } { 2 => 3 }", "3"),
+            ("match 1 { a if match a { 1 => false, _ => true } => 1, _ => 2 }", "2"),
+            ("match 5 { 1 => 2 }", "ERROR: no match arm matches: 5"),
+            ("match [1] { [a] if a > 1 => a }", "ERROR: no match arm matches: [1]"),
+            ("match 1 { n if n + true => 1 }", "ERROR: type mismatch: INTEGER + BOOLEAN"),
+            ("match missing { _ => 1 }", "ERROR: Identifier not found: missing"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "wrong result for {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_bigint_literals() {
+        let tests = vec![
+            ("123456789012345678901234567890", "123456789012345678901234567890"),
+            ("-123456789012345678901234567890", "-123456789012345678901234567890"),
+            ("-9223372036854775808", "-9223372036854775808"),
+            ("9223372036854775807 + 1", "9223372036854775808"),
+            ("100000000000000000000 - 99999999999999999999", "1"),
+            ("100000000000000000000 / 3", "33333333333333333333"),
+            ("100000000000000000000 > 9223372036854775807", "true"),
+            ("-100000000000000000000 < -9223372036854775807", "true"),
+            ("100000000000000000000 == 100000000000000000000", "true"),
+            ("100000000000000000000 != 100000000000000000001", "true"),
+            ("100000000000000000000 == 1e20", "true"),
+            ("say d = {100000000000000000000: \"big\", 1: \"small\"}; d[10000000000 * 10000000000]", "big"),
+            ("[1, 2][100000000000000000000]", ""),
+            // a bigint key used to be stored as its hash, which could equal an integer key
+            ("say d = {}; d[2 ** 70] = \"big\"; d[-3452502586987142673] = \"small\"; [d[2 ** 70], d[-3452502586987142673]]", "[big, small]"),
+            ("100000000000000000000 + true", "ERROR: type mismatch: INTEGER + BOOLEAN"),
+            ("say a = [1]; a[100000000000000000000] = 2", "ERROR: index out of range: 100000000000000000000 (length 1)"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "wrong result for {:?}", input);
+        }
+
+        // `-9223372036854775808` is a negated bigint literal that fits an `i64` again
+        test_integer_object(test_eval("-9223372036854775808"), i64::MIN);
+    }
+
+    #[test]
+    fn test_integer_overflow_promotion() {
+        let tests = vec![
+            ("9223372036854775807 + 1", "9223372036854775808"),
+            ("9223372036854775807 * 9223372036854775807", "85070591730234615847396907784232501249"),
+            ("say min = -9223372036854775807 - 1; -min", "9223372036854775808"),
+            ("(9223372036854775807 + 1) - 1", "9223372036854775807"),
+            ("(9223372036854775807 * 4) / 2 > 9223372036854775807", "true"),
+            ("(9223372036854775807 + 1) == 9223372036854775807", "false"),
+            ("(9223372036854775807 + 1) * 0.5", "4.611686018427388e18"),
+            ("say f = function(n) { if (n < 2) { 1 } else { n * f(n - 1) } }; f(25)", "15511210043330985984000000"),
+            ("(9223372036854775807 * 2) / 0", "ERROR: division by zero: 18446744073709551614 / 0"),
+        ];
+
+        for (input, expected) in tests {
+            let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+            let mut evaluator = Evaluator::new();
+            evaluator.set_integer_overflow(IntegerOverflow::Promote);
+            let result = evaluator.eval_program(program);
+
+            assert_eq!(result.to_string(), expected, "wrong result for {:?}", input);
+        }
+
+        // results that fit again are plain integers
+        let program = Parser::new(Lexer::new("(9223372036854775807 + 1) - 1")).parse_program().unwrap();
+        let mut evaluator = Evaluator::new();
+        evaluator.set_integer_overflow(IntegerOverflow::Promote);
+        test_integer_object(evaluator.eval_program(program), i64::MAX);
+    }
+
+    #[test]
+    fn test_loops() {
+        let tests = vec![
+            ("say i = 0; while (i < 5) { say i = i + 1; } i", 5),
+            ("say sum = 0; for (x in [1, 2, 3, 4]) { say sum = sum + x; } sum", 10),
+            ("say i = 0; while (true) { say i = i + 1; if (i > 3) { break; } } i", 4),
+            (
+                "say sum = 0; for (x in [1, 2, 3, 4]) { if (x == 2) { continue; } say sum = sum + x; } sum",
+                8,
+            ),
+            (
+                "say find = function(items) { for (x in items) { if (x > 2) { return x; } } 0 }; find([1, 5, 3])",
+                5,
+            ),
+            ("say n = 0; for (ch in \"héllo\") { say n = n + 1; } n", 5),
+            (
+                "say count = 0;
+                for (a in [1, 2, 3]) { for (b in [1, 2, 3]) { if (b == 2) { break; } say count = count + 1; } }
+                count",
+                3,
+            ),
+        ];
+
+        for (input, expected) in tests {
+            test_integer_object(test_eval(input), expected);
+        }
+
+        test_null_object(test_eval("while (false) { 1 }"));
+        match test_eval("for (x in 5) { x }") {
+            Object::Error(message) => assert_eq!(message, "not iterable: INTEGER"),
+            other => panic!("expected an error, got={}", other),
+        }
+    }
+
+    #[test]
+    fn test_jumps_inside_expressions() {
+        let tests = vec![
+            (
+                "say out = []; for (x in [1, 2, 3]) { say y = [1, 2, if (x == 2) { continue; } else { x }]; out = concat(out, [y]) } out",
+                "[[1, 2, 1], [1, 2, 3]]",
+            ),
+            ("say out = []; for (i in [1, 2, 3]) { out = concat(out, [1 + if (i == 2) { continue; } else { i }]) } out", "[2, 4]"),
+            ("say d = [0, 0, 0]; for (i in range(3)) { d[i] += if (i == 2) { break; } else { i + 1 } } d", "[1, 2, 0]"),
+            ("say n = 0; while (true) { n += 1; say s = \"{n}{if (n > 2) { break; } else { n }}\"; } n", "3"),
+            ("say f = function(a, b) { a + b }; say t = 0; for (i in [1, 2, 3]) { t += f(i, b: if (i == 2) { continue; } else { i }) } t", "8"),
+            ("say f = function() { say y = [1, if (true) { return 5; } else { 2 }]; 7 }; f() + 1", "6"),
+            ("say f = function(a = if (true) { return 3; } else { 1 }) { a }; [f(), f(2)]", "[3, 2]"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "wrong result for {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_cyclic_values() {
+        let tests = vec![
+            ("say a = [0]; a[0] = a; a", "[[...]]"),
+            ("say a = [0]; a[0] = a; \"{[a, a]}\"", "[[[...]], [[...]]]"),
+            ("say d = {\"me\": 0}; d[\"me\"] = d; d", "{me: {...}}"),
+            ("say a = [0]; a[0] = a; say b = [0]; b[0] = b; a == b", "true"),
+            ("say a = [1, 0]; a[1] = a; say b = [2, 0]; b[1] = b; [a == b, a < b]", "[false, true]"),
+            ("say d = {\"me\": 0}; d[\"me\"] = d; say e = {\"me\": 0}; e[\"me\"] = e; d == e", "true"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "wrong result for {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_assignment() {
+        let tests = vec![
+            ("say x = 1; x = 5; x", 5),
+            ("say x = 1; x = x + 1", 2),
+            ("say x = 10; x += 5; x -= 3; x *= 2; x /= 4; x", 6),
+            ("say a = 1; say b = 1; a = b = 7; a + b", 14),
+            ("say count = 0; say inc = function() { count += 1 }; inc(); inc(); count", 2),
+            (
+                "say make = function() { say n = 0; function() { n = n + 10; n } }; say c = make(); c(); c()",
+                20,
+            ),
+            ("say x = 1; say f = function() { say x = 2; x = 3; x }; f() + x", 4),
+            ("say i = 0; while (i < 4) { i += 1; } i", 4),
+        ];
+
+        for (input, expected) in tests {
+            test_integer_object(test_eval(input), expected);
+        }
+
+        let errors = vec![
+            ("y = 1;", "cannot assign to undeclared name: y"),
+            ("say f = function() { z = 1 }; f();", "cannot assign to undeclared name: z"),
+            ("y += 1;", "Identifier not found: y"),
+            ("say x = 1; x += true;", "type mismatch: INTEGER + BOOLEAN"),
+        ];
+
+        for (input, expected) in errors {
+            match test_eval(input) {
+                Object::Error(message) => assert_eq!(message, expected, "wrong error for {:?}", input),
+                other => panic!("expected an error for {:?}, got={}", input, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_index_assignment() {
+        let tests = vec![
+            ("say a = [1, 2, 3]; a[1] = 20; a[1]", 20),
+            ("say a = [1, 2, 3]; say b = a; b[0] = 10; a[0]", 10),
+            ("say a = [1, 2, 3]; a[2] += 5; a[2]", 8),
+            (r#"say d = {"k": 1}; d["k"] = 7; d["k"]"#, 7),
+            (r#"say d = {}; say alias = d; alias["new"] = 3; d["new"]"#, 3),
+            (r#"say d = {"n": 2}; d["n"] *= 21; d["n"]"#, 42),
+            ("say a = [1]; say set = function(arr) { arr[0] = 9 }; set(a); a[0]", 9),
+            ("say grid = [[0, 0], [0, 0]]; say row = grid[1]; row[0] = 4; grid[1][0]", 4),
+            ("say a = [1, 2]; say b = push(a, 3); b[0] = 5; a[0]", 1),
+        ];
+
+        for (input, expected) in tests {
+            test_integer_object(test_eval(input), expected);
+        }
+
+        let errors = vec![
+            ("say a = [1, 2]; a[2] = 0;", "index out of range: 2 (length 2)"),
+            ("say a = [1, 2]; a[-1] = 0;", "index out of range: -1 (length 2)"),
+            (r#"say a = [1]; a["x"] = 0;"#, "array index must be INTEGER, got=STRING"),
+            ("say d = {}; d[[1]] = 0;", "unusable as Dictionary key: ARRAY"),
+            ("say s = 1; s[0] = 2;", "index assignment not supported: INTEGER"),
+        ];
+
+        for (input, expected) in errors {
+            match test_eval(input) {
+                Object::Error(message) => assert_eq!(message, expected, "wrong error for {:?}", input),
+                other => panic!("expected an error for {:?}, got={}", input, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_builtins_can_be_shadowed() {
+        let input = "say len = function(x) { 42 }; say f = function() { len([1]) }; f();";
+
+        test_integer_object(test_eval(input), 42);
+    }
+
+    #[test]
+    fn test_function_declarations() {
+        let tests = vec![
+            ("function double(x) { x * 2 }; double(4)", "8"),
+            ("double(4); function double(x) { x * 2 }", ""),
+            ("say y = double(4); function double(x) { x * 2 }; y", "8"),
+            (
+                "function isEven(n) { if (n == 0) { true } else { isOdd(n - 1) } }
+                 function isOdd(n) { if (n == 0) { false } else { isEven(n - 1) } }
+                 [isEven(10), isOdd(7), isEven(3)]",
+                "[true, true, false]",
+            ),
+            (
+                "say f = function(x) { say r = g(x); function g(y) { h(y) + 1 } function h(z) { z * 10 } r }; f(2)",
+                "21",
+            ),
+            ("if (true) { say v = inner(); function inner() { 5 } v }", "5"),
+            ("say total = 0; for (i in [1, 2, 3]) { total += sq(i); function sq(v) { v * v } } total", "14"),
+            ("function f() { 1 } function f() { 2 } f()", "2"),
+            ("function fact(n) { if (n < 2) { 1 } else { n * fact(n - 1) } } fact(20)", "2432902008176640000"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "wrong result for {:?}", input);
+        }
+
+        // the declaration statement itself is null
+        test_null_object(test_eval("function f() { 1 }"));
+    }
+
+    #[test]
+    fn test_function_parameters() {
+        let tests = vec![
+            ("say f = function(x, y = 10) { x + y }; [f(1), f(1, 2)]", "[11, 3]"),
+            ("say f = function(x, y = x * 2, z = y + 1) { [x, y, z] }; f(1)", "[1, 2, 3]"),
+            ("say f = function(first, ...rest) { [first, rest] }; [f(1), f(1, 2, 3)]", "[[1, []], [1, [2, 3]]]"),
+            ("say f = function(...all) { len(all) }; f()", "0"),
+            ("say f = function(a, b = 2, c = 3) { [a, b, c] }; f(1, c: 30)", "[1, 2, 30]"),
+            ("say f = function(a, b) { a - b }; f(b: 1, a: 10)", "9"),
+            ("function greet(name, greeting = \"hi\") { \"{greeting} {name}\" } greet(greeting: \"hey\", name: \"arash\")", "hey arash"),
+            ("say x = 1; say f = function(y = x) { y }; say x = 5; f()", "5"),
+            ("say f = function(a) { a }; f()", "wrong number of arguments: want=1, got=0"),
+            ("say f = function(a, b) { a }; f(1, 2, 3)", "wrong number of arguments: want=2, got=3"),
+            ("say f = function(a, b = 1) { a }; f(1, 2, 3)", "wrong number of arguments: want=1 to 2, got=3"),
+            ("say f = function(a, b, ...c) { a }; f(1)", "wrong number of arguments: want=at least 2, got=1"),
+            ("say f = function(a, b) { a }; f(b: 1)", "wrong number of arguments: want=2, got=1"),
+            ("say f = function(a) { a }; f(1, b: 2)", "unknown parameter name: b"),
+            ("say f = function(a, ...b) { a }; f(1, b: 2)", "unknown parameter name: b"),
+            ("say f = function(a) { a }; f(1, a: 2)", "multiple values for parameter: a"),
+            ("say f = function(a = boom) { a }; f()", "Identifier not found: boom"),
+            ("len(\"abc\", x: 1)", "named arguments are not supported by builtin functions"),
+        ];
+
+        for (input, expected) in tests {
+            let evaluated = test_eval(input);
+            let result = match &evaluated {
+                Object::Error(message) => message.clone(),
+                other => other.to_string(),
+            };
+            assert_eq!(result, expected, "wrong result for {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_arrow_functions() {
+        let tests = vec![
+            ("say double = x => x * 2; double(21)", "42"),
+            ("say add = (a, b = 10) => { say sum = a + b; sum }; [add(1), add(1, 2)]", "[11, 3]"),
+            ("say twice = f => x => f(f(x)); twice(x => x + 3)(1)", "7"),
+            ("say n = 5; say get = () => n; say n = 6; get()", "6"),
+            ("((first, ...rest) => rest)(1, 2, 3)", "[2, 3]"),
+            ("say f = x => x + 0; f", "function(x) { \n(x + 0)}\n"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "wrong result for {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_builtins_calling_functions() {
+        let tests = vec![
+            ("map([1, 2, 3], x => x * 2)", "[2, 4, 6]"),
+            ("map([[1], [2, 3]], len)", "[1, 2]"),
+            ("filter([1, 2, 3, 4], x => x % 2 == 0)", "[2, 4]"),
+            ("reduce([1, 2, 3, 4], (sum, x) => sum + x)", "10"),
+            ("reduce([], (sum, x) => sum + x, 0)", "0"),
+            ("sort_by([\"ccc\", \"a\", \"bb\"], len)", "[a, bb, ccc]"),
+            ("sort_by([[2, \"x\"], [1, \"y\"], [2, \"z\"]], pair => pair[0])", "[[1, y], [2, x], [2, z]]"),
+            ("say calls = 0; map([1, 2], x => { calls += 1 }); calls", "2"),
+            ("map([1, 2], x => x + true)", "ERROR: type mismatch: INTEGER + BOOLEAN"),
+            ("map(1, x => x)", "ERROR: argument to `map` must be ARRAY, got=INTEGER"),
+            ("map([1], 5)", "ERROR: not a function: INTEGER"),
+            ("reduce([], (a, b) => a)", "ERROR: reduce of an empty array with no initial value"),
+            ("sort_by([1, \"a\"], x => x)", "ERROR: cannot compare INTEGER with STRING"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "wrong result for {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_collection_builtins() {
+        let tests = vec![
+            ("rest([1, 2, 3])", "[2, 3]"),
+            ("last([1, 2, 3])", "3"),
+            ("sort([3, 1, 2])", "[1, 2, 3]"),
+            ("sort([\"b\", \"a\"])", "[a, b]"),
+            ("sort([3, 1, 2], (a, b) => b - a)", "[3, 2, 1]"),
+            ("sort([[2, 1], [1, 2], [2, 0]], (a, b) => a[0] - b[0])", "[[1, 2], [2, 1], [2, 0]]"),
+            ("say a = [2, 1]; sort(a); a", "[2, 1]"),
+            ("len(sort(range(50), (a, b) => (a * 31 + b * 17) % 7 - 3))", "50"),
+            ("sort(range(6), (a, b) => 1)", "[5, 4, 3, 2, 1, 0]"),
+            ("reverse([1, 2, 3])", "[3, 2, 1]"),
+            ("zip([1, 2, 3], [\"a\", \"b\"])", "[[1, a], [2, b]]"),
+            ("enumerate([\"a\", \"b\"])", "[[0, a], [1, b]]"),
+            ("range(4)", "[0, 1, 2, 3]"),
+            ("range(2, 5)", "[2, 3, 4]"),
+            ("range(10, 0, -3)", "[10, 7, 4, 1]"),
+            ("range(3, 1)", "[]"),
+            ("contains([1, [2], \"x\"], [2])", "true"),
+            ("contains([1, 2], 3)", "false"),
+            ("index_of([5, 6, 7], 7)", "2"),
+            ("index_of([5, 6, 7], 8)", "-1"),
+            ("slice([1, 2, 3, 4], 1, 3)", "[2, 3]"),
+            ("slice([1, 2, 3, 4], -2)", "[3, 4]"),
+            ("slice([1, 2, 3, 4], 3, 1)", "[]"),
+            ("slice([1, 2], 0, 10)", "[1, 2]"),
+            ("concat([1], [], [2, 3])", "[1, 2, 3]"),
+            ("concat()", "[]"),
+            ("flatten([1, [2, [3]], []])", "[1, 2, [3]]"),
+            ("unique([1, 2, 1, [3], [3], 1.0])", "[1, 2, [3]]"),
+            ("reduce([[1], [2, 3]], concat)", "[1, 2, 3]"),
+            ("map(zip([1, 2], [3, 4]), pair => pair[0] * pair[1])", "[3, 8]"),
+            ("filter(range(10), x => contains([2, 3, 5, 7], x))", "[2, 3, 5, 7]"),
+            ("sort([1, \"a\"])", "ERROR: cannot compare INTEGER with STRING"),
+            ("sort([1, 2], (a, b) => \"x\")", "ERROR: comparator of `sort` must return a number, got=STRING"),
+            ("sort([1, 2], (a, b) => a + true)", "ERROR: type mismatch: INTEGER + BOOLEAN"),
+            ("range(1, 5, 0)", "ERROR: step of `range` must not be zero"),
+            ("range(0, 1000000000000)", "ERROR: result of `range` is too long: 1000000000000 elements"),
+            ("range(9223372036854775807, -9223372036854775808, -1)", "ERROR: result of `range` is too long: 18446744073709551615 elements"),
+            ("len(range(9223372036854775800, 9223372036854775807, 3))", "3"),
+            ("range(\"a\")", "ERROR: argument to `range` must be INTEGER, got=STRING"),
+            ("range()", "ERROR: wrong number of arguments. got=0, want=1 to 3"),
+            ("zip([1], 2)", "ERROR: argument to `zip` must be ARRAY, got=INTEGER"),
+            ("slice([1], 0, 1, 2)", "ERROR: wrong number of arguments. got=4, want=2 or 3"),
+            ("rest(1)", "ERROR: argument to `rest` must be ARRAY, got=INTEGER"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "wrong result for {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_recursion_depth_limit() {
+        let count = "say f = function(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } };";
+
+        // on a test thread's small stack the default limit stops recursion before the stack ends
+        assert_eq!(test_eval(&format!("{} f(100000)", count)).to_string(), "ERROR: stack overflow");
+
+        // a thousand tree-walker calls need a big stack, and a limit raised to match it
+        let runner = std::thread::Builder::new().stack_size(256 * 1024 * 1024).spawn(move || {
+            let eval = |evaluator: &mut Evaluator, input: &str| {
+                evaluator.eval_program(Parser::new(Lexer::new(input)).parse_program().unwrap())
+            };
+            let tests = vec![
+                (format!("{} f(1000)", count), "1000"),
+                (format!("{} f(5000)", count), "ERROR: stack overflow"),
+                ("say f = function() { f() }; f()".to_string(), "ERROR: stack overflow"),
+                (format!("{} map([5000], f)", count), "ERROR: stack overflow"),
+            ];
+            for (input, expected) in tests {
+                let mut evaluator = Evaluator::new();
+                evaluator.set_stack_limit(128 * 1024 * 1024);
+                assert_eq!(eval(&mut evaluator, &input).to_string(), expected, "wrong result for {:?}", input);
+            }
+
+            // the depth is back to zero after an overflow
+            let mut evaluator = Evaluator::new();
+            evaluator.set_stack_limit(128 * 1024 * 1024);
+            assert_eq!(eval(&mut evaluator, &format!("{} f(5000)", count)).to_string(), "ERROR: stack overflow");
+            assert_eq!(eval(&mut evaluator, "f(1000)"), Object::Integer(1000));
+        });
+        runner.unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn test_string_builtins() {
+        let tests = vec![
+            ("len(\"héllo\")", "5"),
+            ("split(\" a  b c \")", "[a, b, c]"),
+            ("split(\"a,b,,c\", \",\")", "[a, b, , c]"),
+            ("join([1, \"b\", [2]], \"-\")", "1-b-[2]"),
+            ("join(split(\"x y\"))", "xy"),
+            ("trim(\"  hi \")", "hi"),
+            ("upper(\"straße\")", "STRASSE"),
+            ("lower(\"ABC\")", "abc"),
+            ("replace(\"a-b-c\", \"-\", \"+\")", "a+b+c"),
+            ("starts_with(\"hello\", \"he\")", "true"),
+            ("ends_with(\"hello\", \"he\")", "false"),
+            ("find(\"héllo\", \"l\")", "2"),
+            ("find(\"hello\", \"z\")", "-1"),
+            ("substr(\"héllo\", 1, 3)", "éll"),
+            ("substr(\"hello\", -3)", "llo"),
+            ("substr(\"hello\", 4, 10)", "o"),
+            ("chars(\"hé\")", "[h, é]"),
+            ("repeat(\"ab\", 3)", "ababab"),
+            ("pad_left(\"7\", 3, \"0\")", "007"),
+            ("pad_right(\"é\", 3) + \"|\"", "é  |"),
+            ("pad_left(\"long\", 2)", "long"),
+            ("to_int(\" -42 \") + 1", "-41"),
+            ("to_int(\"100000000000000000000\")", "100000000000000000000"),
+            ("to_int(-2.7)", "-2"),
+            ("to_string([1, 2]) + \"!\"", "[1, 2]!"),
+            (r#"format(r"{} + {} = {0}{{}}", 1, 2)"#, "1 + 2 = 1{}"),
+            (r#"format("\{\}-\{1\}", "a", [2])"#, "a-[2]"),
+            ("len(\"a\", \"b\")", "ERROR: wrong number of arguments. got=2, want=1"),
+            ("split(1)", "ERROR: argument to `split` must be STRING, got=INTEGER"),
+            ("split(\"ab\", \"\")", "ERROR: separator of `split` must not be empty, use `chars` instead"),
+            ("join(\"ab\")", "ERROR: argument to `join` must be ARRAY, got=STRING"),
+            ("trim()", "ERROR: wrong number of arguments. got=0, want=1"),
+            ("replace(\"a\", \"a\")", "ERROR: wrong number of arguments. got=2, want=3"),
+            ("substr(\"hello\", 1, -1)", "ERROR: length of `substr` must not be negative"),
+            ("substr(\"hello\", \"1\")", "ERROR: argument to `substr` must be INTEGER, got=STRING"),
+            ("repeat(\"a\", -1)", "ERROR: count of `repeat` must not be negative"),
+            ("repeat(\"ab\", 9223372036854775807)", "ERROR: result of `repeat` is too long: 2 * 9223372036854775807"),
+            ("pad_right(\"a\", 9223372036854775807)", "ERROR: result of `pad_right` is too long: width 9223372036854775807"),
+            ("pad_left(\"a\", 3, \"ab\")", "ERROR: fill of `pad_left` must be a single character"),
+            ("to_int(\"12a\")", "ERROR: could not parse \"12a\" as integer"),
+            ("to_int(true)", "ERROR: argument to `to_int` must be STRING, FLOAT or INTEGER, got=BOOLEAN"),
+            (r#"format(r"{} {}", 1)"#, "ERROR: not enough arguments for format template: wants argument 1, got=1"),
+            (
+                r#"format(r"{99999999999999999999}", 1)"#,
+                "ERROR: not enough arguments for format template: wants argument 99999999999999999999, got=1",
+            ),
+            (r#"format(r"{x}", 1)"#, "ERROR: invalid placeholder in format template, expected `{}` or `{N}`"),
+            (r#"format(r"}", 1)"#, "ERROR: unmatched `}` in format template, write `}}` for a literal brace"),
+            ("format()", "ERROR: wrong number of arguments. got=0, want=at least 1"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "wrong result for {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_host_closure_builtin() {
+        use std::{cell::Cell, rc::Rc};
+
+        use crate::object::BuiltinFunction;
+
+        // counts its calls in host state and calls its argument back
+        let calls = Rc::new(Cell::new(0));
+        let counted = Rc::clone(&calls);
+        let builtin = BuiltinFunction::new(move |context, arguments| {
+            counted.set(counted.get() + 1);
+            context.apply(&arguments[0], vec![Object::Integer(counted.get())])
+        });
+
+        let mut evaluator = Evaluator::new();
+        evaluator.environment.borrow_mut().set(String::from("host"), Object::Builtin(builtin));
+        let program = Parser::new(Lexer::new("host(n => n * 10) + host(n => n)")).parse_program().unwrap();
+
+        assert_eq!(evaluator.eval_program(program).to_string(), "12");
+        assert_eq!(calls.get(), 2);
+    }
+
     #[test]
     fn test_function_object() {
         let input = "function(x) {x + 23}";
@@ -706,6 +2118,21 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_interpolated_strings() {
+        let tests = vec![
+            (r#"say name = "arash"; say items = [1, 2]; "Hello {name}, you have {len(items)} items""#, "Hello arash, you have 2 items"),
+            (r#""{1.5} {true} {[1, "a"]} {100000000000000000000}""#, "1.5 true [1, a] 100000000000000000000"),
+            (r#"say f = function(x) { "<{x}>" }; "{f(1)}{f("b")}""#, "<1><b>"),
+            (r#""nested {"inner {1 + 1}"} \{literal\}""#, "nested inner 2 {literal}"),
+            (r#""missing {nope}""#, "ERROR: Identifier not found: nope"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "wrong result for {:?}", input);
+        }
+    }
+
     #[test]
     fn test_string_concatenation() {
         let input = r#"
@@ -736,6 +2163,7 @@ mod test {
 
         match evaluated {
             Object::Array(elements) => {
+                let elements = elements.borrow();
                 assert_eq!(
                     elements.len(),
                     3,
@@ -834,6 +2262,7 @@ mod test {
 
         match evaluated {
             Object::DictObject(hash) => {
+                let hash = hash.borrow();
                 let expected = vec![
                     (Object::StringObject("one".to_string()).dict_key(), 1),
                     (Object::StringObject("two".to_string()).dict_key(), 2),
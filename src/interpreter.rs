@@ -0,0 +1,268 @@
+use std::{fmt::Display, fs, path::Path};
+
+use crate::{
+    convert::IntoObject,
+    engine::{Backend, Engine},
+    evaluator::IntegerOverflow,
+    lexer::Lexer,
+    object::{BuiltinFunction, CallContext, Object},
+    parser::{Parser, ParserError},
+    script::strip_shebang,
+    token::Span,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InterpreterError {
+    Io(String),
+    Parse(Vec<ParserError>),
+    Runtime { message: String, span: Option<Span> },
+}
+
+impl Display for InterpreterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(message) => write!(f, "{}", message),
+            Self::Parse(errors) => {
+                let errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+                write!(f, "{}", errors.join("\n"))
+            }
+            Self::Runtime { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for InterpreterError {}
+
+//      TwoT embedded in a host program. Globals are kept between calls, so a program can
+//      define functions that the host calls later, and the host can hand values and
+//      builtins to the programs it runs. It runs on the VM unless asked for another backend:
+//      the tree-walker recurses on the host's stack, and only goes as deep as
+//      `DEFAULT_STACK_LIMIT` allows unless the host has a bigger stack and raises the limit
+pub struct Interpreter {
+    engine: Engine,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter::with_backend(Backend::Vm)
+    }
+
+    pub fn with_backend(backend: Backend) -> Interpreter {
+        Interpreter {
+            engine: Engine::new(backend),
+        }
+    }
+
+    pub fn set_integer_overflow(&mut self, integer_overflow: IntegerOverflow) {
+        self.engine.set_integer_overflow(integer_overflow);
+    }
+
+    // the native stack, in bytes, the tree-walker may use for nested calls
+    pub fn set_stack_limit(&mut self, bytes: usize) {
+        self.engine.set_stack_limit(bytes);
+    }
+
+    // the value of the program's last expression statement
+    pub fn eval_str(&mut self, source: &str) -> Result<Object, InterpreterError> {
+        let mut parser = Parser::new(Lexer::new(source));
+        let program = parser.parse_program().expect("error parsing program");
+        if !parser.errors().is_empty() {
+            return Err(InterpreterError::Parse(parser.errors().to_vec()));
+        }
+
+        let result = self.engine.run(program);
+        self.check(result)
+    }
+
+    pub fn eval_file(&mut self, path: impl AsRef<Path>) -> Result<Object, InterpreterError> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|e| InterpreterError::Io(format!("cannot read {}: {}", path.display(), e)))?;
+        self.eval_str(strip_shebang(&source).as_str())
+    }
+
+    pub fn set_global(&mut self, name: &str, value: impl IntoObject) {
+        self.engine.set_global(name, value.into_object());
+    }
+
+    // builtins are globals too, so this also finds `len` and the registered ones
+    pub fn get_global(&self, name: &str) -> Option<Object> {
+        self.engine.get_global(name)
+    }
+
+    pub fn call_function(&mut self, name: &str, arguments: Vec<Object>) -> Result<Object, InterpreterError> {
+        let function = self.get_global(name).ok_or_else(|| InterpreterError::Runtime {
+            message: format!("Identifier not found: {}", name),
+            span: None,
+        })?;
+
+        let result = self.engine.call_function(&function, arguments);
+        self.check(result)
+    }
+
+    // makes a Rust closure callable from TwoT under `name`
+    pub fn register_builtin(
+        &mut self,
+        name: &str,
+        function: impl Fn(&mut dyn CallContext, Vec<Object>) -> Object + 'static,
+    ) {
+        self.set_global(name, Object::Builtin(BuiltinFunction::new(function)));
+    }
+
+    fn check(&self, result: Object) -> Result<Object, InterpreterError> {
+        match result {
+            Object::Error(message) => Err(InterpreterError::Runtime {
+                message,
+                span: self.engine.error_span(),
+            }),
+            value => Ok(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::Cell, fs, rc::Rc};
+
+    use crate::{engine::Backend, object::Object};
+
+    use super::{Interpreter, InterpreterError};
+
+    const BACKENDS: [Backend; 2] = [Backend::TreeWalker, Backend::Vm];
+
+    #[test]
+    fn test_eval_str_keeps_state() {
+        for backend in BACKENDS {
+            let mut interpreter = Interpreter::with_backend(backend);
+
+            assert_eq!(interpreter.eval_str("say a = 20; a + 1"), Ok(Object::Integer(21)));
+            assert_eq!(interpreter.eval_str("a * 2"), Ok(Object::Integer(40)));
+            assert_eq!(interpreter.get_global("a"), Some(Object::Integer(20)));
+            assert_eq!(interpreter.get_global("missing"), None);
+            assert!(matches!(interpreter.get_global("len"), Some(Object::Builtin(_))));
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        for backend in BACKENDS {
+            let mut interpreter = Interpreter::with_backend(backend);
+
+            match interpreter.eval_str("say = 1;") {
+                Err(InterpreterError::Parse(errors)) => {
+                    assert_eq!(errors[0].to_string(), "line 1, column 5: expected next token to be Ident, got== instead")
+                }
+                other => panic!("expected a parse error on {:?}, got={:?}", backend, other),
+            }
+            // an expression missing where one is required is reported, not a crash
+            for input in ["if () { 1 }", "say x = [1]; x[]", "{:1}"] {
+                let result = interpreter.eval_str(input);
+                assert!(matches!(result, Err(InterpreterError::Parse(_))), "expected a parse error for {:?}, got={:?}", input, result);
+            }
+
+            match interpreter.eval_str("say f = function(x) { x + true };\nf(1)") {
+                Err(InterpreterError::Runtime { message, span: Some(span) }) => {
+                    assert_eq!(message, "type mismatch: INTEGER + BOOLEAN");
+                    assert_eq!((span.start.line, span.start.column), (1, 23));
+                }
+                other => panic!("expected a runtime error on {:?}, got={:?}", backend, other),
+            }
+
+            let error = interpreter.eval_file("/nonexistent/program.twot").unwrap_err();
+            assert!(error.to_string().starts_with("cannot read /nonexistent/program.twot"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_deep_recursion_is_an_error() {
+        // a test thread has a small stack, deep recursion must still not abort the host
+        let count = "say f = function(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } };";
+        for backend in BACKENDS {
+            let mut interpreter = Interpreter::with_backend(backend);
+            interpreter.eval_str(count).unwrap();
+
+            match interpreter.eval_str("f(100000)") {
+                Err(InterpreterError::Runtime { message, .. }) => assert_eq!(message, "stack overflow"),
+                other => panic!("expected a stack overflow on {:?}, got={:?}", backend, other),
+            }
+            assert_eq!(interpreter.eval_str("f(10)"), Ok(Object::Integer(10)));
+        }
+        assert_eq!(Interpreter::new().eval_str(&format!("{} f(1000)", count)), Ok(Object::Integer(1000)));
+    }
+
+    #[test]
+    fn test_host_values_and_calls() {
+        for backend in BACKENDS {
+            let mut interpreter = Interpreter::with_backend(backend);
+
+            interpreter.set_global("limit", Object::Integer(3));
+            interpreter
+                .eval_str("function clamp(x, low = 0) { if (x > limit) { limit } else if (x < low) { low } else { x } }")
+                .unwrap();
+            interpreter.set_global("limit", Object::Integer(5));
+
+            assert_eq!(interpreter.call_function("clamp", vec![Object::Integer(9)]), Ok(Object::Integer(5)));
+            assert_eq!(interpreter.call_function("clamp", vec![Object::Integer(-2)]), Ok(Object::Integer(0)));
+            assert_eq!(
+                interpreter.call_function("len", vec![Object::StringObject(String::from("four"))]),
+                Ok(Object::Integer(4))
+            );
+
+            match interpreter.call_function("clamp", vec![]) {
+                Err(InterpreterError::Runtime { message, .. }) => {
+                    assert_eq!(message, "wrong number of arguments: want=1 to 2, got=0")
+                }
+                other => panic!("expected an arity error on {:?}, got={:?}", backend, other),
+            }
+            match interpreter.call_function("nothing", vec![]) {
+                Err(InterpreterError::Runtime { message, span: None }) => {
+                    assert_eq!(message, "Identifier not found: nothing")
+                }
+                other => panic!("expected a missing function on {:?}, got={:?}", backend, other),
+            }
+
+            // the program keeps working after calls from the host
+            assert_eq!(interpreter.eval_str("clamp(4) + limit"), Ok(Object::Integer(9)));
+        }
+    }
+
+    #[test]
+    fn test_register_builtin() {
+        for backend in BACKENDS {
+            let mut interpreter = Interpreter::with_backend(backend);
+
+            let logged = Rc::new(Cell::new(0));
+            let counter = Rc::clone(&logged);
+            interpreter.register_builtin("log", move |_, arguments| {
+                counter.set(counter.get() + arguments.len());
+                Object::Null
+            });
+            interpreter.register_builtin("twice", |context, arguments| {
+                let once = context.apply(&arguments[0], vec![arguments[1].clone()]);
+                context.apply(&arguments[0], vec![once])
+            });
+
+            let result = interpreter.eval_str("log(1, 2); say add = function(x) { log(x); x + 10 }; twice(add, 1)");
+            assert_eq!(result, Ok(Object::Integer(21)), "on {:?}", backend);
+            assert_eq!(logged.get(), 4);
+        }
+    }
+
+    #[test]
+    fn test_eval_file() {
+        let path = std::env::temp_dir().join(format!("twot-interpreter-{}.twot", std::process::id()));
+        fs::write(&path, "#!/usr/bin/env inter\nsay answer = 6 * 7;\nanswer").unwrap();
+
+        for backend in BACKENDS {
+            let mut interpreter = Interpreter::with_backend(backend);
+            assert_eq!(interpreter.eval_file(&path), Ok(Object::Integer(42)));
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,19 +1,65 @@
-use crate::token::{lookup_ident, Token, TokenKind};
+use std::rc::Rc;
 
+use crate::{
+    diagnostics::ErrorCode,
+    token::{lookup_ident, Position, Span, Token, TokenKind},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexerError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub span: Span,
+}
+
+// the input is shared, so the parser can look ahead on a copy without copying the source
 pub struct Lexer {
-    input: Vec<char>,
+    input: Rc<[char]>,
     position: usize,
     read_position: usize,
     ch: char,
+    line: usize,
+    column: usize,
+    offset: usize,
+    errors: Vec<LexerError>,
+    keep_doc_comments: bool,
+    interpolations: Vec<Interpolation>,
+}
+
+//      a string whose text was left at `{` to lex an embedded expression. `depth` counts the
+//      braces opened inside the expression, the `}` seen at depth 0 resumes the string
+#[derive(Clone)]
+struct Interpolation {
+    opening: Span,
+    triple: bool,
+    depth: usize,
+}
+
+// what stopped `read_string_body`
+enum StringEnd {
+    Quote,
+    Interpolation,
+    Eof,
 }
 
 impl Lexer {
     pub fn new(input: &str) -> Lexer {
+        Lexer::new_at_line(input, 1)
+    }
+
+    // used by the REPL so that spans keep counting lines across the whole session
+    pub fn new_at_line(input: &str, line: usize) -> Lexer {
         let mut lexer = Lexer {
             input: input.chars().collect(),
             position: 0,
             read_position: 0,
             ch: Default::default(),
+            line,
+            column: 1,
+            offset: 0,
+            errors: vec![],
+            keep_doc_comments: false,
+            interpolations: vec![],
         };
 
         lexer.read_char();
@@ -21,7 +67,38 @@ impl Lexer {
         lexer
     }
 
+    // a lexer that continues from this one's position without consuming its tokens.
+    // Errors it finds are its own, the real pass reports them again
+    pub fn lookahead(&self) -> Lexer {
+        Lexer {
+            input: Rc::clone(&self.input),
+            errors: vec![],
+            interpolations: self.interpolations.clone(),
+            ..*self
+        }
+    }
+
+    // `///` comments become `DocComment` tokens instead of being skipped,
+    // so documentation tools can attach them to the `say` binding that follows
+    pub fn with_doc_comments(mut self) -> Lexer {
+        self.keep_doc_comments = true;
+        self
+    }
+
     fn read_char(&mut self) {
+        // move line/column/offset past the character we are leaving behind
+        if self.read_position > 0 {
+            if let Some(&previous) = self.input.get(self.position) {
+                self.offset += previous.len_utf8();
+                if previous == '\n' {
+                    self.line += 1;
+                    self.column = 1;
+                } else {
+                    self.column += 1;
+                }
+            }
+        }
+
         if self.read_position >= self.input.len() {
             self.ch = '\0';
         } else {
@@ -31,17 +108,37 @@ impl Lexer {
         self.read_position += 1;
     }
 
+    fn current_position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+            offset: self.offset,
+        }
+    }
+
     pub fn next_token(&mut self) -> Token {
-        self.skip_whitespace();
+        self.skip_whitespace_and_comments();
 
-        let token = match self.ch {
+        let start = self.current_position();
+        let mut token = match self.ch {
+            '/' if self.is_doc_comment() => {
+                let literal = self.read_doc_comment();
+                return Token {
+                    kind: TokenKind::DocComment,
+                    literal,
+                    span: Span::new(start, self.current_position()),
+                };
+            }
             '=' => {
                 if self.peek_char() == '=' {
                     self.read_char();
                     Token {
                         kind: TokenKind::Eq,
                         literal: String::from("=="),
+                        span: Default::default(),
                     }
+                } else if self.peek_char() == '>' {
+                    self.read_two_char_token(TokenKind::FatArrow)
                 } else {
                     Lexer::new_token(TokenKind::Assign, self.ch)
                 }
@@ -50,74 +147,235 @@ impl Lexer {
             '(' => Lexer::new_token(TokenKind::Lparen, self.ch),
             ')' => Lexer::new_token(TokenKind::Rparen, self.ch),
             ',' => Lexer::new_token(TokenKind::Comma, self.ch),
-            '+' => Lexer::new_token(TokenKind::Plus, self.ch),
-            '{' => Lexer::new_token(TokenKind::Lbrace, self.ch),
-            '}' => Lexer::new_token(TokenKind::Rbrace, self.ch),
-            '\0' => Token {
-                kind: TokenKind::Eof,
-                literal: "".to_string(),
+            '+' => self.read_operator(TokenKind::Plus, TokenKind::PlusAssign),
+            '{' => {
+                if let Some(interpolation) = self.interpolations.last_mut() {
+                    interpolation.depth += 1;
+                }
+                Lexer::new_token(TokenKind::Lbrace, self.ch)
+            }
+            '}' => match self.interpolations.last_mut() {
+                Some(interpolation) if interpolation.depth == 0 => self.continue_string(),
+                Some(interpolation) => {
+                    interpolation.depth -= 1;
+                    Lexer::new_token(TokenKind::Rbrace, self.ch)
+                }
+                None => Lexer::new_token(TokenKind::Rbrace, self.ch),
             },
-            '-' => Lexer::new_token(TokenKind::Minus, self.ch),
+            '\0' => {
+                for interpolation in std::mem::take(&mut self.interpolations) {
+                    self.push_unterminated_string(interpolation.opening);
+                }
+                Token {
+                    kind: TokenKind::Eof,
+                    literal: "".to_string(),
+                    span: Default::default(),
+                }
+            }
+            '-' => self.read_operator(TokenKind::Minus, TokenKind::MinusAssign),
             '!' => {
                 if self.peek_char() == '=' {
                     self.read_char();
                     Token {
                         kind: TokenKind::NotEq,
                         literal: String::from("!="),
+                        span: Default::default(),
                     }
                 } else {
                     Lexer::new_token(TokenKind::Bang, self.ch)
                 }
             }
-            '/' => Lexer::new_token(TokenKind::Slash, self.ch),
-            '*' => Lexer::new_token(TokenKind::Asterisk, self.ch),
-            '<' => Lexer::new_token(TokenKind::Lt, self.ch),
-            '>' => Lexer::new_token(TokenKind::Gt, self.ch),
-            '"' => Token {
-                kind: TokenKind::String,
-                literal: self.read_string(),
-            },
+            '/' => self.read_operator(TokenKind::Slash, TokenKind::SlashAssign),
+            '*' if self.peek_char() == '*' => self.read_two_char_token(TokenKind::Power),
+            '*' => self.read_operator(TokenKind::Asterisk, TokenKind::AsteriskAssign),
+            '%' => Lexer::new_token(TokenKind::Percent, self.ch),
+            '~' => Lexer::new_token(TokenKind::Tilde, self.ch),
+            '^' => Lexer::new_token(TokenKind::BitXor, self.ch),
+            '<' => match self.peek_char() {
+                '=' => self.read_two_char_token(TokenKind::LtEq),
+                '<' => self.read_two_char_token(TokenKind::ShiftLeft),
+                _ => Lexer::new_token(TokenKind::Lt, self.ch),
+            },
+            '>' => match self.peek_char() {
+                '=' => self.read_two_char_token(TokenKind::GtEq),
+                '>' => self.read_two_char_token(TokenKind::ShiftRight),
+                _ => Lexer::new_token(TokenKind::Gt, self.ch),
+            },
+            '&' if self.peek_char() == '&' => self.read_two_char_token(TokenKind::And),
+            '&' => Lexer::new_token(TokenKind::BitAnd, self.ch),
+            '|' if self.peek_char() == '|' => self.read_two_char_token(TokenKind::Or),
+            '|' => Lexer::new_token(TokenKind::BitOr, self.ch),
+            '"' => self.read_string(start, false),
+            'r' if self.peek_char() == '"' => self.read_string(start, true),
             '[' => Lexer::new_token(TokenKind::Lbracket, self.ch),
             ']' => Lexer::new_token(TokenKind::Rbracket, self.ch),
             ':' => Lexer::new_token(TokenKind::Colon, self.ch),
+            '.' if self.peek_char() == '.' && self.peek_char_at(2) == '.' => {
+                self.read_char();
+                self.read_char();
+                Token {
+                    kind: TokenKind::Ellipsis,
+                    literal: String::from("..."),
+                    span: Default::default(),
+                }
+            }
             _ => {
-                return if Lexer::is_letter(self.ch) {
+                let mut token = if Lexer::is_letter(self.ch) {
                     let literal = self.read_identifier();
                     let kind = lookup_ident(&literal);
-                    Token { kind, literal }
-                } else if Lexer::is_digit(self.ch) {
-                    let kind = TokenKind::Int;
-                    let literal = self.read_number();
-                    Token { kind, literal }
+                    Token { kind, literal, span: Default::default() }
+                } else if Lexer::is_digit(self.ch) || (self.ch == '.' && Lexer::is_digit(self.peek_char())) {
+                    let (kind, literal) = self.read_number();
+                    Token { kind, literal, span: Default::default() }
                 } else {
-                    Lexer::new_token(TokenKind::Illegal, self.ch)
+                    let token = Lexer::new_token(TokenKind::Illegal, self.ch);
+                    self.read_char();
+                    self.errors.push(LexerError {
+                        code: ErrorCode::IllegalCharacter,
+                        message: format!("illegal character `{}`", token.literal),
+                        span: Span::new(start, self.current_position()),
+                    });
+                    token
                 };
+                token.span = Span::new(start, self.current_position());
+                return token;
             }
         };
 
         self.read_char();
+        token.span = Span::new(start, self.current_position());
+
+        token
+    }
+
+    // errors found since the last call, in source order
+    pub fn take_errors(&mut self) -> Vec<LexerError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        loop {
+            while self.ch.is_ascii_whitespace() {
+                self.read_char();
+            }
 
-        return token;
+            match (self.ch, self.peek_char()) {
+                ('/', '/') if !self.is_doc_comment() => self.skip_line_comment(),
+                ('/', '*') => self.skip_block_comment(),
+                _ => return,
+            }
+        }
+    }
+
+    // like Rust, `////` is a plain comment rather than a doc comment
+    fn is_doc_comment(&self) -> bool {
+        self.keep_doc_comments
+            && self.ch == '/'
+            && self.peek_char() == '/'
+            && self.peek_char_at(2) == '/'
+            && self.peek_char_at(3) != '/'
     }
 
-    fn skip_whitespace(&mut self) {
-        while self.ch.is_ascii_whitespace() {
+    fn skip_line_comment(&mut self) {
+        while self.ch != '\n' && self.ch != '\0' {
             self.read_char();
         }
     }
 
+    // block comments nest, so `/* a /* b */ c */` is a single comment
+    fn skip_block_comment(&mut self) {
+        let start = self.current_position();
+        self.read_char();
+        self.read_char();
+        let opening = Span::new(start, self.current_position());
+
+        let mut depth = 1;
+        while depth > 0 {
+            match (self.ch, self.peek_char()) {
+                ('\0', _) => {
+                    self.errors.push(LexerError {
+                        code: ErrorCode::UnterminatedComment,
+                        message: String::from("unterminated block comment"),
+                        span: opening,
+                    });
+                    return;
+                }
+                ('/', '*') => {
+                    depth += 1;
+                    self.read_char();
+                }
+                ('*', '/') => {
+                    depth -= 1;
+                    self.read_char();
+                }
+                _ => {}
+            }
+            self.read_char();
+        }
+    }
+
+    // the text after `///`, without the single space that usually follows it
+    fn read_doc_comment(&mut self) -> String {
+        for _ in 0..3 {
+            self.read_char();
+        }
+        if self.ch == ' ' {
+            self.read_char();
+        }
+
+        let mut text = String::new();
+        while self.ch != '\n' && self.ch != '\0' {
+            text.push(self.ch);
+            self.read_char();
+        }
+        text.trim_end().to_string()
+    }
+
     fn peek_char(&self) -> char {
-        return if self.read_position >= self.input.len() {
+        self.peek_char_at(1)
+    }
+
+    // the character `distance` places after the current one
+    fn peek_char_at(&self, distance: usize) -> char {
+        let position = self.read_position + distance - 1;
+        if position >= self.input.len() {
             '\0'
         } else {
-            self.input[self.read_position]
-        };
+            self.input[position]
+        }
+    }
+
+    // `+` or `+=`, depending on whether the operator is followed by `=`
+    fn read_operator(&mut self, kind: TokenKind, assign_kind: TokenKind) -> Token {
+        if self.peek_char() != '=' {
+            return Lexer::new_token(kind, self.ch);
+        }
+
+        let literal = format!("{}=", self.ch);
+        self.read_char();
+        Token {
+            kind: assign_kind,
+            literal,
+            span: Default::default(),
+        }
+    }
+
+    // operators such as `<=` or `&&`, made of the current and the next character
+    fn read_two_char_token(&mut self, kind: TokenKind) -> Token {
+        let literal = format!("{}{}", self.ch, self.peek_char());
+        self.read_char();
+        Token {
+            kind,
+            literal,
+            span: Default::default(),
+        }
     }
 
     fn new_token(kind: TokenKind, ch: char) -> Token {
         Token {
             kind,
             literal: ch.to_string(),
+            span: Default::default(),
         }
     }
 
@@ -140,31 +398,198 @@ impl Lexer {
         ch.is_numeric()
     }
 
-    fn read_number(&mut self) -> String {
-        let mut num = String::from("");
+    // `12` is an Int; `1.5`, `.5`, `1e-3` and `2.5E+3` are Floats. A `.` or an `e` only
+    // belongs to the number when a digit follows it
+    fn read_number(&mut self) -> (TokenKind, String) {
+        let mut kind = TokenKind::Int;
+        let mut num = self.read_digits();
 
-        while Lexer::is_digit(self.ch) {
+        if self.ch == '.' && Lexer::is_digit(self.peek_char()) {
+            kind = TokenKind::Float;
             num.push(self.ch);
             self.read_char();
+            num.push_str(self.read_digits().as_str());
+        }
+
+        if self.ch == 'e' || self.ch == 'E' {
+            let sign = matches!(self.peek_char(), '+' | '-');
+            let digit = if sign { self.peek_char_at(2) } else { self.peek_char() };
+
+            if Lexer::is_digit(digit) {
+                kind = TokenKind::Float;
+                num.push(self.ch);
+                self.read_char();
+                if sign {
+                    num.push(self.ch);
+                    self.read_char();
+                }
+                num.push_str(self.read_digits().as_str());
+            }
+        }
+
+        (kind, num)
+    }
+
+    fn read_digits(&mut self) -> String {
+        let mut digits = String::from("");
+
+        while Lexer::is_digit(self.ch) {
+            digits.push(self.ch);
+            self.read_char();
+        }
+        digits
+    }
+
+    // `"..."` or `"""..."""`, and their raw `r` forms that keep backslashes and braces as
+    // they are. A `{` in a non-raw string starts an embedded expression: the text before it
+    // becomes an `InterpolationStart` token. A newline right after an opening `"""` is dropped
+    fn read_string(&mut self, start: Position, raw: bool) -> Token {
+        if raw {
+            self.read_char();
+        }
+        let triple = self.peek_char() == '"' && self.peek_char_at(2) == '"';
+        for _ in 0..if triple { 3 } else { 1 } {
+            self.read_char();
         }
-        num
+        let opening = Span::new(start, self.current_position());
+        if triple && self.ch == '\n' {
+            self.read_char();
+        }
+
+        let (literal, end) = self.read_string_body(triple, raw);
+        let kind = match end {
+            StringEnd::Quote => TokenKind::String,
+            StringEnd::Interpolation => {
+                self.interpolations.push(Interpolation { opening, triple, depth: 0 });
+                TokenKind::InterpolationStart
+            }
+            StringEnd::Eof => {
+                self.push_unterminated_string(opening);
+                TokenKind::String
+            }
+        };
+        Token { kind, literal, span: Default::default() }
     }
 
-    fn read_string(&mut self) -> String {
-        let position = self.position + 1;
+    // the text between the `}` closing an embedded expression and the next `{` or the closing quote
+    fn continue_string(&mut self) -> Token {
+        let interpolation = self.interpolations.pop().expect("an interpolation is open");
         self.read_char();
-        while self.ch != '"' && self.ch != '\0' {
+
+        let (literal, end) = self.read_string_body(interpolation.triple, false);
+        let kind = match end {
+            StringEnd::Quote => TokenKind::InterpolationEnd,
+            StringEnd::Interpolation => {
+                self.interpolations.push(interpolation);
+                TokenKind::InterpolationMiddle
+            }
+            StringEnd::Eof => {
+                self.push_unterminated_string(interpolation.opening);
+                TokenKind::InterpolationEnd
+            }
+        };
+        Token { kind, literal, span: Default::default() }
+    }
+
+    // stops on the last character of the closing quote, or on the `{`
+    fn read_string_body(&mut self, triple: bool, raw: bool) -> (String, StringEnd) {
+        let mut string = String::new();
+        loop {
+            match self.ch {
+                '\0' => return (string, StringEnd::Eof),
+                '"' if !triple => return (string, StringEnd::Quote),
+                '"' if self.peek_char() == '"' && self.peek_char_at(2) == '"' => {
+                    self.read_char();
+                    self.read_char();
+                    return (string, StringEnd::Quote);
+                }
+                '{' if !raw => return (string, StringEnd::Interpolation),
+                '\\' if !raw => {
+                    if let Some(ch) = self.read_escape() {
+                        string.push(ch);
+                    }
+                }
+                ch => {
+                    string.push(ch);
+                    self.read_char();
+                }
+            }
+        }
+    }
+
+    fn push_unterminated_string(&mut self, opening: Span) {
+        self.errors.push(LexerError {
+            code: ErrorCode::UnterminatedString,
+            message: String::from("unterminated string"),
+            span: opening,
+        });
+    }
+
+    // reads the escape sequence starting at the current `\`; None after reporting a bad one
+    fn read_escape(&mut self) -> Option<char> {
+        let start = self.current_position();
+        self.read_char();
+
+        let escaped = match self.ch {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+            '{' => '{',
+            '}' => '}',
+            'u' if self.peek_char() == '{' => return self.read_unicode_escape(start),
+            // the string is unterminated, which `read_string` reports
+            '\0' => return None,
+            other => {
+                self.read_char();
+                self.errors.push(LexerError {
+                    code: ErrorCode::InvalidEscape,
+                    message: format!("unknown escape sequence `\\{}`", other),
+                    span: Span::new(start, self.current_position()),
+                });
+                return None;
+            }
+        };
+        self.read_char();
+        Some(escaped)
+    }
+
+    // `\u{1F600}`: one to six hex digits naming a unicode scalar value
+    fn read_unicode_escape(&mut self, start: Position) -> Option<char> {
+        self.read_char();
+        self.read_char();
+
+        let mut digits = String::new();
+        while self.ch.is_ascii_hexdigit() {
+            digits.push(self.ch);
+            self.read_char();
+        }
+        let closed = self.ch == '}';
+        if closed {
             self.read_char();
         }
 
-        let string_slice = &self.input[position..self.position];
-        string_slice.into_iter().collect()
+        let ch = u32::from_str_radix(digits.as_str(), 16)
+            .ok()
+            .filter(|_| closed && digits.len() <= 6)
+            .and_then(char::from_u32);
+        if ch.is_none() {
+            self.errors.push(LexerError {
+                code: ErrorCode::InvalidEscape,
+                message: format!("invalid unicode escape `\\u{{{}{}`", digits, if closed { "}" } else { "" }),
+                span: Span::new(start, self.current_position()),
+            });
+        }
+        ch
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::token::{Token, TokenKind};
+    use crate::token::{Position, Span, Token, TokenKind};
 
     use super::Lexer;
 
@@ -179,7 +604,7 @@ mod test {
         };
 
         say result = add(five, ten);
-        !-/*5;
+        !-/ *5;
         5 < 10 > 5;
 
         if (5 < 10) {
@@ -200,350 +625,437 @@ mod test {
             Token {
                 kind: TokenKind::Say,
                 literal: "say".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Ident,
                 literal: "five".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Assign,
                 literal: "=".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Int,
                 literal: "5".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Semicolon,
                 literal: ";".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Say,
                 literal: "say".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Ident,
                 literal: "ten".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Assign,
                 literal: "=".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Int,
                 literal: "10".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Semicolon,
                 literal: ";".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Say,
                 literal: "say".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Ident,
                 literal: "add".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Assign,
                 literal: "=".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Function,
                 literal: "function".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Lparen,
                 literal: "(".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Ident,
                 literal: "x".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Comma,
                 literal: ",".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Ident,
                 literal: "y".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Rparen,
                 literal: ")".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Lbrace,
                 literal: "{".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Ident,
                 literal: "x".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Plus,
                 literal: "+".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Ident,
                 literal: "y".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Semicolon,
                 literal: ";".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Rbrace,
                 literal: "}".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Semicolon,
                 literal: ";".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Say,
                 literal: "say".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Ident,
                 literal: "result".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Assign,
                 literal: "=".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Ident,
                 literal: "add".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Lparen,
                 literal: "(".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Ident,
                 literal: "five".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Comma,
                 literal: ",".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Ident,
                 literal: "ten".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Rparen,
                 literal: ")".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Semicolon,
                 literal: ";".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Bang,
                 literal: "!".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Minus,
                 literal: "-".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Slash,
                 literal: "/".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Asterisk,
                 literal: "*".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Int,
                 literal: "5".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Semicolon,
                 literal: ";".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Int,
                 literal: "5".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Lt,
                 literal: "<".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Int,
                 literal: "10".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Gt,
                 literal: ">".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Int,
                 literal: "5".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Semicolon,
                 literal: ";".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::If,
                 literal: "if".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Lparen,
                 literal: "(".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Int,
                 literal: "5".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Lt,
                 literal: "<".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Int,
                 literal: "10".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Rparen,
                 literal: ")".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Lbrace,
                 literal: "{".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Return,
                 literal: "return".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::True,
                 literal: "true".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Semicolon,
                 literal: ";".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Rbrace,
                 literal: "}".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Else,
                 literal: "else".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Lbrace,
                 literal: "{".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Return,
                 literal: "return".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::False,
                 literal: "false".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Semicolon,
                 literal: ";".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Rbrace,
                 literal: "}".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Int,
                 literal: "10".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Eq,
                 literal: "==".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Int,
                 literal: "10".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Semicolon,
                 literal: ";".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Int,
                 literal: "10".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::NotEq,
                 literal: "!=".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Int,
                 literal: "9".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Semicolon,
                 literal: ";".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::String,
                 literal: "jacksparrow".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::String,
                 literal: "jack sparrow".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Lbracket,
                 literal: "[".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Int,
                 literal: "2003".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Comma,
                 literal: ",".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Int,
                 literal: "2004".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Rbracket,
                 literal: "]".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Semicolon,
                 literal: ";".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Lbrace,
                 literal: "{".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::String,
                 literal: "creator".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Colon,
                 literal: ":".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::String,
                 literal: "arash".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Rbrace,
                 literal: "}".to_string(),
+                ..Default::default()
             },
             Token {
                 kind: TokenKind::Eof,
                 literal: "".to_string(),
+                ..Default::default()
             },
         ];
 
@@ -563,4 +1075,300 @@ mod test {
             );
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_token_spans() {
+        let input = "say añb = 10;\n  \"hi\" != x";
+
+        let expected = vec![
+            (TokenKind::Say, (1, 1, 0), (1, 4, 3)),
+            (TokenKind::Ident, (1, 5, 4), (1, 8, 8)),
+            (TokenKind::Assign, (1, 9, 9), (1, 10, 10)),
+            (TokenKind::Int, (1, 11, 11), (1, 13, 13)),
+            (TokenKind::Semicolon, (1, 13, 13), (1, 14, 14)),
+            (TokenKind::String, (2, 3, 17), (2, 7, 21)),
+            (TokenKind::NotEq, (2, 8, 22), (2, 10, 24)),
+            (TokenKind::Ident, (2, 11, 25), (2, 12, 26)),
+            (TokenKind::Eof, (2, 12, 26), (2, 12, 26)),
+        ];
+
+        let position = |(line, column, offset)| Position {
+            line,
+            column,
+            offset,
+        };
+
+        let mut lexer = Lexer::new(input);
+
+        for (idx, (kind, start, end)) in expected.into_iter().enumerate() {
+            let token = lexer.next_token();
+            assert_eq!(kind, token.kind, "tests[{idx}] - token type wrong");
+            assert_eq!(
+                Span::new(position(start), position(end)),
+                token.span,
+                "tests[{idx}] - span wrong for {}",
+                token.literal
+            );
+        }
+    }
+
+    #[test]
+    fn test_compound_assignment_operators() {
+        let mut lexer = Lexer::new("x += 1 -= *=/= + =");
+        let expected = vec![
+            (TokenKind::Ident, "x"),
+            (TokenKind::PlusAssign, "+="),
+            (TokenKind::Int, "1"),
+            (TokenKind::MinusAssign, "-="),
+            (TokenKind::AsteriskAssign, "*="),
+            (TokenKind::SlashAssign, "/="),
+            (TokenKind::Plus, "+"),
+            (TokenKind::Assign, "="),
+            (TokenKind::Eof, ""),
+        ];
+
+        for (kind, literal) in expected {
+            let token = lexer.next_token();
+            assert_eq!((token.kind, token.literal.as_str()), (kind, literal));
+        }
+    }
+
+    #[test]
+    fn test_comparison_logical_and_bitwise_operators() {
+        let mut lexer = Lexer::new("<= >= < > % ** * && || & | ^ ~ << >> <<=");
+        let expected = vec![
+            (TokenKind::LtEq, "<="),
+            (TokenKind::GtEq, ">="),
+            (TokenKind::Lt, "<"),
+            (TokenKind::Gt, ">"),
+            (TokenKind::Percent, "%"),
+            (TokenKind::Power, "**"),
+            (TokenKind::Asterisk, "*"),
+            (TokenKind::And, "&&"),
+            (TokenKind::Or, "||"),
+            (TokenKind::BitAnd, "&"),
+            (TokenKind::BitOr, "|"),
+            (TokenKind::BitXor, "^"),
+            (TokenKind::Tilde, "~"),
+            (TokenKind::ShiftLeft, "<<"),
+            (TokenKind::ShiftRight, ">>"),
+            (TokenKind::ShiftLeft, "<<"),
+            (TokenKind::Assign, "="),
+            (TokenKind::Eof, ""),
+        ];
+
+        for (kind, literal) in expected {
+            let token = lexer.next_token();
+            assert_eq!((token.kind, token.literal.as_str()), (kind, literal));
+        }
+    }
+
+    #[test]
+    fn test_match_and_parameter_tokens() {
+        let mut lexer = Lexer::new("match x { _ => 1 } == >=> ...rest .. .5");
+        let expected = vec![
+            (TokenKind::Match, "match"),
+            (TokenKind::Ident, "x"),
+            (TokenKind::Lbrace, "{"),
+            (TokenKind::Ident, "_"),
+            (TokenKind::FatArrow, "=>"),
+            (TokenKind::Int, "1"),
+            (TokenKind::Rbrace, "}"),
+            (TokenKind::Eq, "=="),
+            (TokenKind::GtEq, ">="),
+            (TokenKind::Gt, ">"),
+            (TokenKind::Ellipsis, "..."),
+            (TokenKind::Ident, "rest"),
+            (TokenKind::Illegal, "."),
+            (TokenKind::Illegal, "."),
+            (TokenKind::Float, ".5"),
+            (TokenKind::Eof, ""),
+        ];
+
+        for (kind, literal) in expected {
+            let token = lexer.next_token();
+            assert_eq!((token.kind, token.literal.as_str()), (kind, literal));
+        }
+    }
+
+    #[test]
+    fn test_number_literals() {
+        let mut lexer = Lexer::new("12 1.5 .5 1e-3 2.5E+3 7e 3.x 4..5");
+        let expected = vec![
+            (TokenKind::Int, "12"),
+            (TokenKind::Float, "1.5"),
+            (TokenKind::Float, ".5"),
+            (TokenKind::Float, "1e-3"),
+            (TokenKind::Float, "2.5E+3"),
+            (TokenKind::Int, "7"),
+            (TokenKind::Ident, "e"),
+            (TokenKind::Int, "3"),
+            (TokenKind::Illegal, "."),
+            (TokenKind::Ident, "x"),
+            (TokenKind::Int, "4"),
+            (TokenKind::Illegal, "."),
+            (TokenKind::Float, ".5"),
+            (TokenKind::Eof, ""),
+        ];
+
+        for (kind, literal) in expected {
+            let token = lexer.next_token();
+            assert_eq!((token.kind, token.literal.as_str()), (kind, literal));
+        }
+    }
+
+    #[test]
+    fn test_comments() {
+        let input = "// a line comment
+say a = 1; // trailing
+/* a block
+   comment */ a /* inline */ / 2;
+/* outer /* inner */ still outer */ a
+/// a doc comment
+//// not a doc comment";
+        let mut lexer = Lexer::new(input);
+        let expected = vec![
+            (TokenKind::Say, "say"),
+            (TokenKind::Ident, "a"),
+            (TokenKind::Assign, "="),
+            (TokenKind::Int, "1"),
+            (TokenKind::Semicolon, ";"),
+            (TokenKind::Ident, "a"),
+            (TokenKind::Slash, "/"),
+            (TokenKind::Int, "2"),
+            (TokenKind::Semicolon, ";"),
+            (TokenKind::Ident, "a"),
+            (TokenKind::Eof, ""),
+        ];
+
+        for (kind, literal) in expected {
+            let token = lexer.next_token();
+            assert_eq!((token.kind, token.literal.as_str()), (kind, literal));
+        }
+        assert!(lexer.take_errors().is_empty());
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        let mut lexer = Lexer::new("/// Adds one.\n///\n//// plain\nsay inc = 1;").with_doc_comments();
+
+        let token = lexer.next_token();
+        assert_eq!((token.kind, token.literal.as_str()), (TokenKind::DocComment, "Adds one."));
+        assert_eq!((token.span.start.column, token.span.end.column), (1, 14));
+
+        let token = lexer.next_token();
+        assert_eq!((token.kind, token.literal.as_str()), (TokenKind::DocComment, ""));
+        assert_eq!(lexer.next_token().kind, TokenKind::Say);
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let mut lexer = Lexer::new("1 /* open /* nested */");
+
+        assert_eq!(lexer.next_token().kind, TokenKind::Int);
+        assert_eq!(lexer.next_token().kind, TokenKind::Eof);
+
+        let errors = lexer.take_errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "unterminated block comment");
+        assert_eq!((errors[0].span.start.column, errors[0].span.end.column), (3, 5));
+    }
+
+    #[test]
+    fn test_string_literals() {
+        let input = r#""a\nb\t\"c\"\\" "\u{1F600}\u{e9}\0" r"C:\dir\n" ""
+"""
+line one
+  "quoted" line two""" r"""raw \n""" """""" rest"#;
+        let mut lexer = Lexer::new(input);
+        let expected = vec![
+            (TokenKind::String, "a\nb\t\"c\"\\"),
+            (TokenKind::String, "\u{1F600}\u{e9}\0"),
+            (TokenKind::String, "C:\\dir\\n"),
+            (TokenKind::String, ""),
+            (TokenKind::String, "line one\n  \"quoted\" line two"),
+            (TokenKind::String, "raw \\n"),
+            (TokenKind::String, ""),
+            (TokenKind::Ident, "rest"),
+            (TokenKind::Eof, ""),
+        ];
+
+        for (kind, literal) in expected {
+            let token = lexer.next_token();
+            assert_eq!((token.kind, token.literal.as_str()), (kind, literal));
+        }
+        assert!(lexer.take_errors().is_empty());
+    }
+
+    #[test]
+    fn test_interpolated_string_tokens() {
+        let input = r#""Hi {name}, {d[{"k": 1}["k"]]} \{x\}" "{"in {y}"}" r"{raw}""#;
+        let mut lexer = Lexer::new(input);
+        let expected = vec![
+            (TokenKind::InterpolationStart, "Hi "),
+            (TokenKind::Ident, "name"),
+            (TokenKind::InterpolationMiddle, ", "),
+            (TokenKind::Ident, "d"),
+            (TokenKind::Lbracket, "["),
+            (TokenKind::Lbrace, "{"),
+            (TokenKind::String, "k"),
+            (TokenKind::Colon, ":"),
+            (TokenKind::Int, "1"),
+            (TokenKind::Rbrace, "}"),
+            (TokenKind::Lbracket, "["),
+            (TokenKind::String, "k"),
+            (TokenKind::Rbracket, "]"),
+            (TokenKind::Rbracket, "]"),
+            (TokenKind::InterpolationEnd, " {x}"),
+            (TokenKind::InterpolationStart, ""),
+            (TokenKind::InterpolationStart, "in "),
+            (TokenKind::Ident, "y"),
+            (TokenKind::InterpolationEnd, ""),
+            (TokenKind::InterpolationEnd, ""),
+            (TokenKind::String, "{raw}"),
+            (TokenKind::Eof, ""),
+        ];
+
+        for (kind, literal) in expected {
+            let token = lexer.next_token();
+            assert_eq!((token.kind, token.literal.as_str()), (kind, literal));
+        }
+        assert!(lexer.take_errors().is_empty());
+
+        let mut lexer = Lexer::new(r#""a {b"#);
+        while lexer.next_token().kind != TokenKind::Eof {}
+        let errors = lexer.take_errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "unterminated string");
+    }
+
+    #[test]
+    fn test_string_errors() {
+        let tests = vec![
+            (r#""open"#, "unterminated string", (1, 2)),
+            ("say s = \"\"\"never closed\"\";", "unterminated string", (9, 12)),
+            (r#""bad \q escape""#, "unknown escape sequence `\\q`", (6, 8)),
+            (r#""\u{110000}""#, "invalid unicode escape `\\u{110000}`", (2, 12)),
+            (r#""\u{41""#, "invalid unicode escape `\\u{41`", (2, 7)),
+        ];
+
+        for (input, message, (start, end)) in tests {
+            let mut lexer = Lexer::new(input);
+            while lexer.next_token().kind != TokenKind::Eof {}
+
+            let errors = lexer.take_errors();
+            assert_eq!(errors.len(), 1, "wrong number of errors for {:?}", input);
+            assert_eq!(errors[0].message, message, "wrong error for {:?}", input);
+            assert_eq!((errors[0].span.start.column, errors[0].span.end.column), (start, end), "wrong span for {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_illegal_character_is_consumed() {
+        let mut lexer = Lexer::new("#1");
+
+        assert_eq!(lexer.next_token().kind, TokenKind::Illegal);
+        assert_eq!(lexer.next_token().kind, TokenKind::Int);
+        assert_eq!(lexer.next_token().kind, TokenKind::Eof);
+    }
+}
@@ -0,0 +1,24 @@
+//      TwoT as a library. Host programs start from `Interpreter`; the modules are public
+//      for tools that need the lexer, parser or compiler on their own
+pub mod ast;
+pub mod bigint;
+pub mod builtins;
+pub mod code;
+pub mod compiler;
+pub mod convert;
+pub mod diagnostics;
+pub mod engine;
+pub mod evaluator;
+pub mod interpreter;
+pub mod lexer;
+pub mod object;
+pub mod parser;
+pub mod repl;
+pub mod script;
+pub mod token;
+pub mod vm;
+
+pub use convert::{ConversionError, FromObject, IntoObject};
+pub use engine::Backend;
+pub use interpreter::{Interpreter, InterpreterError};
+pub use object::{BuiltinFunction, CallContext, Object};
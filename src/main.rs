@@ -1,18 +1,110 @@
-use std::io;
-use crate::repl::start;
-
-pub mod ast;
-pub mod builtins;
-pub mod evaluator;
-pub mod lexer;
-pub mod object;
-pub mod parser;
-pub mod repl;
-pub mod token;
+use std::{
+    env, fs,
+    io::{self, IsTerminal, Read},
+    process, thread,
+};
+
+use inter::{engine::Backend, evaluator::IntegerOverflow, repl::start, script};
+
+const USAGE: &str = "usage: inter [--backend tree|vm] [--no-bigint] [FILE | -e CODE | -]
+
+  (no arguments)   start the interactive REPL, or run piped stdin
+  FILE             run a .twot source file
+  -e CODE          run CODE given on the command line
+  -                read the program from stdin
+  --backend NAME   run on the tree-walking interpreter (`tree`, default)
+                   or on the bytecode virtual machine (`vm`)
+  --no-bigint      report integers that do not fit in 64 bits as an
+                   error instead of continuing with arbitrary precision
+  -h, --help       print this message";
+
+// enough native stack for the tree-walker to reach its call depth limit. It may use half,
+// the rest is headroom for the work between two of its checks
+const STACK_BYTES: usize = 256 * 1024 * 1024;
+const STACK_LIMIT: usize = STACK_BYTES / 2;
 
 fn main() {
-    println!("Hello, You are using the TwoT language!!");
-    println!("version 0.1.0");
-    println!("This language Developed by arash :) \n");
-    start(io::stdin(), io::stdout());
+    let runner = thread::Builder::new()
+        .stack_size(STACK_BYTES)
+        .spawn(run)
+        .expect("cannot start the interpreter thread");
+    if runner.join().is_err() {
+        process::exit(101);
+    }
+}
+
+fn run() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    let mut backend = Backend::default();
+    if let Some(idx) = args.iter().position(|arg| arg == "--backend" || arg.starts_with("--backend=")) {
+        let value = match args[idx].strip_prefix("--backend=") {
+            Some(value) => Some(value.to_string()),
+            None if idx + 1 < args.len() => Some(args.remove(idx + 1)),
+            None => None,
+        };
+        args.remove(idx);
+
+        backend = match value.as_deref().map(str::parse) {
+            Some(Ok(backend)) => backend,
+            Some(Err(e)) => {
+                eprintln!("inter: {e}");
+                process::exit(2);
+            }
+            None => {
+                eprintln!("{USAGE}");
+                process::exit(2);
+            }
+        };
+    }
+
+    let mut integer_overflow = IntegerOverflow::default();
+    if let Some(idx) = args.iter().position(|arg| arg == "--no-bigint") {
+        args.remove(idx);
+        integer_overflow = IntegerOverflow::Error;
+    }
+
+    let source = match args.iter().map(String::as_str).collect::<Vec<&str>>().as_slice() {
+        [] if io::stdin().is_terminal() => {
+            println!("Hello, You are using the TwoT language!!");
+            println!("version 0.1.0");
+            println!("This language Developed by arash :) \n");
+            start(io::stdin(), io::stdout(), backend, integer_overflow, STACK_LIMIT);
+            return;
+        }
+        [] | ["-"] => read_stdin(),
+        ["-h"] | ["--help"] => {
+            println!("{USAGE}");
+            return;
+        }
+        ["-e", code] => code.to_string(),
+        [path] if !path.starts_with('-') => match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("inter: cannot read {path}: {e}");
+                process::exit(2);
+            }
+        },
+        _ => {
+            eprintln!("{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    let origin = match args.first().map(String::as_str) {
+        None | Some("-") => "<stdin>",
+        Some("-e") => "<-e>",
+        Some(path) => path,
+    };
+    let status = script::run(origin, source.as_str(), backend, integer_overflow, STACK_LIMIT, &mut io::stderr());
+    process::exit(status.exit_code());
+}
+
+fn read_stdin() -> String {
+    let mut source = String::new();
+    if let Err(e) = io::stdin().read_to_string(&mut source) {
+        eprintln!("inter: cannot read stdin: {e}");
+        process::exit(2);
+    }
+    source
 }
@@ -1,37 +1,95 @@
 use std::{
-    collections::{hash_map::DefaultHasher, HashMap},
-    fmt::Display,
-    hash::{Hash, Hasher},
+    cell::RefCell,
+    cmp::Ordering,
+    collections::HashMap,
+    fmt::{Debug, Display},
+    rc::Rc,
 };
 
-use crate::{ast::{BlockStatement, Identifier, Node}, builtins::Builtin};
+use crate::{ast::{BlockStatement, Node, Parameter}, bigint::BigInt, builtins::Builtin, code::Instructions, compiler::SymbolScope, token::Span};
 
-pub type BuiltinFunction = fn(Vec<Object>) -> Object;
+//      what a builtin can ask of the interpreter running it
+pub trait CallContext {
+    // calls a function, closure or builtin value with positional arguments; failures come
+    // back as `Object::Error`
+    fn apply(&mut self, function: &Object, arguments: Vec<Object>) -> Object;
+}
 
+//      a native function. It is a closure, so it can hold host state, and it gets the running
+//      interpreter to call back into TwoT functions
+#[derive(Clone)]
+pub struct BuiltinFunction(Rc<NativeFunction>);
 
-#[derive(Debug, Clone)]
+type NativeFunction = dyn Fn(&mut dyn CallContext, Vec<Object>) -> Object;
+
+impl BuiltinFunction {
+    pub fn new(function: impl Fn(&mut dyn CallContext, Vec<Object>) -> Object + 'static) -> BuiltinFunction {
+        BuiltinFunction(Rc::new(function))
+    }
+
+    pub fn call(&self, context: &mut dyn CallContext, arguments: Vec<Object>) -> Object {
+        (self.0)(context, arguments)
+    }
+}
+
+impl Debug for BuiltinFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "BuiltinFunction")
+    }
+}
+
+
+#[derive(Clone)]
 //      Add types in our programming language
 pub enum Object {
     Integer(i64),
+    BigInt(BigInt),     // only for integers outside the `i64` range
+    Float(f64),
     Boolean(bool),
     ReturnValue(Box<Object>),
+    Break,
+    Continue,
     Error(String),
     Function(Function),
+    CompiledFunction(Rc<CompiledFunction>),
+    Closure(Closure),
     StringObject(String),
     Builtin(BuiltinFunction),
-    Array(Vec<Object>),
-    DictObject(DictStruct),
+    Array(Rc<RefCell<Vec<Object>>>),
+    DictObject(Rc<RefCell<DictStruct>>),
     Null,
 }
 
 impl Object {
+    // arrays and dictionaries are shared: every binding to one sees its mutations
+    pub fn new_array(elements: Vec<Object>) -> Object {
+        Object::Array(Rc::new(RefCell::new(elements)))
+    }
+
+    pub fn new_dict(pairs: HashMap<DictKey, DictPair>) -> Object {
+        Object::DictObject(Rc::new(RefCell::new(DictStruct { pairs })))
+    }
+
+    // integers that fit in an `i64` are always kept as `Object::Integer`
+    pub fn from_bigint(value: BigInt) -> Object {
+        match value.to_i64() {
+            Some(int) => Object::Integer(int),
+            None => Object::BigInt(value),
+        }
+    }
+
     pub fn object_type(&self) -> String{
         match self {
-            Self::Integer(_) => String::from("INTEGER"),
+            Self::Integer(_) | Self::BigInt(_) => String::from("INTEGER"),
+            Self::Float(_) => String::from("FLOAT"),
             Self::Boolean(_) => String::from("BOOLEAN"),
             Self::ReturnValue(_) => String::from("RETURN_VALUE"),
+            Self::Break => String::from("BREAK"),
+            Self::Continue => String::from("CONTINUE"),
             Self::Error(_) => String::from("ERROR"),
             Self::Function(_) => String::from("FUNCTION"),
+            Self::CompiledFunction(_) => String::from("COMPILED_FUNCTION"),
+            Self::Closure(_) => String::from("FUNCTION"),
             Self::StringObject(_) => String::from("STRING"),
             Self::Builtin(_) => String::from("BUILTIN"),
             Self::Array(_) => String::from("ARRAY"),
@@ -43,87 +101,276 @@ impl Object {
 
 impl Display for Object {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.display(&mut vec![]))
+    }
+}
+
+//      arrays and dictionaries are shared, so one can end up inside itself. The walks below
+//      keep the containers they are inside of and stop when they reach one of them again
+type Container = *const ();
+
+fn container(object: &Object) -> Option<Container> {
+    match object {
+        Object::Array(elements) => Some(Rc::as_ptr(elements) as Container),
+        Object::DictObject(dictionary) => Some(Rc::as_ptr(dictionary) as Container),
+        _ => None,
+    }
+}
+
+impl Object {
+    // a container inside itself is written as `[...]` or `{...}`
+    fn display(&self, inside: &mut Vec<Container>) -> String {
         match self {
-            Self::Integer(int) => write!(f, "{}", int),
-            Self::Boolean(bool) => write!(f, "{}", bool),
-            Self::ReturnValue(return_value) => write!(f, "{}", *return_value),
-            Self::Error(error) => write!(f, "ERROR: {}", error),
-            Self::Function(function) => {
-                let mut out = String::from("");
-                let mut parameters = vec![];
-
-                for parameter in &function.parameters {
-                    parameters.push(parameter.print_string());
+            Self::Integer(int) => int.to_string(),
+            Self::BigInt(big) => big.to_string(),
+            // `{:?}` is the shortest text that parses back to the same f64, and keeps the `.0`
+            Self::Float(float) => format!("{:?}", float),
+            Self::Boolean(bool) => bool.to_string(),
+            Self::ReturnValue(return_value) => return_value.display(inside),
+            Self::Break => String::from("break"),
+            Self::Continue => String::from("continue"),
+            Self::Error(error) => format!("ERROR: {}", error),
+            Self::Function(function) => function_string(&function.parameters, &function.body),
+            Self::CompiledFunction(function) => function.literal.clone(),
+            Self::Closure(closure) => closure.function.literal.clone(),
+            Self::StringObject(string) => string.clone(),
+            Self::Builtin(_) => String::from("builtin function"),
+            Self::Array(elements) => {
+                let this = Rc::as_ptr(elements) as Container;
+                if inside.contains(&this) {
+                    return String::from("[...]");
                 }
 
-                out.push_str("function");
-                out.push_str("(");
-                out.push_str(parameters.join(", ").as_str());
-                out.push_str(") { \n");
-                out.push_str(function.body.print_string().as_str());
-                out.push_str("}\n");
+                inside.push(this);
+                let els: Vec<String> = elements.borrow().iter().map(|element| element.display(inside)).collect();
+                inside.pop();
 
-                write!(f, "{}", out)
+                format!("[{}]", els.join(", "))
             }
-            Self::StringObject(string) => write!(f, "{}", string),
-            Self::Builtin(_) => write!(f, "builtin function"), 
-            Self::Array(elements) => {
-                let mut out = String::from("");
-                let mut els = vec![];
-
-                for element in elements {
-                    els.push(format!("{}", element));
+            Self::DictObject(dictionary) => {
+                let this = Rc::as_ptr(dictionary) as Container;
+                if inside.contains(&this) {
+                    return String::from("{...}");
                 }
 
-                out.push_str("[");
-                out.push_str(els.join(", ").as_str());
-                out.push_str("]");
+                inside.push(this);
+                let pairs: Vec<String> = dictionary
+                    .borrow()
+                    .pairs
+                    .values()
+                    .map(|pair| format!("{}: {}", pair.key.display(inside), pair.value.display(inside)))
+                    .collect();
+                inside.pop();
 
-                write!(f, "{}", out)
+                format!("{{{}}}", pairs.join(", "))
             }
-            Self::DictObject(dictionary) => {
-                let mut out = String::from("");
-                let mut pairs = vec![];
+            Self::Null => String::new(),
+        }
+    }
+
+    // two containers met again while comparing them are taken as equal: nothing found so far
+    // tells them apart, and going on would only repeat the same comparisons
+    fn equals(&self, other: &Object, inside: &mut Vec<(Container, Container)>) -> bool {
+        if let Some(ordering) = compare_numbers(self, other) {
+            return ordering == Ordering::Equal;
+        }
+        let containers = match (container(self), container(other)) {
+            (Some(left), Some(right)) if left == right || inside.contains(&(left, right)) => return true,
+            (Some(left), Some(right)) => {
+                inside.push((left, right));
+                true
+            }
+            _ => false,
+        };
+
+        let equal = match (self, other) {
+            (Self::Boolean(left), Self::Boolean(right)) => left == right,
+            (Self::StringObject(left), Self::StringObject(right)) => left == right,
+            (Self::Null, Self::Null) | (Self::Break, Self::Break) | (Self::Continue, Self::Continue) => true,
+            (Self::Error(left), Self::Error(right)) => left == right,
+            (Self::ReturnValue(left), Self::ReturnValue(right)) => left.equals(right, inside),
+            (Self::Array(left), Self::Array(right)) => {
+                let (left, right) = (left.borrow(), right.borrow());
+                left.len() == right.len() && left.iter().zip(right.iter()).all(|(left, right)| left.equals(right, inside))
+            }
+            (Self::DictObject(left), Self::DictObject(right)) => {
+                let (left, right) = (&left.borrow().pairs, &right.borrow().pairs);
+                left.len() == right.len()
+                    && left.iter().all(|(key, pair)| {
+                        right.get(key).is_some_and(|other| other.value.equals(&pair.value, inside))
+                    })
+            }
+            (Self::Function(left), Self::Function(right)) => {
+                Rc::ptr_eq(&left.environment, &right.environment)
+                    && function_string(&left.parameters, &left.body)
+                        == function_string(&right.parameters, &right.body)
+            }
+            (Self::CompiledFunction(left), Self::CompiledFunction(right)) => Rc::ptr_eq(left, right),
+            (Self::Closure(left), Self::Closure(right)) => {
+                Rc::ptr_eq(&left.function, &right.function)
+                    && left.free.iter().zip(&right.free).all(|(left, right)| Rc::ptr_eq(left, right))
+            }
+            (Self::Builtin(left), Self::Builtin(right)) => Rc::ptr_eq(&left.0, &right.0),
+            _ => false,
+        };
+
+        if containers {
+            inside.pop();
+        }
+        equal
+    }
 
-                for(_ , pair) in &dictionary.pairs {
-                    pairs.push(format!("{}: {}", pair.key, pair.value))
+    // like `equals`, arrays met again while comparing them are taken as equal
+    fn compare(&self, other: &Object, inside: &mut Vec<(Container, Container)>) -> Option<Ordering> {
+        if let Some(ordering) = compare_numbers(self, other) {
+            return Some(ordering);
+        }
+        match (self, other) {
+            (Self::StringObject(left), Self::StringObject(right)) => Some(left.cmp(right)),
+            (Self::Array(left_elements), Self::Array(right_elements)) => {
+                let pair = (Rc::as_ptr(left_elements) as Container, Rc::as_ptr(right_elements) as Container);
+                if pair.0 == pair.1 || inside.contains(&pair) {
+                    return Some(Ordering::Equal);
                 }
 
-                out.push_str("{");
-                out.push_str(pairs.join(", ").as_str());
-                out.push_str("}");
+                inside.push(pair);
+                let (left, right) = (left_elements.borrow(), right_elements.borrow());
+                let mut ordering = Some(left.len().cmp(&right.len()));
+                for (left, right) in left.iter().zip(right.iter()) {
+                    match left.compare(right, inside) {
+                        Some(Ordering::Equal) => {}
+                        different => {
+                            ordering = different;
+                            break;
+                        }
+                    }
+                }
+                inside.pop();
+                ordering
+            }
+            _ => None,
+        }
+    }
+}
+
+//      `==` is structural: arrays and dictionaries are equal when their contents are, numbers compare
+//      by value across integer and float, and values of different types are never equal.
+//      functions are only equal to themselves
+impl Debug for Object {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Guarded { object: self, inside: &RefCell::new(vec![]) }.fmt(f)
+    }
+}
 
-                write!(f, "{}", out)
+// an object being written with `{:?}`, with the containers it is inside of
+struct Guarded<'a> {
+    object: &'a Object,
+    inside: &'a RefCell<Vec<Container>>,
+}
+
+impl Debug for Guarded<'_> {
+    // like `display`, a container inside itself is written as `[...]` or `{...}`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let guarded = |object| Guarded { object, inside: self.inside };
+        match self.object {
+            Object::Integer(int) => f.debug_tuple("Integer").field(int).finish(),
+            Object::BigInt(big) => f.debug_tuple("BigInt").field(big).finish(),
+            Object::Float(float) => f.debug_tuple("Float").field(float).finish(),
+            Object::Boolean(bool) => f.debug_tuple("Boolean").field(bool).finish(),
+            Object::ReturnValue(value) => f.debug_tuple("ReturnValue").field(&guarded(value)).finish(),
+            Object::Break => f.write_str("Break"),
+            Object::Continue => f.write_str("Continue"),
+            Object::Error(error) => f.debug_tuple("Error").field(error).finish(),
+            Object::Function(function) => f.debug_tuple("Function").field(function).finish(),
+            Object::CompiledFunction(function) => f.debug_tuple("CompiledFunction").field(function).finish(),
+            Object::Closure(closure) => f.debug_tuple("Closure").field(closure).finish(),
+            Object::StringObject(string) => f.debug_tuple("StringObject").field(string).finish(),
+            Object::Builtin(builtin) => f.debug_tuple("Builtin").field(builtin).finish(),
+            Object::Array(elements) => {
+                let this = Rc::as_ptr(elements) as Container;
+                if self.inside.borrow().contains(&this) {
+                    return f.write_str("Array([...])");
+                }
+
+                self.inside.borrow_mut().push(this);
+                f.write_str("Array(")?;
+                let written = f.debug_list().entries(elements.borrow().iter().map(guarded)).finish();
+                self.inside.borrow_mut().pop();
+                written?;
+                f.write_str(")")
             }
-            Self::Null => write!(f, ""),
-        
+            Object::DictObject(dictionary) => {
+                let this = Rc::as_ptr(dictionary) as Container;
+                if self.inside.borrow().contains(&this) {
+                    return f.write_str("DictObject({...})");
+                }
+
+                self.inside.borrow_mut().push(this);
+                f.write_str("DictObject(")?;
+                let dictionary = dictionary.borrow();
+                let pairs = dictionary.pairs.values().map(|pair| (guarded(&pair.key), guarded(&pair.value)));
+                let written = f.debug_map().entries(pairs).finish();
+                self.inside.borrow_mut().pop();
+                written?;
+                f.write_str(")")
+            }
+            Object::Null => f.write_str("Null"),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        self.equals(other, &mut vec![])
+    }
+}
+
+//      numbers are ordered by value, strings by code point and arrays element by element.
+//      `None` for everything else, including values of different types
+impl PartialOrd for Object {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.compare(other, &mut vec![])
+    }
+}
+
+fn compare_numbers(left: &Object, right: &Object) -> Option<Ordering> {
+    match (left, right) {
+        (Object::Integer(left), Object::Integer(right)) => Some(left.cmp(right)),
+        (Object::BigInt(left), Object::BigInt(right)) => Some(left.cmp(right)),
+        (Object::Integer(int), Object::BigInt(big)) => Some(BigInt::from_i64(*int).cmp(big)),
+        (Object::BigInt(big), Object::Integer(int)) => Some(big.cmp(&BigInt::from_i64(*int))),
+        (Object::Float(left), Object::Float(right)) => left.partial_cmp(right),
+        (Object::Integer(int), Object::Float(float)) => (*int as f64).partial_cmp(float),
+        (Object::Float(float), Object::Integer(int)) => float.partial_cmp(&(*int as f64)),
+        (Object::BigInt(big), Object::Float(float)) => big.to_f64().partial_cmp(float),
+        (Object::Float(float), Object::BigInt(big)) => float.partial_cmp(&big.to_f64()),
+        _ => None,
+    }
+}
+
+//      scopes are shared: every closure created in a scope holds the same
+//      `Rc<RefCell<..>>`, so it sees bindings added or changed after its creation
+#[derive(Debug)]
 pub struct Environment {
     pub store: HashMap<String, Object>,
-    pub outer: Option<Box<Environment>>,
+    pub outer: Option<Rc<RefCell<Environment>>>,
 }
 
 impl Environment {
-    pub fn new_environment() -> Environment {
+    pub fn new_environment() -> Rc<RefCell<Environment>> {
         let mut environment_map = HashMap::new();
         Self::init_builtin (&mut environment_map);
-        Environment {
+        Rc::new(RefCell::new(Environment {
             store: environment_map,
             outer: None
-        }
+        }))
     }
 
-    pub fn new_enclosed_evironment(outer: Box<Environment>) -> Environment {
-        let mut environment_map = HashMap::new();
-        Self::init_builtin (&mut environment_map);
-        Environment {
-            store: environment_map,
+    pub fn new_enclosed_evironment(outer: Rc<RefCell<Environment>>) -> Rc<RefCell<Environment>> {
+        Rc::new(RefCell::new(Environment {
+            store: HashMap::new(),
             outer: Some(outer),
-        }
+        }))
     }
 
     fn init_builtin(hashmap: &mut HashMap<String, Object>) {
@@ -139,40 +386,134 @@ impl Environment {
         match self.store.get(name.as_str()) {
             Some(object) => Some(object.clone()),
             None => match &self.outer {
-                Some(environment) => environment.get(name),
-                None => match &self.outer {
-                    Some(environment) => environment.get(name),
-                    None => None
-                },
+                Some(environment) => environment.borrow().get(name),
+                None => None,
             },
         }
     }
     pub fn set(&mut self, name: String, value: Object) -> Option<Object> {
         self.store.insert(name.clone(), value);
-        return self.get(name);
+        self.get(name)
+    }
+
+    // rebinds `name` in the innermost environment that declares it; false if none does
+    pub fn assign(&mut self, name: String, value: Object) -> bool {
+        if let Some(slot) = self.store.get_mut(name.as_str()) {
+            *slot = value;
+            return true;
+        }
+        match &self.outer {
+            Some(environment) => environment.borrow_mut().assign(name, value),
+            None => false,
+        }
     }
 }
 
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Function {
-    pub parameters: Vec<Identifier>,
+    pub parameters: Vec<Parameter>,
     pub body: BlockStatement,
-    pub environment: Environment,
+    pub environment: Rc<RefCell<Environment>>,
 }
 
-#[derive(Debug, PartialEq, Clone, Eq)]
-pub struct DictKey{
-    pub object_type: String,
-    pub value: i64
+// the environment is left out: a recursive function is stored inside its own environment
+impl Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Function")
+            .field("parameters", &self.parameters)
+            .field("body", &self.body)
+            .finish_non_exhaustive()
+    }
 }
 
+// how a function value is shown, shared by both backends
+pub fn function_string(parameters: &[Parameter], body: &BlockStatement) -> String {
+    let mut out = String::from("");
+    let mut params = vec![];
 
-impl Hash for DictKey {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.value.hash(state);
-        self.object_type.hash(state);
+    for parameter in parameters {
+        params.push(parameter.print_string());
     }
+
+    out.push_str("function");
+    out.push('(');
+    out.push_str(params.join(", ").as_str());
+    out.push_str(") { \n");
+    out.push_str(body.print_string().as_str());
+    out.push_str("}\n");
+
+    out
+}
+
+//      what a call needs to know about a parameter, in both backends
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterSpec {
+    pub name: String,
+    pub has_default: bool,
+    pub rest: bool,
+}
+
+impl From<&Parameter> for ParameterSpec {
+    fn from(parameter: &Parameter) -> ParameterSpec {
+        ParameterSpec {
+            name: parameter.name.value.clone(),
+            has_default: parameter.default.is_some(),
+            rest: parameter.rest,
+        }
+    }
+}
+
+//      a variable slot of the bytecode VM; `None` until the variable is first assigned.
+//      closures capture the slot itself, so they observe later assignments
+pub type Cell = Rc<RefCell<Option<Object>>>;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct CompiledFunction {
+    pub instructions: Instructions,
+    pub spans: Vec<(usize, Span)>,   // instruction offset -> source span, sorted by offset
+    pub num_locals: usize,
+    pub parameters: Vec<ParameterSpec>,     // the first locals
+    pub local_names: Vec<String>,
+    pub free_names: Vec<String>,
+    pub captures: Vec<(SymbolScope, usize)>,   // where each free variable lives in the enclosing function
+    pub literal: String,
+}
+
+impl CompiledFunction {
+    pub fn span_at(&self, ip: usize) -> Option<Span> {
+        let idx = self.spans.partition_point(|(offset, _)| *offset <= ip);
+        if idx == 0 {
+            return None;
+        }
+        Some(self.spans[idx - 1].1)
+    }
+}
+
+#[derive(Clone)]
+pub struct Closure {
+    pub function: Rc<CompiledFunction>,
+    pub free: Vec<Cell>,
+}
+
+// free variables are left out, a closure can capture the cell that holds it
+impl Debug for Closure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Closure")
+            .field("function", &self.function)
+            .field("free", &self.function.free_names)
+            .finish()
+    }
+}
+
+//      a key is the value itself, not a hash of it, so two keys are the same only when their
+//      values are. An integer outside the `i64` range is always a `BigInt`, never an `Integer`
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub enum DictKey {
+    Integer(i64),
+    BigInt(BigInt),
+    Boolean(bool),
+    String(String),
 }
 
 
@@ -183,25 +524,10 @@ pub trait Dictado {
 impl Dictado for Object {
     fn dict_key(&self) -> Result<DictKey, String> {
         match &self {
-            Object::Boolean(bool) => {
-                let value = if *bool {1} else {0};
-                Ok(DictKey {
-                    object_type: self.object_type(),
-                    value
-                })
-            }
-            Object::Integer(int) => Ok(DictKey {
-                object_type: self.object_type(),
-                value: *int,
-            }),
-            Object::StringObject(string) => {
-                let mut hasher = DefaultHasher::new();
-                string.hash(&mut hasher);
-                Ok(DictKey {
-                    object_type: self.object_type(),
-                    value: hasher.finish() as i64,
-                })
-            }
+            Object::Boolean(bool) => Ok(DictKey::Boolean(*bool)),
+            Object::Integer(int) => Ok(DictKey::Integer(*int)),
+            Object::BigInt(big) => Ok(DictKey::BigInt(big.clone())),
+            Object::StringObject(string) => Ok(DictKey::String(string.clone())),
             other => Err(format!("unusable as Dictionary key: {}", other.object_type())),
         }
     }
@@ -220,7 +546,9 @@ pub struct DictStruct {
 
 #[cfg(test)]
 mod test{
-    use super::{Dictado, Object};
+    use std::collections::HashMap;
+
+    use super::{DictPair, Dictado, Object};
 
 
     #[test]
@@ -241,4 +569,46 @@ mod test{
             "Entered String with different content, have same Dictionary key"
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_bigint_dict_key() {
+        let big1 = Object::BigInt("123456789012345678901234567890".parse().unwrap());
+        let big2 = Object::BigInt("123456789012345678901234567890".parse().unwrap());
+        let other = Object::BigInt("-123456789012345678901234567890".parse().unwrap());
+
+        assert_eq!(big1.dict_key(), big2.dict_key());
+        assert_ne!(big1.dict_key(), other.dict_key());
+
+        // the key is the number itself, so no integer shares it
+        let power = Object::BigInt("1180591620717411303424".parse().unwrap());
+        assert_ne!(power.dict_key(), Object::Integer(-3452502586987142673).dict_key());
+    }
+
+    #[test]
+    fn test_cyclic_values() {
+        let cyclic = |first: i64| {
+            let array = Object::new_array(vec![Object::Integer(first)]);
+            if let Object::Array(elements) = &array {
+                elements.borrow_mut().push(array.clone());
+            }
+            array
+        };
+        let (a, b, c) = (cyclic(1), cyclic(1), cyclic(2));
+
+        assert_eq!(a.to_string(), "[1, [...]]");
+        assert_eq!(Object::new_array(vec![a.clone(), a.clone()]).to_string(), "[[1, [...]], [1, [...]]]");
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert!(a < c);
+        assert_eq!(a.partial_cmp(&b), Some(std::cmp::Ordering::Equal));
+
+        assert_eq!(format!("{:?}", a), "Array([Integer(1), Array([...])])");
+        let dictionary = Object::new_dict(HashMap::new());
+        if let Object::DictObject(pairs) = &dictionary {
+            let key = Object::StringObject(String::from("self"));
+            let pair = DictPair { key: key.clone(), value: dictionary.clone() };
+            pairs.borrow_mut().pairs.insert(key.dict_key().unwrap(), pair);
+        }
+        assert_eq!(format!("{:?}", dictionary), "DictObject({StringObject(\"self\"): DictObject({...})})");
+    }
+}
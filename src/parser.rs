@@ -1,14 +1,16 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt::Display};
 
 use crate::{
     ast::{
-        ArrayLiteral, BlockStatement, Boolean, CallExpression, ExpressionNode, ExpressionStatement,
-        FunctionLiteral, DictLiteral, Identifier, IfExpression, IndexExpression, InfixExpression,
-        IntegerLiteral, SayStatement, PrefixExpression, Program, ReturnStatement, StatementNode,
-        StringLiteral,
+        ArrayLiteral, AssignExpression, BigIntLiteral, BlockStatement, FloatLiteral, Boolean, CallExpression, ExpressionNode, ExpressionStatement,
+        FunctionDeclaration, FunctionLiteral, DictLiteral, Identifier, IfExpression, IndexExpression, InfixExpression, InterpolatedString,
+        IntegerLiteral, MatchArm, MatchExpression, Parameter, Pattern, SayStatement, PrefixExpression, Program, ReturnStatement, StatementNode,
+        StringLiteral, StringPart, WhileStatement, ForStatement, Node,
     },
-    lexer::Lexer,
-    token::{Token, TokenKind},
+    bigint::BigInt,
+    diagnostics::ErrorCode,
+    lexer::{Lexer, LexerError},
+    token::{Span, Token, TokenKind},
 };
 
 type PrefixParseFn = fn(parser: &mut Parser) -> Option<ExpressionNode>;
@@ -17,38 +19,89 @@ type InfixParseFn = fn(parser: &mut Parser, exp: ExpressionNode) -> Option<Expre
 #[derive(Debug, Copy, Clone)]
 enum PrecedenceLevel {
     Lowest = 0,
-    Equals = 1,      // ==
-    LessGreater = 2, // > or <
-    Sum = 3,         // +
-    Product = 4,
-    Prefix = 5,
-    Call = 6,
-    Index = 7,
+    Assign = 1,      // = or +=
+    LogicalOr = 2,   // ||
+    LogicalAnd = 3,  // &&
+    Equals = 4,      // ==
+    LessGreater = 5, // > or <=
+    BitOr = 6,       // |
+    BitXor = 7,      // ^
+    BitAnd = 8,      // &
+    Shift = 9,       // << or >>
+    Sum = 10,        // +
+    Product = 11,    // * or %
+    Prefix = 12,
+    Power = 13,      // **, binds tighter than a prefix on its left: -2 ** 2 is -(2 ** 2)
+    Call = 14,
+    Index = 15,
 }
 
 fn precedence_map(kind: &TokenKind) -> PrecedenceLevel {
-    return match kind {
+    match kind {
+        TokenKind::FatArrow
+        | TokenKind::Assign
+        | TokenKind::PlusAssign
+        | TokenKind::MinusAssign
+        | TokenKind::AsteriskAssign
+        | TokenKind::SlashAssign => PrecedenceLevel::Assign,
+        TokenKind::Or => PrecedenceLevel::LogicalOr,
+        TokenKind::And => PrecedenceLevel::LogicalAnd,
         TokenKind::Eq => PrecedenceLevel::Equals,
         TokenKind::NotEq => PrecedenceLevel::Equals,
         TokenKind::Lt => PrecedenceLevel::LessGreater,
         TokenKind::Gt => PrecedenceLevel::LessGreater,
+        TokenKind::LtEq => PrecedenceLevel::LessGreater,
+        TokenKind::GtEq => PrecedenceLevel::LessGreater,
+        TokenKind::BitOr => PrecedenceLevel::BitOr,
+        TokenKind::BitXor => PrecedenceLevel::BitXor,
+        TokenKind::BitAnd => PrecedenceLevel::BitAnd,
+        TokenKind::ShiftLeft => PrecedenceLevel::Shift,
+        TokenKind::ShiftRight => PrecedenceLevel::Shift,
         TokenKind::Plus => PrecedenceLevel::Sum,
         TokenKind::Minus => PrecedenceLevel::Sum,
         TokenKind::Slash => PrecedenceLevel::Product,
         TokenKind::Asterisk => PrecedenceLevel::Product,
+        TokenKind::Percent => PrecedenceLevel::Product,
+        TokenKind::Power => PrecedenceLevel::Power,
         TokenKind::Lparen => PrecedenceLevel::Call,
         TokenKind::Lbracket => PrecedenceLevel::Index,
         _ => PrecedenceLevel::Lowest,
-    };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParserError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub span: Span,
+}
+
+impl From<LexerError> for ParserError {
+    fn from(error: LexerError) -> ParserError {
+        ParserError {
+            code: error.code,
+            message: error.message,
+            span: error.span,
+        }
+    }
+}
+
+impl Display for ParserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.span, self.message)
+    }
 }
 
 pub struct Parser {
     lexer: Lexer,
     current_token: Token,
     peek_token: Token,
-    errors: Vec<String>,
+    errors: Vec<ParserError>,
     prefix_parse_fns: HashMap<TokenKind, PrefixParseFn>,
     infix_parse_fns: HashMap<TokenKind, InfixParseFn>,
+    loop_depth: usize,      // loops enclosing the current token, reset inside function literals
+    nesting: usize,         // brackets opened up to the current token and not closed yet
+    guard_nesting: Option<usize>,   // `nesting` at the start of the match guard being parsed
 }
 
 impl Parser {
@@ -60,21 +113,29 @@ impl Parser {
             errors: vec![],
             prefix_parse_fns: HashMap::new(),
             infix_parse_fns: HashMap::new(),
+            loop_depth: 0,
+            nesting: 0,
+            guard_nesting: None,
         };
 
         parser.register_prefix(TokenKind::Ident, Self::parse_identifier);
         parser.register_prefix(TokenKind::Int, Self::parse_integer_literal);
+        parser.register_prefix(TokenKind::Float, Self::parse_float_literal);
+        parser.register_prefix(TokenKind::InterpolationStart, Self::parse_interpolated_string);
         parser.register_prefix(TokenKind::Bang, Self::parse_prefix_expression);
         parser.register_prefix(TokenKind::Minus, Self::parse_prefix_expression);
+        parser.register_prefix(TokenKind::Tilde, Self::parse_prefix_expression);
         parser.register_prefix(TokenKind::True, Self::parse_boolean);
         parser.register_prefix(TokenKind::False, Self::parse_boolean);
         parser.register_prefix(TokenKind::Lparen, Self::parse_grouped_expression);
         parser.register_prefix(TokenKind::If, Self::parse_if_expression);
+        parser.register_prefix(TokenKind::Match, Self::parse_match_expression);
         parser.register_prefix(TokenKind::Function, Self::parse_function_literal);
         parser.register_prefix(TokenKind::String, Self::parse_string_literal);
         parser.register_prefix(TokenKind::Lbracket, Self::parse_array_literal);
         parser.register_prefix(TokenKind::Lbrace, Self::parse_dictionary_literal);
 
+        parser.register_infix(TokenKind::FatArrow, Self::parse_arrow_function);
         parser.register_infix(TokenKind::Plus, Self::parse_infix_expression);
         parser.register_infix(TokenKind::Minus, Self::parse_infix_expression);
         parser.register_infix(TokenKind::Slash, Self::parse_infix_expression);
@@ -83,6 +144,26 @@ impl Parser {
         parser.register_infix(TokenKind::NotEq, Self::parse_infix_expression);
         parser.register_infix(TokenKind::Lt, Self::parse_infix_expression);
         parser.register_infix(TokenKind::Gt, Self::parse_infix_expression);
+        for kind in [
+            TokenKind::LtEq,
+            TokenKind::GtEq,
+            TokenKind::Percent,
+            TokenKind::Power,
+            TokenKind::And,
+            TokenKind::Or,
+            TokenKind::BitAnd,
+            TokenKind::BitOr,
+            TokenKind::BitXor,
+            TokenKind::ShiftLeft,
+            TokenKind::ShiftRight,
+        ] {
+            parser.register_infix(kind, Self::parse_infix_expression);
+        }
+        parser.register_infix(TokenKind::Assign, Self::parse_assign_expression);
+        parser.register_infix(TokenKind::PlusAssign, Self::parse_assign_expression);
+        parser.register_infix(TokenKind::MinusAssign, Self::parse_assign_expression);
+        parser.register_infix(TokenKind::AsteriskAssign, Self::parse_assign_expression);
+        parser.register_infix(TokenKind::SlashAssign, Self::parse_assign_expression);
         parser.register_infix(TokenKind::Lparen, Self::parse_call_expression);
         parser.register_infix(TokenKind::Lbracket, Self::parse_index_expression);
 
@@ -105,17 +186,36 @@ impl Parser {
             value: Default::default(),
         };
 
-        return match self.current_token.literal.parse::<i64>() {
-            Ok(value) => {
-                literal.value = value;
-                Some(ExpressionNode::Integer(literal))
-            }
+        if let Ok(value) = self.current_token.literal.parse::<i64>() {
+            literal.value = value;
+            return Some(ExpressionNode::Integer(literal));
+        }
+
+        match self.current_token.literal.parse::<BigInt>() {
+            Ok(value) => Some(ExpressionNode::BigInt(BigIntLiteral {
+                token: literal.token,
+                value,
+            })),
             Err(_) => {
                 let msg = format!("could not parse {} as integer", self.current_token.literal);
-                self.errors.push(msg);
+                self.push_error(ErrorCode::InvalidLiteral, msg, self.current_token.span);
                 None
             }
-        };
+        }
+    }
+
+    fn parse_float_literal(&mut self) -> Option<ExpressionNode> {
+        match self.current_token.literal.parse::<f64>() {
+            Ok(value) => Some(ExpressionNode::Float(FloatLiteral {
+                token: self.current_token.clone(),
+                value,
+            })),
+            Err(_) => {
+                let msg = format!("could not parse {} as float", self.current_token.literal);
+                self.push_error(ErrorCode::InvalidLiteral, msg, self.current_token.span);
+                None
+            }
+        }
     }
 
     fn parse_prefix_expression(&mut self) -> Option<ExpressionNode> {
@@ -141,6 +241,16 @@ impl Parser {
     }
 
     fn parse_grouped_expression(&mut self) -> Option<ExpressionNode> {
+        // `=>` right after the guard of a match arm belongs to the arm
+        if self.guard_nesting != Some(self.nesting - 1) && self.parameter_list_follows() {
+            let token = Self::arrow_function_token(self.current_token.span);
+            let parameters = self.parse_function_parameters()?;
+            if !self.expect_peek(TokenKind::FatArrow) {
+                return None;
+            }
+            return self.parse_arrow_function_body(token, parameters);
+        }
+
         self.next_token();
 
         let exp = self.parse_expression(PrecedenceLevel::Lowest);
@@ -165,10 +275,7 @@ impl Parser {
         }
 
         self.next_token();
-        expression.condition = Box::new(
-            self.parse_expression(PrecedenceLevel::Lowest)
-                .expect("error parsing condition"),
-        );
+        expression.condition = Box::new(self.parse_expression(PrecedenceLevel::Lowest)?);
 
         if !self.expect_peek(TokenKind::Rparen) {
             return None;
@@ -183,6 +290,21 @@ impl Parser {
         if self.peek_token_is(TokenKind::Else) {
             self.next_token();
 
+            // `else if` is an `else` block holding just the next `if`
+            if self.peek_token_is(TokenKind::If) {
+                self.next_token();
+                let token = self.current_token.clone();
+                let nested = self.parse_if_expression()?;
+                expression.alternative = Some(BlockStatement {
+                    token: token.clone(),
+                    statements: vec![StatementNode::Expression(ExpressionStatement {
+                        token,
+                        expression: Some(nested),
+                    })],
+                });
+                return Some(ExpressionNode::IfExpressionNode(expression));
+            }
+
             if !self.expect_peek(TokenKind::Lbrace) {
                 return None;
             }
@@ -193,6 +315,132 @@ impl Parser {
         Some(ExpressionNode::IfExpressionNode(expression))
     }
 
+    fn parse_match_expression(&mut self) -> Option<ExpressionNode> {
+        let token = self.current_token.clone();
+
+        self.next_token();
+        let subject = self.parse_expression(PrecedenceLevel::Lowest)?;
+
+        if !self.expect_peek(TokenKind::Lbrace) {
+            return None;
+        }
+
+        let mut arms = vec![];
+        while !self.peek_token_is(TokenKind::Rbrace) {
+            self.next_token();
+            let pattern = self.parse_pattern()?;
+
+            // the guard stops before `=>`, so `if ready => 1` is not read as an arrow function
+            let mut guard = None;
+            if self.peek_token_is(TokenKind::If) {
+                self.next_token();
+                let guard_nesting = self.guard_nesting.replace(self.nesting);
+                self.next_token();
+                let parsed = self.parse_expression(PrecedenceLevel::Assign);
+                self.guard_nesting = guard_nesting;
+                guard = Some(parsed?);
+            }
+
+            if !self.expect_peek(TokenKind::FatArrow) {
+                return None;
+            }
+            self.next_token();
+            let value = self.parse_expression(PrecedenceLevel::Lowest)?;
+            arms.push(MatchArm { pattern, guard, value });
+
+            if !self.peek_token_is(TokenKind::Rbrace) && !self.expect_peek(TokenKind::Comma) {
+                return None;
+            }
+        }
+
+        if !self.expect_peek(TokenKind::Rbrace) {
+            return None;
+        }
+
+        Some(ExpressionNode::Match(MatchExpression {
+            token,
+            subject: Box::new(subject),
+            arms,
+            end: self.current_token.clone(),
+        }))
+    }
+
+    fn parse_pattern(&mut self) -> Option<Pattern> {
+        match self.current_token.kind {
+            TokenKind::Ident if self.current_token.literal == "_" => {
+                Some(Pattern::Wildcard(self.current_token.clone()))
+            }
+            TokenKind::Ident => Some(Pattern::Binding(Identifier {
+                token: self.current_token.clone(),
+                value: self.current_token.literal.clone(),
+            })),
+            TokenKind::Lbracket => {
+                let token = self.current_token.clone();
+                let mut elements = vec![];
+
+                while !self.peek_token_is(TokenKind::Rbracket) {
+                    self.next_token();
+                    elements.push(self.parse_pattern()?);
+
+                    if !self.peek_token_is(TokenKind::Rbracket) && !self.expect_peek(TokenKind::Comma) {
+                        return None;
+                    }
+                }
+                self.next_token();
+
+                Some(Pattern::Array(token, elements))
+            }
+            TokenKind::Lbrace => {
+                let token = self.current_token.clone();
+                let mut pairs = vec![];
+
+                while !self.peek_token_is(TokenKind::Rbrace) {
+                    self.next_token();
+                    let key = self.parse_literal_pattern()?;
+
+                    if !self.expect_peek(TokenKind::Colon) {
+                        return None;
+                    }
+                    self.next_token();
+                    pairs.push((key, self.parse_pattern()?));
+
+                    if !self.peek_token_is(TokenKind::Rbrace) && !self.expect_peek(TokenKind::Comma) {
+                        return None;
+                    }
+                }
+                self.next_token();
+
+                Some(Pattern::Dictionary(token, pairs))
+            }
+            _ => self.parse_literal_pattern().map(Pattern::Literal),
+        }
+    }
+
+    // a number (optionally negative), string or boolean
+    fn parse_literal_pattern(&mut self) -> Option<ExpressionNode> {
+        match self.current_token.kind {
+            TokenKind::Int => self.parse_integer_literal(),
+            TokenKind::Float => self.parse_float_literal(),
+            TokenKind::String => self.parse_string_literal(),
+            TokenKind::True | TokenKind::False => self.parse_boolean(),
+            TokenKind::Minus if self.peek_token_is(TokenKind::Int) || self.peek_token_is(TokenKind::Float) => {
+                let token = self.current_token.clone();
+                self.next_token();
+                let number = self.parse_literal_pattern()?;
+                Some(ExpressionNode::Prefix(PrefixExpression {
+                    operator: token.literal.clone(),
+                    token,
+                    right: Box::new(number),
+                }))
+            }
+            _ => {
+                let msg = format!("expected a pattern, got {} instead", self.current_token.kind);
+                self.push_error(ErrorCode::ExpectedExpression, msg, self.current_token.span);
+                None
+            }
+        }
+    }
+
     fn parse_function_literal(&mut self) -> Option<ExpressionNode> {
         let mut literal = FunctionLiteral {
             token: self.current_token.clone(),
@@ -204,19 +452,99 @@ impl Parser {
             return None;
         }
 
-        literal.parameters = self
-            .parse_function_parameters()
-            .expect("error parsing parameters");
+        literal.parameters = self.parse_function_parameters()?;
 
         if !self.expect_peek(TokenKind::Lbrace) {
             return None;
         }
 
+        // `break` inside a function body cannot leave a loop around the function
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
         literal.body = self.parse_block_statement();
+        self.loop_depth = loop_depth;
 
         Some(ExpressionNode::Function(literal))
     }
 
+    // `x => x * 2`: the parameter name was parsed as the left side of `=>`
+    fn parse_arrow_function(&mut self, left: ExpressionNode) -> Option<ExpressionNode> {
+        let name = match left {
+            ExpressionNode::IdentifierNode(identifier) => identifier,
+            other => {
+                let msg = format!("expected a parameter name or list before =>, got {}", other.print_string());
+                self.push_error(ErrorCode::UnexpectedToken, msg, other.span());
+                return None;
+            }
+        };
+        let token = Self::arrow_function_token(name.token.span);
+        self.next_token();
+
+        let parameter = Parameter { name, default: None, rest: false };
+        self.parse_arrow_function_body(token, vec![parameter])
+    }
+
+    // the current token is `=>`. A body in braces is a block, anything else is a single
+    // expression; both become a plain function literal
+    fn parse_arrow_function_body(&mut self, token: Token, parameters: Vec<Parameter>) -> Option<ExpressionNode> {
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        let body = if self.peek_token_is(TokenKind::Lbrace) {
+            self.next_token();
+            Some(self.parse_block_statement())
+        } else {
+            self.next_token();
+            let body_token = self.current_token.clone();
+            self.parse_expression(PrecedenceLevel::Lowest).map(|expression| BlockStatement {
+                token: body_token.clone(),
+                statements: vec![StatementNode::Expression(ExpressionStatement {
+                    token: body_token,
+                    expression: Some(expression),
+                })],
+            })
+        };
+        self.loop_depth = loop_depth;
+
+        Some(ExpressionNode::Function(FunctionLiteral { token, parameters, body: body? }))
+    }
+
+    // stands in for the `function` keyword of an arrow function starting at `span`
+    fn arrow_function_token(span: Span) -> Token {
+        Token {
+            kind: TokenKind::Function,
+            literal: String::from("function"),
+            span,
+        }
+    }
+
+    // whether the `(` at the current token opens the parameters of an arrow function, i.e. its
+    // matching `)` is followed by `=>`. Scans with a lookahead lexer, so nothing is consumed
+    fn parameter_list_follows(&self) -> bool {
+        let mut lexer = self.lexer.lookahead();
+        let mut token = self.peek_token.clone();
+        let mut depth = 1;
+
+        loop {
+            match token.kind {
+                TokenKind::Lparen => depth += 1,
+                TokenKind::Rparen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                TokenKind::Eof => return false,
+                _ => {}
+            }
+            token = lexer.next_token();
+        }
+
+        loop {
+            match lexer.next_token().kind {
+                TokenKind::DocComment => {}
+                kind => return kind == TokenKind::FatArrow,
+            }
+        }
+    }
+
     fn parse_string_literal(&mut self) -> Option<ExpressionNode> {
         Some(ExpressionNode::StringExp(StringLiteral {
             token: self.current_token.clone(),
@@ -224,6 +552,43 @@ impl Parser {
         }))
     }
 
+    // the lexer alternates string text and the tokens of the embedded expressions:
+    // InterpolationStart, expression, (InterpolationMiddle, expression)*, InterpolationEnd
+    fn parse_interpolated_string(&mut self) -> Option<ExpressionNode> {
+        let token = self.current_token.clone();
+        let mut parts = vec![StringPart::Literal(token.literal.clone())];
+
+        loop {
+            if self.peek_token_is(TokenKind::InterpolationMiddle) || self.peek_token_is(TokenKind::InterpolationEnd) {
+                // keep going, so the rest of the string is still checked
+                let msg = String::from("empty `{}` in string, write `\\{` for a literal brace");
+                self.push_error(ErrorCode::ExpectedExpression, msg, self.peek_token.span);
+            } else {
+                self.next_token();
+                let expression = self.parse_expression(PrecedenceLevel::Lowest)?;
+                parts.push(StringPart::Expression(Box::new(expression)));
+            }
+
+            if self.peek_token_is(TokenKind::InterpolationMiddle) {
+                self.next_token();
+                parts.push(StringPart::Literal(self.current_token.literal.clone()));
+                continue;
+            }
+            if !self.expect_peek(TokenKind::InterpolationEnd) {
+                return None;
+            }
+            parts.push(StringPart::Literal(self.current_token.literal.clone()));
+            break;
+        }
+
+        parts.retain(|part| !matches!(part, StringPart::Literal(text) if text.is_empty()));
+        Some(ExpressionNode::InterpolatedString(InterpolatedString {
+            token,
+            parts,
+            end: self.current_token.clone(),
+        }))
+    }
+
     fn parse_array_literal(&mut self) -> Option<ExpressionNode> {
         let array = ArrayLiteral {
             token: self.current_token.clone(),
@@ -240,9 +605,7 @@ impl Parser {
 
         while !self.peek_token_is(TokenKind::Rbrace) {
             self.next_token();
-            let key = self
-                .parse_expression(PrecedenceLevel::Lowest)
-                .expect("error parsing key expression");
+            let key = self.parse_expression(PrecedenceLevel::Lowest)?;
 
             if !self.expect_peek(TokenKind::Colon) {
                 return None;
@@ -250,9 +613,7 @@ impl Parser {
 
             self.next_token();
 
-            let value = self
-                .parse_expression(PrecedenceLevel::Lowest)
-                .expect("error parsing value expression");
+            let value = self.parse_expression(PrecedenceLevel::Lowest)?;
             dictionary.pairs.push((key, value));
 
             if !self.peek_token_is(TokenKind::Rbrace) && !self.expect_peek(TokenKind::Comma) {
@@ -267,38 +628,66 @@ impl Parser {
         Some(ExpressionNode::Dictionary(dictionary))
     }
 
-    fn parse_function_parameters(&mut self) -> Option<Vec<Identifier>> {
-        let mut identifiers = vec![];
-
-        if self.peek_token_is(TokenKind::Rparen) {
-            self.next_token();
-            return Some(identifiers);
-        }
+    // `(a, b = 1, ...rest)`: parameters with a default come after those without,
+    // and a rest parameter is last
+    // defaults run inside the call, so like the body they cannot `break` out of a loop
+    fn parse_function_parameters(&mut self) -> Option<Vec<Parameter>> {
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        let parameters = self.parse_parameter_list();
+        self.loop_depth = loop_depth;
+        parameters
+    }
 
-        self.next_token();
+    fn parse_parameter_list(&mut self) -> Option<Vec<Parameter>> {
+        let mut parameters: Vec<Parameter> = vec![];
 
-        let ident = Identifier {
-            token: self.current_token.clone(),
-            value: self.current_token.literal.clone(),
-        };
-
-        identifiers.push(ident);
+        while !self.peek_token_is(TokenKind::Rparen) {
+            if let Some(last) = parameters.last() {
+                if last.rest {
+                    let msg = format!("rest parameter `...{}` must be the last parameter", last.name.value);
+                    self.push_error(ErrorCode::UnexpectedToken, msg, last.span());
+                    return None;
+                }
+            }
 
-        while self.peek_token_is(TokenKind::Comma) {
-            self.next_token();
-            self.next_token();
-            let ident = Identifier {
-                token: self.current_token.clone(),
-                value: self.current_token.literal.clone(),
+            let rest = self.peek_token_is(TokenKind::Ellipsis);
+            if rest {
+                self.next_token();
+            }
+            if !self.expect_peek(TokenKind::Ident) {
+                return None;
+            }
+            let mut parameter = Parameter {
+                name: Identifier {
+                    token: self.current_token.clone(),
+                    value: self.current_token.literal.clone(),
+                },
+                default: None,
+                rest,
             };
-            identifiers.push(ident);
-        }
 
-        if !self.expect_peek(TokenKind::Rparen) {
-            return None;
+            if rest && self.peek_token_is(TokenKind::Assign) {
+                let msg = format!("rest parameter `...{}` cannot have a default", parameter.name.value);
+                self.push_error(ErrorCode::UnexpectedToken, msg, parameter.span());
+                return None;
+            } else if self.peek_token_is(TokenKind::Assign) {
+                self.next_token();
+                self.next_token();
+                parameter.default = Some(self.parse_expression(PrecedenceLevel::Lowest)?);
+            } else if !rest && parameters.iter().any(|parameter| parameter.default.is_some()) {
+                let msg = format!("parameter `{}` without a default follows one with a default", parameter.name.value);
+                self.push_error(ErrorCode::UnexpectedToken, msg, parameter.span());
+                return None;
+            }
+            parameters.push(parameter);
+
+            if !self.peek_token_is(TokenKind::Rparen) && !self.expect_peek(TokenKind::Comma) {
+                return None;
+            }
         }
+        self.next_token();
 
-        Some(identifiers)
+        Some(parameters)
     }
 
     fn parse_block_statement(&mut self) -> BlockStatement {
@@ -331,7 +720,11 @@ impl Parser {
             left: Box::new(left),
             right: Default::default(),
         };
-        let precedence = self.cur_precedence();
+        let mut precedence = self.cur_precedence();
+        // `**` is right-associative: `2 ** 3 ** 2` is `2 ** (3 ** 2)`
+        if self.current_token.kind == TokenKind::Power {
+            precedence = PrecedenceLevel::Prefix;
+        }
         self.next_token();
         match self.parse_expression(precedence) {
             Some(exp) => expression.right = Box::new(exp),
@@ -341,15 +734,67 @@ impl Parser {
         Some(ExpressionNode::Infix(expression))
     }
 
+    fn parse_assign_expression(&mut self, target: ExpressionNode) -> Option<ExpressionNode> {
+        self.next_token();
+        let mut expression = AssignExpression {
+            token: self.current_token.clone(),
+            operator: self.current_token.literal.clone(),
+            target: Box::new(target),
+            value: Default::default(),
+        };
+
+        // right associative: `a = b = 1` assigns `b` first
+        self.next_token();
+        expression.value = Box::new(self.parse_expression(PrecedenceLevel::Lowest)?);
+
+        match *expression.target {
+            ExpressionNode::IdentifierNode(_) | ExpressionNode::Index(_) => {
+                Some(ExpressionNode::Assign(expression))
+            }
+            _ => {
+                let msg = format!("invalid assignment target: {}", expression.target.print_string());
+                self.push_error(ErrorCode::InvalidAssignmentTarget, msg, expression.target.span());
+                None
+            }
+        }
+    }
+
     fn parse_call_expression(&mut self, function: ExpressionNode) -> Option<ExpressionNode> {
         self.next_token();
         let mut exp = CallExpression {
             token: self.current_token.clone(),
             function: Box::new(function),
             arguments: vec![],
+            named: vec![],
         };
 
-        exp.arguments = self.parse_expression_list(TokenKind::Rparen);
+        // positional arguments, then `name: value` ones
+        while !self.peek_token_is(TokenKind::Rparen) {
+            self.next_token();
+
+            if self.current_token_is(TokenKind::Ident) && self.peek_token_is(TokenKind::Colon) {
+                let name = Identifier {
+                    token: self.current_token.clone(),
+                    value: self.current_token.literal.clone(),
+                };
+                self.next_token();
+                self.next_token();
+                exp.named.push((name, self.parse_expression(PrecedenceLevel::Lowest)?));
+            } else {
+                let argument = self.parse_expression(PrecedenceLevel::Lowest)?;
+                if !exp.named.is_empty() {
+                    let msg = String::from("positional argument after a named argument");
+                    self.push_error(ErrorCode::UnexpectedToken, msg, argument.span());
+                    return None;
+                }
+                exp.arguments.push(argument);
+            }
+
+            if !self.peek_token_is(TokenKind::Rparen) && !self.expect_peek(TokenKind::Comma) {
+                return None;
+            }
+        }
+        self.next_token();
 
         Some(ExpressionNode::Call(exp))
     }
@@ -364,10 +809,7 @@ impl Parser {
 
         self.next_token();
 
-        exp.index = Box::new(
-            self.parse_expression(PrecedenceLevel::Lowest)
-                .expect("error parsing index expression"),
-        );
+        exp.index = Box::new(self.parse_expression(PrecedenceLevel::Lowest)?);
 
         if !self.expect_peek(TokenKind::Rbracket) {
             return None;
@@ -384,19 +826,14 @@ impl Parser {
             return arguments;
         }
 
+        // an argument that fails to parse has already recorded its error
         self.next_token();
-        arguments.push(
-            self.parse_expression(PrecedenceLevel::Lowest)
-                .expect("error parsing arguments"),
-        );
+        arguments.extend(self.parse_expression(PrecedenceLevel::Lowest));
 
         while self.peek_token_is(TokenKind::Comma) {
             self.next_token();
             self.next_token();
-            arguments.push(
-                self.parse_expression(PrecedenceLevel::Lowest)
-                    .expect("error parsing arguments"),
-            )
+            arguments.extend(self.parse_expression(PrecedenceLevel::Lowest));
         }
 
         if !self.expect_peek(end) {
@@ -408,7 +845,20 @@ impl Parser {
 
     fn next_token(&mut self) {
         self.current_token = self.peek_token.clone();
+        match self.current_token.kind {
+            TokenKind::Lparen | TokenKind::Lbracket | TokenKind::Lbrace => self.nesting += 1,
+            TokenKind::Rparen | TokenKind::Rbracket | TokenKind::Rbrace => self.nesting = self.nesting.saturating_sub(1),
+            _ => {}
+        }
         self.peek_token = self.lexer.next_token();
+        // doc comments are only for documentation tools, programs ignore them
+        while self.peek_token.kind == TokenKind::DocComment {
+            self.peek_token = self.lexer.next_token();
+        }
+
+        for error in self.lexer.take_errors() {
+            self.errors.push(ParserError::from(error));
+        }
     }
 
     pub fn parse_program(&mut self) -> Option<Program> {
@@ -428,10 +878,129 @@ impl Parser {
         match self.current_token.kind {
             TokenKind::Say => self.parse_say_statement(),
             TokenKind::Return => self.parse_return_statement(),
+            TokenKind::While => self.parse_while_statement(),
+            TokenKind::For => self.parse_for_statement(),
+            TokenKind::Function if self.peek_token_is(TokenKind::Ident) => self.parse_function_declaration(),
+            TokenKind::Break | TokenKind::Continue => self.parse_loop_control_statement(),
             _ => self.parse_expression_statement(),
         }
     }
 
+    fn parse_while_statement(&mut self) -> Option<StatementNode> {
+        let mut stmt = WhileStatement {
+            token: self.current_token.clone(),
+            condition: Default::default(),
+            body: Default::default(),
+        };
+
+        if !self.expect_peek(TokenKind::Lparen) {
+            return None;
+        }
+
+        self.next_token();
+        stmt.condition = self.parse_expression(PrecedenceLevel::Lowest)?;
+
+        if !self.expect_peek(TokenKind::Rparen) {
+            return None;
+        }
+
+        if !self.expect_peek(TokenKind::Lbrace) {
+            return None;
+        }
+
+        stmt.body = self.parse_loop_body();
+
+        Some(StatementNode::While(stmt))
+    }
+
+    fn parse_for_statement(&mut self) -> Option<StatementNode> {
+        let mut stmt = ForStatement {
+            token: self.current_token.clone(),
+            variable: Default::default(),
+            iterable: Default::default(),
+            body: Default::default(),
+        };
+
+        if !self.expect_peek(TokenKind::Lparen) {
+            return None;
+        }
+
+        if !self.expect_peek(TokenKind::Ident) {
+            return None;
+        }
+        stmt.variable = Identifier {
+            token: self.current_token.clone(),
+            value: self.current_token.literal.clone(),
+        };
+
+        if !self.expect_peek(TokenKind::In) {
+            return None;
+        }
+
+        self.next_token();
+        stmt.iterable = self.parse_expression(PrecedenceLevel::Lowest)?;
+
+        if !self.expect_peek(TokenKind::Rparen) {
+            return None;
+        }
+
+        if !self.expect_peek(TokenKind::Lbrace) {
+            return None;
+        }
+
+        stmt.body = self.parse_loop_body();
+
+        Some(StatementNode::For(stmt))
+    }
+
+    fn parse_function_declaration(&mut self) -> Option<StatementNode> {
+        let token = self.current_token.clone();
+
+        self.next_token();
+        let name = Identifier {
+            token: self.current_token.clone(),
+            value: self.current_token.literal.clone(),
+        };
+
+        let mut function = match self.parse_function_literal()? {
+            ExpressionNode::Function(function) => function,
+            _ => return None,
+        };
+        function.token = token.clone();
+
+        if self.peek_token_is(TokenKind::Semicolon) {
+            self.next_token();
+        }
+
+        Some(StatementNode::Function(FunctionDeclaration { token, name, function }))
+    }
+
+    fn parse_loop_body(&mut self) -> BlockStatement {
+        self.loop_depth += 1;
+        let body = self.parse_block_statement();
+        self.loop_depth -= 1;
+        body
+    }
+
+    fn parse_loop_control_statement(&mut self) -> Option<StatementNode> {
+        let token = self.current_token.clone();
+
+        if self.peek_token_is(TokenKind::Semicolon) {
+            self.next_token();
+        }
+
+        if self.loop_depth == 0 {
+            let msg = format!("`{}` outside of a loop", token.literal);
+            self.push_error(ErrorCode::UnexpectedToken, msg, token.span);
+            return None;
+        }
+
+        match token.kind {
+            TokenKind::Break => Some(StatementNode::Break(token)),
+            _ => Some(StatementNode::Continue(token)),
+        }
+    }
+
     fn parse_expression_statement(&mut self) -> Option<StatementNode> {
         let stmt = ExpressionStatement {
             token: self.current_token.clone(),
@@ -456,7 +1025,9 @@ impl Parser {
             {
                 let infix_fn = self.infix_parse_fns.get(&self.peek_token.kind);
                 if let Some(infix) = infix_fn {
-                    left_exp = infix(self, left_exp.expect("left exp should be present"))
+                    // the left side failed to parse and has already recorded its error
+                    let left = left_exp?;
+                    left_exp = infix(self, left)
                 }
             }
             return left_exp;
@@ -466,8 +1037,12 @@ impl Parser {
     }
 
     fn no_prefix_parse_fn_error(&mut self, token_kind: TokenKind) {
+        // the lexer has already reported the illegal character itself
+        if token_kind == TokenKind::Illegal {
+            return;
+        }
         let msg = format!("no prefix parse function for {} found", token_kind);
-        self.errors.push(msg)
+        self.push_error(ErrorCode::ExpectedExpression, msg, self.current_token.span);
     }
 
     fn parse_say_statement(&mut self) -> Option<StatementNode> {
@@ -477,7 +1052,7 @@ impl Parser {
             value: Default::default(),
         };
 
-        return if !self.expect_peek(TokenKind::Ident) {
+        if !self.expect_peek(TokenKind::Ident) {
             None
         } else {
             stmt.name = Identifier {
@@ -495,7 +1070,7 @@ impl Parser {
                 }
                 Some(StatementNode::Say(stmt))
             }
-        };
+        }
     }
 
     fn parse_return_statement(&mut self) -> Option<StatementNode> {
@@ -531,16 +1106,20 @@ impl Parser {
         self.current_token.kind == token_kind
     }
 
-    pub fn errors(&self) -> &Vec<String> {
+    pub fn errors(&self) -> &Vec<ParserError> {
         &self.errors
     }
 
+    fn push_error(&mut self, code: ErrorCode, message: String, span: Span) {
+        self.errors.push(ParserError { code, message, span });
+    }
+
     fn peek_error(&mut self, token_kind: TokenKind) {
         let msg = format!(
             "expected next token to be {}, got={} instead",
             token_kind, self.peek_token.kind
         );
-        self.errors.push(msg);
+        self.push_error(ErrorCode::UnexpectedToken, msg, self.peek_token.span);
     }
 
     fn register_prefix(&mut self, token_kind: TokenKind, prefix_fn: PrefixParseFn) {
@@ -565,7 +1144,7 @@ mod test {
     use std::any;
 
     use crate::{
-        ast::{ExpressionNode, Identifier, Node, StatementNode},
+        ast::{ExpressionNode, ExpressionStatement, Identifier, Node, StatementNode},
         lexer::Lexer,
         token::TokenKind,
     };
@@ -794,7 +1373,8 @@ mod test {
 
     #[test]
     fn test_parsing_infix_expressions() {               //          Comparsion and Arithmetic Operators
-        let infix_tests: Vec<(&str, Box<dyn any::Any>, &str, Box<dyn any::Any>)> = vec![
+        type InfixTest = (&'static str, Box<dyn any::Any>, &'static str, Box<dyn any::Any>);
+        let infix_tests: Vec<InfixTest> = vec![
             ("1382 + 1382;", Box::new(1382), "+", Box::new(1382)),
             ("1383 - 1383;", Box::new(1383), "-", Box::new(1383)),
             ("8443 * 8443;", Box::new(8443), "*", Box::new(8443)),
@@ -869,6 +1449,21 @@ mod test {
             ("3 > 5 == false", "((3 > 5) == false)"),
             ("3 < 5 == true", "((3 < 5) == true)"),
             ("1 + (2 + 3) + 4", "((1 + (2 + 3)) + 4)"),
+            ("a = b = 1 + 2", "(a = (b = (1 + 2)))"),
+            ("-.5 * 2.0e1", "((-.5) * 2.0e1)"),
+            ("x += y * 2 == 4", "(x += ((y * 2) == 4))"),
+            ("x /= f(1)", "(x /= f(1))"),
+            ("a[i + 1] = d[\"k\"] *= 2", "((a[(i + 1)]) = ((d[k]) *= 2))"),
+            ("a || b && c", "(a || (b && c))"),
+            ("a && b == c || !d", "((a && (b == c)) || (!d))"),
+            ("1 + 2 <= 3 * 4 >= 5", "(((1 + 2) <= (3 * 4)) >= 5)"),
+            ("a % b * c", "((a % b) * c)"),
+            ("-2 ** 2", "(-(2 ** 2))"),
+            ("2 ** 3 ** 2", "(2 ** (3 ** 2))"),
+            ("a | b ^ c & d", "(a | (b ^ (c & d)))"),
+            ("a & b == c", "((a & b) == c)"),
+            ("1 << 2 + 3", "(1 << (2 + 3))"),
+            ("~a >> 1 < b", "(((~a) >> 1) < b)"),
             ("(5 + 5) * 2", "((5 + 5) * 2)"),
             ("2 / (5 + 5)", "(2 / (5 + 5))"),
             ("-(5 + 5)", "(-(5 + 5))"),
@@ -1106,27 +1701,21 @@ mod test {
                         fn_lit.parameters.len()
                     );
 
-                    match &fn_lit.parameters[0] {
-                        Identifier { token, value } => {
-                            assert_eq!(value, "x", "parameter wrong. Expected `x` got={}", value);
-                            assert_eq!(
-                                token.literal, "x",
-                                "parameter wrong. Expected `x` got={}",
-                                token.literal
-                            );
-                        }
-                    }
+                    let Identifier { token, value } = &fn_lit.parameters[0].name;
+                    assert_eq!(value, "x", "parameter wrong. Expected `x` got={}", value);
+                    assert_eq!(
+                        token.literal, "x",
+                        "parameter wrong. Expected `x` got={}",
+                        token.literal
+                    );
 
-                    match &fn_lit.parameters[1] {
-                        Identifier { token, value } => {
-                            assert_eq!(value, "y", "parameter wrong. Expected `y` got={}", value);
-                            assert_eq!(
-                                token.literal, "y",
-                                "parameter wrong. Expected `y` got={}",
-                                token.literal
-                            );
-                        }
-                    }
+                    let Identifier { token, value } = &fn_lit.parameters[1].name;
+                    assert_eq!(value, "y", "parameter wrong. Expected `y` got={}", value);
+                    assert_eq!(
+                        token.literal, "y",
+                        "parameter wrong. Expected `y` got={}",
+                        token.literal
+                    );
 
                     assert_eq!(
                         fn_lit.body.statements.len(),
@@ -1182,9 +1771,9 @@ mod test {
 
                             for (idx, ident) in test.1.into_iter().enumerate() {
                                 assert_eq!(
-                                    fn_lit.parameters[idx].value, ident,
+                                    fn_lit.parameters[idx].name.value, ident,
                                     "expected {}, got={}",
-                                    ident, fn_lit.parameters[idx].value
+                                    ident, fn_lit.parameters[idx].name.value
                                 );
                                 assert_eq!(
                                     fn_lit.parameters[idx].token_literal(),
@@ -1250,6 +1839,37 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_interpolated_strings() {
+        let tests = vec![
+            (r#""Hello {name}!""#, r#""Hello {name}!""#, 3),
+            (r#""{a}{b}""#, r#""{a}{b}""#, 2),
+            (r#""sum: {1 + 2 * x}""#, r#""sum: {(1 + (2 * x))}""#, 2),
+            (r#""{{"k": 1}["k"]} \{x\}""#, r#""{({k:1}[k])} \{x\}""#, 2),
+            (r#""outer {"inner {n}"}""#, r#""outer {"inner {n}"}""#, 2),
+        ];
+
+        for (input, expected, parts) in tests {
+            let lexer = Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            let program = parser.parse_program().unwrap();
+            check_parser_errors(parser);
+
+            let stmt = &program.statements[0];
+            match stmt {
+                StatementNode::Expression(ExpressionStatement {
+                    expression: Some(ExpressionNode::InterpolatedString(interpolated)),
+                    ..
+                }) => {
+                    assert_eq!(interpolated.print_string(), expected, "wrong string for {:?}", input);
+                    assert_eq!(interpolated.parts.len(), parts, "wrong number of parts for {:?}", input);
+                    assert_eq!(interpolated.span().end.offset, input.len(), "span should end at the quote");
+                }
+                other => panic!("not an interpolated string for {:?}, got={:?}", input, other),
+            }
+        }
+    }
+
     #[test]
     fn test_string_literal_expression() {
         let input = r#""hello world""#;
@@ -1380,18 +2000,13 @@ mod test {
                             dictionary.pairs.len()
                         );
 
-                        let expected = vec![
-                            ("one".to_string(), 1),
+                        let expected = [("one".to_string(), 1),
                             ("two".to_string(), 2),
-                            ("three".to_string(), 3),
-                        ];
-
-                        let mut curr_idx: usize = 0;
+                            ("three".to_string(), 3)];
 
-                        for (_, value) in &dictionary.pairs {
+                        for (curr_idx, (_, value)) in dictionary.pairs.iter().enumerate() {
                             let expected_value = expected[curr_idx].1;
                             test_integer_literal(value, expected_value);
-                            curr_idx += 1;
                         }
                     }
                     other => panic!("not an dictionary literal. got={:?}", other),
@@ -1456,15 +2071,11 @@ mod test {
                             dictionary.pairs.len()
                         );
 
-                        let expected = vec![
-                            ("one".to_string(), (0, "+", 1)),
+                        let expected = [("one".to_string(), (0, "+", 1)),
                             ("two".to_string(), (10, "-", 8)),
-                            ("three".to_string(), (15, "/", 5)),
-                        ];
-
-                        let mut curr_idx: usize = 0;
+                            ("three".to_string(), (15, "/", 5))];
 
-                        for (_, value) in &dictionary.pairs {
+                        for (curr_idx, (_, value)) in dictionary.pairs.iter().enumerate() {
                             let expected_value = &expected[curr_idx];
                             test_func_for_key(
                                 value,
@@ -1472,7 +2083,6 @@ mod test {
                                 expected_value.1 .1,
                                 expected_value.1 .2,
                             );
-                            curr_idx += 1;
                         }
                     }
                     other => panic!("not an hash literal. got={:?}", other),
@@ -1482,6 +2092,261 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_else_if_chains() {
+        let input = "if (x < 0) { -1 } else if (x == 0) { 0 } else if (x < 10) { 1 } else { 2 }";
+        let program = Parser::new(Lexer::new(input)).parse_program().unwrap();
+
+        assert_eq!(
+            program.print_string(),
+            "if(x < 0) (-1)else if(x == 0) 0else if(x < 10) 1else 2"
+        );
+
+        // the chain is nested: each `else` block holds the next `if`
+        let mut depth = 0;
+        let mut expression = match &program.statements[0] {
+            StatementNode::Expression(stmt) => stmt.expression.clone().unwrap(),
+            other => panic!("statement is not an ExpressionStatement. got={:?}", other),
+        };
+        while let ExpressionNode::IfExpressionNode(if_exp) = expression {
+            depth += 1;
+            expression = match if_exp.alternative.as_ref().map(|block| &block.statements[..]) {
+                Some([StatementNode::Expression(stmt)]) => stmt.expression.clone().unwrap(),
+                _ => ExpressionNode::None,
+            };
+        }
+        assert_eq!(depth, 3);
+    }
+
+    #[test]
+    fn test_match_expression_parsing() {
+        let tests = vec![
+            ("match x { 1 => \"one\", _ => \"other\" }", "match x { 1 => one, _ => other }"),
+            ("match x { -1 => a, 2.5 => b, true => c, \"s\" => d, }", "match x { (-1) => a, 2.5 => b, true => c, s => d }"),
+            ("match f(x) { [] => 0, [a, [b, _]] => a + b }", "match f(x) { [] => 0, [a, [b, _]] => (a + b) }"),
+            ("match p { {\"x\": 0, \"y\": y} => y, {} => 0 }", "match p { {x:0, y:y} => y, {} => 0 }"),
+            ("match n { n if n > 1 && n < 5 => n * 2 }", "match n { n if ((n > 1) && (n < 5)) => (n * 2) }"),
+            ("say y = match x { _ => 1 } + 2;", "say y = (match x { _ => 1 } + 2);"),
+            ("match x { }", "match x {  }"),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program().unwrap();
+            assert!(parser.errors().is_empty(), "parser errors for {:?}: {:?}", input, parser.errors());
+            assert_eq!(program.print_string(), expected);
+        }
+
+        let mut parser = Parser::new(Lexer::new("match x { [a, b] if a > b => a }"));
+        let program = parser.parse_program().unwrap();
+        match &program.statements[0] {
+            StatementNode::Expression(ExpressionStatement {
+                expression: Some(ExpressionNode::Match(match_exp)),
+                ..
+            }) => {
+                assert_eq!(match_exp.arms.len(), 1);
+                let names: Vec<&str> = match_exp.arms[0].pattern.bindings().iter().map(|id| id.value.as_str()).collect();
+                assert_eq!(names, vec!["a", "b"]);
+                assert!(match_exp.arms[0].guard.is_some());
+            }
+            other => panic!("statement is not a match expression. got={:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_function_declaration_parsing() {
+        let input = "function add(a, b) { a + b }; function() { 1 }; function noop() {}";
+        let mut parser = Parser::new(Lexer::new(input));
+        let program = parser.parse_program().unwrap();
+        check_parser_errors(parser);
+
+        assert_eq!(program.statements.len(), 3);
+        match &program.statements[0] {
+            StatementNode::Function(declaration) => {
+                assert_eq!(declaration.name.value, "add");
+                assert_eq!(declaration.function.parameters.len(), 2);
+                assert_eq!(declaration.print_string(), "function add(a, b)(a + b)");
+            }
+            other => panic!("statement is not a function declaration. got={:?}", other),
+        }
+        // without a name it is still a function literal
+        assert!(matches!(&program.statements[1], StatementNode::Expression(_)));
+        assert!(matches!(&program.statements[2], StatementNode::Function(_)));
+    }
+
+    #[test]
+    fn test_default_rest_and_named_parameters() {
+        let tests = vec![
+            ("function(x, y = x * 2, ...rest) { rest }", "function(x, y = (x * 2), ...rest)rest"),
+            ("function(...all) {}", "function(...all)"),
+            ("greet(\"hi\", name: \"arash\", times: 1 + 1)", "greet(hi, name: arash, times: (1 + 1))"),
+            ("f(a: [1, 2][0])", "f(a: ([1, 2][0]))"),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program().unwrap();
+            check_parser_errors(parser);
+
+            assert_eq!(program.print_string(), expected);
+        }
+
+        let mut parser = Parser::new(Lexer::new("function(a, b = 1, ...c) {}"));
+        let program = parser.parse_program().unwrap();
+        check_parser_errors(parser);
+        match &program.statements[0] {
+            StatementNode::Expression(ExpressionStatement { expression: Some(ExpressionNode::Function(fn_lit)), .. }) => {
+                let flags: Vec<(bool, bool)> = fn_lit
+                    .parameters
+                    .iter()
+                    .map(|parameter| (parameter.default.is_some(), parameter.rest))
+                    .collect();
+                assert_eq!(flags, vec![(false, false), (true, false), (false, true)]);
+            }
+            other => panic!("statement is not a function literal. got={:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_arrow_functions() {
+        let tests = vec![
+            ("x => x * 2", "function(x)(x * 2)"),
+            ("(a, b = 1) => { a + b }", "function(a, b = 1)(a + b)"),
+            ("() => 1", "function()1"),
+            ("(...rest) => rest", "function(...rest)rest"),
+            ("f => x => f(x)", "function(f)function(x)f(x)"),
+            ("say double = x => x + x;", "say double = function(x)(x + x);"),
+            ("map(items, (item) => item[0])", "map(items, function(item)(item[0]))"),
+            ("(a) + (b)", "(a + b)"),
+            ("match v { n if ready => n, _ if (ready) => 0 }", "match v { n if ready => n, _ if ready => 0 }"),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program().unwrap();
+            check_parser_errors(parser);
+
+            assert_eq!(program.print_string(), expected, "wrong parse of {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_doc_comments_are_skipped() {
+        let lexer = Lexer::new("/// The answer.\nsay answer = /* folded */ 42; /// trailing").with_doc_comments();
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+        check_parser_errors(parser);
+
+        assert_eq!(program.statements.len(), 1);
+        assert_eq!(program.print_string(), "say answer = 42;");
+    }
+
+    #[test]
+    fn test_loop_statements() {
+        let input = "while (x < 10) { continue; } for (item in [1, 2]) { break; }";
+
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+        check_parser_errors(parser);
+
+        assert_eq!(program.statements.len(), 2);
+
+        match &program.statements[0] {
+            StatementNode::While(while_stmt) => {
+                test_infix_expression(&while_stmt.condition, Box::new("x"), String::from("<"), Box::new(10));
+                assert!(matches!(while_stmt.body.statements[..], [StatementNode::Continue(_)]));
+            }
+            other => panic!("statement is not a WhileStatement. got={:?}", other),
+        }
+
+        match &program.statements[1] {
+            StatementNode::For(for_stmt) => {
+                assert_eq!(for_stmt.variable.value, "item");
+                assert_eq!(for_stmt.iterable.print_string(), "[1, 2]");
+                assert!(matches!(for_stmt.body.statements[..], [StatementNode::Break(_)]));
+            }
+            other => panic!("statement is not a ForStatement. got={:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_node_spans() {
+        let tests = vec![
+            ("1 + 2 * 3", (1, 1), (1, 10)),
+            ("say x = -y;", (1, 1), (1, 11)),
+            ("add(1,\n    foo)", (1, 1), (2, 8)),
+            ("\n  [1, 2][0]", (2, 3), (2, 11)),
+        ];
+
+        for (input, start, end) in tests {
+            let lexer = Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            let program = parser.parse_program().unwrap();
+            check_parser_errors(parser);
+
+            let span = program.statements[0].span();
+            assert_eq!(
+                (span.start.line, span.start.column),
+                start,
+                "wrong start for {:?}, got={:?}",
+                input,
+                span
+            );
+            assert_eq!(
+                (span.end.line, span.end.column),
+                end,
+                "wrong end for {:?}, got={:?}",
+                input,
+                span
+            );
+        }
+    }
+
+    #[test]
+    fn test_parser_error_locations() {
+        let tests = vec![
+            ("say = 5;", "line 1, column 5: expected next token to be Ident, got== instead"),
+            ("say x 5;", "line 1, column 7: expected next token to be =, got=Int instead"),
+            ("\n\n   )", "line 3, column 4: no prefix parse function for ) found"),
+            ("break;", "line 1, column 1: `break` outside of a loop"),
+            ("\n 1 + 2 = 3;", "line 2, column 2: invalid assignment target: (1 + 2)"),
+            ("while (x) { function() { continue; } }", "line 1, column 26: `continue` outside of a loop"),
+            ("for (x of y) { x }", "line 1, column 8: expected next token to be in, got=Ident instead"),
+            ("\"a {} b\"", "line 1, column 5: empty `{}` in string, write `\\{` for a literal brace"),
+            ("\"a {x y} b\"", "line 1, column 7: expected next token to be InterpolationEnd, got=Ident instead"),
+            ("match x { x + 1 => 2 }", "line 1, column 13: expected next token to be =>, got=+ instead"),
+            ("match x { (1) => 2 }", "line 1, column 11: expected a pattern, got ( instead"),
+            ("function f { 1 }", "line 1, column 12: expected next token to be (, got={ instead"),
+            ("match x { 1 => 2 3 => 4 }", "line 1, column 18: expected next token to be ,, got=Int instead"),
+            ("function(...a, b) {}", "line 1, column 13: rest parameter `...a` must be the last parameter"),
+            ("function(a = 1, b) {}", "line 1, column 17: parameter `b` without a default follows one with a default"),
+            ("function(...a = 1) {}", "line 1, column 13: rest parameter `...a` cannot have a default"),
+            ("f(a: 1, 2)", "line 1, column 9: positional argument after a named argument"),
+            ("a + b => 1", "line 1, column 1: expected a parameter name or list before =>, got (a + b)"),
+            ("(1) => 1", "line 1, column 2: expected next token to be Ident, got=Int instead"),
+            ("while (x) { y => { break; } }", "line 1, column 20: `break` outside of a loop"),
+            ("while (x) { function(a = if (x) { break; } else { 1 }) { a } }", "line 1, column 35: `break` outside of a loop"),
+            ("if () { 1 }", "line 1, column 5: no prefix parse function for ) found"),
+            ("say x = [1]; x[]", "line 1, column 16: no prefix parse function for ] found"),
+            ("{:1}", "line 1, column 2: no prefix parse function for : found"),
+            ("{1: }", "line 1, column 5: no prefix parse function for } found"),
+        ];
+
+        for (input, expected) in tests {
+            let lexer = Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            parser.parse_program();
+
+            assert_eq!(
+                parser.errors()[0].to_string(),
+                expected,
+                "wrong first error for {:?}",
+                input
+            );
+        }
+    }
+
     fn test_func_for_key(exp: &ExpressionNode, left: i64, operator: &str, right: i64) {
         test_infix_expression(exp, Box::new(left), operator.to_string(), Box::new(right));
     }
@@ -1515,7 +2380,7 @@ mod test {
     fn check_parser_errors(parser: Parser) {
         let errors = parser.errors();
 
-        if errors.len() == 0 {
+        if errors.is_empty() {
             return;
         }
 
@@ -1571,10 +2436,7 @@ mod test {
             Some(exp_string) => test_identifier(exp, exp_string.to_string()),
             None => match expected.downcast_ref::<i64>() {
                 Some(int_exp) => test_integer_literal(exp, int_exp.to_owned()),
-                None => match expected.downcast_ref::<bool>() {
-                    Some(bool) => test_boolean_literal(exp, bool.to_owned()),
-                    None => (),
-                },
+                None => if let Some(bool) = expected.downcast_ref::<bool>() { test_boolean_literal(exp, bool.to_owned()) },
             },
         }
     }
@@ -1,9 +1,23 @@
 use std::io::{Stdin, Stdout, Write};
 
-use crate::{evaluator::Evaluator, lexer::Lexer, parser::Parser};
+use crate::{
+    diagnostics::Diagnostic,
+    engine::{Backend, Engine},
+    evaluator::IntegerOverflow,
+    lexer::Lexer,
+    object::Object,
+    parser::{Parser, ParserError},
+};
 
-pub fn start(stdin: Stdin, mut stdout: Stdout) {
-    let mut evaluator = Evaluator::new();
+const ORIGIN: &str = "<repl>";
+
+pub fn start(stdin: Stdin, mut stdout: Stdout, backend: Backend, integer_overflow: IntegerOverflow, stack_limit: usize) {
+    let mut engine = Engine::new(backend);
+    engine.set_integer_overflow(integer_overflow);
+    engine.set_stack_limit(stack_limit);
+    // every line typed so far; functions defined earlier can fail later, so diagnostics
+    // need the whole session to show the line an error came from
+    let mut session = String::new();
     loop {
         write!(stdout, "==> ").expect("should have written prompt string ==>");
         stdout.flush().expect("should have flushed stdout!");
@@ -15,36 +29,36 @@ pub fn start(stdin: Stdin, mut stdout: Stdout) {
             return;
         }
 
-        let lexer = Lexer::new(input.as_str());
+        let first_line = session.lines().count() + 1;
+        session.push_str(input.as_str());
+        if !session.ends_with('\n') {
+            session.push('\n');
+        }
+
+        let lexer = Lexer::new_at_line(input.as_str(), first_line);
         let mut parser = Parser::new(lexer);
         let program = parser.parse_program().expect("error parsing program");
 
-        if parser.errors().len() != 0 {
-            print_parse_errors(&stdout, parser.errors());
+        if !parser.errors().is_empty() {
+            print_parse_errors(&stdout, parser.errors(), &session);
             continue;
         }
 
-        let evaluated = evaluator.eval_program(program);
+        let evaluated = engine.run(program);
 
-        writeln!(stdout, "{evaluated}").expect("parsed program should be written to stdout");
+        match (&evaluated, engine.error_span()) {
+            (Object::Error(error), Some(span)) => {
+                write!(stdout, "{}", Diagnostic::runtime(error, span).render(ORIGIN, &session))
+            }
+            _ => writeln!(stdout, "{evaluated}"),
+        }
+        .expect("parsed program should be written to stdout");
     }
 }
 
-fn print_parse_errors(mut stdout: &Stdout, errors: &Vec<String>) {
-    writeln!(
-        stdout,
-        "
-     ####### #######
-        #       #
-        #       #
-        #       #
-        #       #
-"
-    )
-    .unwrap();
-    writeln!(stdout, "Oops! We have error into parser")
-        .expect("error message info should be written to stdout");
+fn print_parse_errors(mut stdout: &Stdout, errors: &[ParserError], session: &str) {
     for error in errors {
-        writeln!(stdout, "\t=> {error}").expect("error should be written to stdout");
+        write!(stdout, "{}", Diagnostic::from(error).render(ORIGIN, session))
+            .expect("error should be written to stdout");
     }
 }
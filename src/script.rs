@@ -0,0 +1,140 @@
+use std::io::Write;
+
+use crate::{
+    diagnostics::Diagnostic,
+    engine::{Backend, Engine},
+    evaluator::IntegerOverflow,
+    lexer::Lexer,
+    object::Object,
+    parser::Parser,
+};
+
+/// Outcome of running a whole program non-interactively.
+#[derive(Debug, PartialEq)]
+pub enum ScriptStatus {
+    Success,
+    ParseError,
+    RuntimeError,
+}
+
+impl ScriptStatus {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Success => 0,
+            Self::ParseError | Self::RuntimeError => 1,
+        }
+    }
+}
+
+// a `#!/usr/bin/env inter` first line is blanked out rather than removed,
+// so every following line keeps its original line number.
+pub fn strip_shebang(source: &str) -> String {
+    if !source.starts_with("#!") {
+        return source.to_string();
+    }
+
+    match source.find('\n') {
+        Some(idx) => source[idx..].to_string(),
+        None => String::new(),
+    }
+}
+
+// `origin` names the program in diagnostics: a file path, `<stdin>` or `<-e>`
+pub fn run(
+    origin: &str,
+    source: &str,
+    backend: Backend,
+    integer_overflow: IntegerOverflow,
+    stack_limit: usize,
+    stderr: &mut impl Write,
+) -> ScriptStatus {
+    let source = strip_shebang(source);
+
+    let lexer = Lexer::new(source.as_str());
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program().expect("error parsing program");
+
+    if !parser.errors().is_empty() {
+        for error in parser.errors() {
+            let report = Diagnostic::from(error).render(origin, source.as_str());
+            writeln!(stderr, "{report}").expect("error should be written to stderr");
+        }
+        return ScriptStatus::ParseError;
+    }
+
+    let mut engine = Engine::new(backend);
+    engine.set_integer_overflow(integer_overflow);
+    engine.set_stack_limit(stack_limit);
+    match engine.run(program) {
+        Object::Error(error) => {
+            let span = engine.error_span().unwrap_or_default();
+            let report = Diagnostic::runtime(&error, span).render(origin, source.as_str());
+            writeln!(stderr, "{report}").expect("error should be written to stderr");
+            ScriptStatus::RuntimeError
+        }
+        _ => ScriptStatus::Success,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{engine::Backend, evaluator::{IntegerOverflow, DEFAULT_STACK_LIMIT}};
+
+    use super::{run, strip_shebang, ScriptStatus};
+
+    #[test]
+    fn test_strip_shebang() {
+        let tests = vec![
+            ("#!/usr/bin/env inter\nsay a = 1;", "\nsay a = 1;"),
+            ("#!/usr/bin/env inter", ""),
+            ("say a = 1;", "say a = 1;"),
+            ("say a = 1;\n#!not a shebang", "say a = 1;\n#!not a shebang"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(strip_shebang(input), expected, "wrong output for {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_run_status() {
+        let tests = vec![
+            ("#!/usr/bin/env inter\nsay a = 5; a * 2;", ScriptStatus::Success, ""),
+            (
+                "say = 5;",
+                ScriptStatus::ParseError,
+                "error[E0101]: expected next token to be Ident, got== instead\n --> test.twot:1:5\n",
+            ),
+            (
+                "say a = 1;\n  a + true;",
+                ScriptStatus::RuntimeError,
+                "error[E0201]: type mismatch: INTEGER + BOOLEAN\n --> test.twot:2:3\n",
+            ),
+            (
+                "say big = 9223372036854775807;\nbig * 2",
+                ScriptStatus::RuntimeError,
+                "error[E0208]: integer overflow: 9223372036854775807 * 2\n --> test.twot:2:1\n",
+            ),
+            (
+                "say day = 9;\nmatch day { 6 => \"sat\", 7 => \"sun\" }",
+                ScriptStatus::RuntimeError,
+                "error[E0209]: no match arm matches: 9\n --> test.twot:2:1\n",
+            ),
+        ];
+
+        for backend in [Backend::TreeWalker, Backend::Vm] {
+            for (input, expected, message) in &tests {
+                let mut stderr = vec![];
+                let status = run("test.twot", input, backend, IntegerOverflow::Error, DEFAULT_STACK_LIMIT, &mut stderr);
+                let stderr = String::from_utf8(stderr).unwrap();
+
+                assert_eq!(&status, expected, "wrong status for {:?} on {:?}", input, backend);
+                assert!(stderr.starts_with(message), "wrong stderr for {:?} on {:?}, got={}", input, backend, stderr);
+            }
+
+            let mut stderr = vec![];
+            let status = run("test.twot", "9223372036854775807 * 2", backend, IntegerOverflow::Promote, DEFAULT_STACK_LIMIT, &mut stderr);
+            assert_eq!(status, ScriptStatus::Success, "overflow should be promoted on {:?}", backend);
+        }
+    }
+}
@@ -4,6 +4,42 @@ use std::fmt::Display;
 pub struct Token {
     pub kind: TokenKind,
     pub literal: String,
+    pub span: Span,
+}
+
+//      line and column are 1-based, offset is the byte offset into the source
+#[derive(PartialEq, Debug, Default, Clone, Copy)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+//      end is exclusive: it points just past the last character of the span
+#[derive(PartialEq, Debug, Default, Clone, Copy)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Span {
+        Span { start, end }
+    }
+
+    // covers everything from the start of `self` to the end of `other`
+    pub fn to(&self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end,
+        }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.start.line, self.start.column)
+    }
 }
 
 #[derive(PartialEq, Debug, Default, Clone, Hash, Eq)]
@@ -11,26 +47,47 @@ pub enum TokenKind {
     #[default]
     Illegal,
     Eof,            // End of file
+    DocComment,     // `///` text, only when the lexer is asked to keep them
 
     Ident,
     Int,
+    Float,
 
     Assign,
+    PlusAssign,
+    MinusAssign,
+    AsteriskAssign,
+    SlashAssign,
     Plus,
     Minus,
     Bang,
     Asterisk,
     Slash,
+    Percent,
+    Power,          // **
+    Tilde,          // ~, bitwise not
 
     Lt,
     Gt,
+    LtEq,
+    GtEq,
 
     Eq,             // Equal
     NotEq,          // Not Equal
 
+    And,            // &&
+    Or,             // ||
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftLeft,
+    ShiftRight,
+
     Comma,
     Semicolon,
     Colon,
+    FatArrow,       // =>, between a match pattern and its value
+    Ellipsis,       // ..., before a rest parameter
 
     Lparen,
     Rparen,
@@ -46,7 +103,16 @@ pub enum TokenKind {
     If,
     Else,
     Return,
+    While,
+    For,
+    In,
+    Break,
+    Continue,
+    Match,
     String,
+    InterpolationStart,     // `"text {` up to an embedded expression
+    InterpolationMiddle,    // `} text {` between two embedded expressions
+    InterpolationEnd,       // `} text"` after the last one
 }
 
 impl Display for TokenKind {
@@ -54,9 +120,15 @@ impl Display for TokenKind {
         match self {
             TokenKind::Illegal => write!(f, "Illegal"),
             TokenKind::Eof => write!(f, "Eof"),
+            TokenKind::DocComment => write!(f, "DocComment"),
             TokenKind::Ident => write!(f, "Ident"),
             TokenKind::Int => write!(f, "Int"),
+            TokenKind::Float => write!(f, "Float"),
             TokenKind::Assign => write!(f, "="),
+            TokenKind::PlusAssign => write!(f, "+="),
+            TokenKind::MinusAssign => write!(f, "-="),
+            TokenKind::AsteriskAssign => write!(f, "*="),
+            TokenKind::SlashAssign => write!(f, "/="),
             TokenKind::Plus => write!(f, "+"),
             TokenKind::Comma => write!(f, ","),
             TokenKind::Semicolon => write!(f, ";"),
@@ -70,25 +142,48 @@ impl Display for TokenKind {
             TokenKind::Slash => write!(f, "/"),
             TokenKind::Bang => write!(f, "!"),
             TokenKind::Asterisk => write!(f, "*"),
+            TokenKind::Percent => write!(f, "%"),
+            TokenKind::Power => write!(f, "**"),
+            TokenKind::Tilde => write!(f, "~"),
             TokenKind::Lt => write!(f, "<"),
             TokenKind::Gt => write!(f, ">"),
+            TokenKind::LtEq => write!(f, "<="),
+            TokenKind::GtEq => write!(f, ">="),
+            TokenKind::And => write!(f, "&&"),
+            TokenKind::Or => write!(f, "||"),
+            TokenKind::BitAnd => write!(f, "&"),
+            TokenKind::BitOr => write!(f, "|"),
+            TokenKind::BitXor => write!(f, "^"),
+            TokenKind::ShiftLeft => write!(f, "<<"),
+            TokenKind::ShiftRight => write!(f, ">>"),
             TokenKind::True => write!(f, "true"),
             TokenKind::False => write!(f, "false"),
             TokenKind::If => write!(f, "if"),
             TokenKind::Else => write!(f, "else"),
             TokenKind::Return => write!(f, "return"),
+            TokenKind::While => write!(f, "while"),
+            TokenKind::For => write!(f, "for"),
+            TokenKind::In => write!(f, "in"),
+            TokenKind::Break => write!(f, "break"),
+            TokenKind::Continue => write!(f, "continue"),
+            TokenKind::Match => write!(f, "match"),
             TokenKind::Eq => write!(f, "=="),
             TokenKind::NotEq => write!(f, "!="),
             TokenKind::String => write!(f, "String"),
+            TokenKind::InterpolationStart => write!(f, "InterpolationStart"),
+            TokenKind::InterpolationMiddle => write!(f, "InterpolationMiddle"),
+            TokenKind::InterpolationEnd => write!(f, "InterpolationEnd"),
             TokenKind::Lbracket => write!(f, "["),
             TokenKind::Rbracket => write!(f, "]"),
             TokenKind::Colon => write!(f, ":"),
+            TokenKind::FatArrow => write!(f, "=>"),
+            TokenKind::Ellipsis => write!(f, "..."),
         }
     }
 }
 
-pub fn lookup_ident(identifier: &String) -> TokenKind {
-    match identifier.as_str() {
+pub fn lookup_ident(identifier: &str) -> TokenKind {
+    match identifier {
         "function" => TokenKind::Function,
         "say" => TokenKind::Say,
         "true" => TokenKind::True,
@@ -96,6 +191,12 @@ pub fn lookup_ident(identifier: &String) -> TokenKind {
         "if" => TokenKind::If,
         "else" => TokenKind::Else,
         "return" => TokenKind::Return,
+        "while" => TokenKind::While,
+        "for" => TokenKind::For,
+        "in" => TokenKind::In,
+        "break" => TokenKind::Break,
+        "continue" => TokenKind::Continue,
+        "match" => TokenKind::Match,
         _ => TokenKind::Ident,
     }
 }
\ No newline at end of file
@@ -0,0 +1,936 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{
+    builtins::Builtin,
+    code::{read_u16, read_u8, Opcode},
+    compiler::{Bytecode, SymbolScope},
+    evaluator::{Evaluator, IntegerOverflow},
+    object::{CallContext, Cell, Closure, CompiledFunction, DictPair, Dictado, Object},
+    token::Span,
+};
+
+const STACK_SIZE: usize = 2048;
+const MAX_FRAMES: usize = 1024;
+
+struct Frame {
+    closure: Closure,
+    ip: usize,
+    locals: Vec<Cell>,
+    base_pointer: usize,
+}
+
+impl Frame {
+    fn new(closure: Closure, locals: Vec<Cell>, base_pointer: usize) -> Frame {
+        Frame {
+            closure,
+            ip: 0,
+            locals,
+            base_pointer,
+        }
+    }
+}
+
+//      runs the bytecode produced by the compiler. Globals outlive a single `run`,
+//      so the REPL can feed it one input at a time
+pub struct VM {
+    constants: Vec<Object>,
+    globals: Vec<Option<Object>>,
+    global_names: Vec<String>,
+    builtins: Vec<Object>,
+    stack: Vec<Object>,
+    frames: Vec<Frame>,
+    last_popped: Object,
+    error_span: Option<Span>,
+    integer_overflow: IntegerOverflow,
+}
+
+impl Default for VM {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VM {
+    pub fn new() -> VM {
+        VM {
+            constants: vec![],
+            globals: vec![],
+            global_names: vec![],
+            builtins: Builtin
+                .all_builtins()
+                .into_iter()
+                .map(|(_, builtin)| builtin)
+                .collect(),
+            stack: Vec::with_capacity(STACK_SIZE),
+            frames: vec![],
+            last_popped: Object::Null,
+            error_span: None,
+            integer_overflow: IntegerOverflow::default(),
+        }
+    }
+
+    pub fn set_integer_overflow(&mut self, integer_overflow: IntegerOverflow) {
+        self.integer_overflow = integer_overflow;
+    }
+
+    // span of the instruction that raised the last runtime error
+    pub fn error_span(&self) -> Option<Span> {
+        self.error_span
+    }
+
+    pub fn set_global(&mut self, index: usize, value: Object) {
+        if index >= self.globals.len() {
+            self.globals.resize(index + 1, None);
+        }
+        self.globals[index] = Some(value);
+    }
+
+    pub fn global(&self, index: usize) -> Option<Object> {
+        self.globals.get(index).cloned().flatten()
+    }
+
+    pub fn builtin(&self, index: usize) -> Object {
+        self.builtins[index].clone()
+    }
+
+    // calls a function value outside of `run`, e.g. a closure an earlier program left in a global
+    pub fn call_function(&mut self, function: &Object, arguments: Vec<Object>) -> Object {
+        self.stack.clear();
+        self.frames.clear();
+        self.error_span = None;
+
+        // an empty main frame for the call to return into
+        let main = Closure {
+            function: Rc::new(CompiledFunction::default()),
+            free: vec![],
+        };
+        self.frames.push(Frame::new(main, vec![], 0));
+
+        self.apply(function, arguments)
+    }
+
+    // the value of the last expression statement, or `Object::Error` if execution failed
+    pub fn run(&mut self, bytecode: Bytecode) -> Object {
+        self.constants = bytecode.constants;
+        self.global_names = bytecode.global_names;
+        self.globals.resize(self.global_names.len(), None);
+        self.stack.clear();
+        self.frames.clear();
+        self.last_popped = Object::Null;
+        self.error_span = None;
+
+        let main = Closure {
+            function: bytecode.main,
+            free: vec![],
+        };
+        self.frames.push(Frame::new(main, vec![], 0));
+
+        match self.execute(0) {
+            Ok(()) => self.last_popped.clone(),
+            Err(message) => Object::Error(message),
+        }
+    }
+
+    // runs until only `depth` frames are left. A builtin calling back into a function runs
+    // it with a nested `execute`, so the span of an error is kept from the innermost one
+    fn execute(&mut self, depth: usize) -> Result<(), String> {
+        while self.frames.len() > depth {
+            let frame = self.frames.last().unwrap();
+            let function = Rc::clone(&frame.closure.function);
+            let ip = frame.ip;
+
+            // only the main frame runs off its end, functions always return
+            if ip >= function.instructions.len() {
+                break;
+            }
+
+            if let Err(message) = self.step(&function, ip) {
+                if self.error_span.is_none() {
+                    self.error_span = function.span_at(ip);
+                }
+                return Err(message);
+            }
+        }
+        Ok(())
+    }
+
+    // calls a value from a builtin; the frames and stack are back as they were afterwards
+    fn call_value(&mut self, function: &Object, arguments: Vec<Object>) -> Result<Object, String> {
+        let depth = self.frames.len();
+        let stack_len = self.stack.len();
+        let num_arguments = arguments.len();
+
+        let result = std::iter::once(function.clone())
+            .chain(arguments)
+            .try_for_each(|value| self.push(value))
+            .and_then(|()| self.call(num_arguments, vec![]))
+            .and_then(|()| self.execute(depth))
+            .map(|()| self.pop());
+
+        if result.is_err() {
+            self.frames.truncate(depth);
+            self.stack.truncate(stack_len);
+        }
+        result
+    }
+
+    fn step(&mut self, function: &CompiledFunction, ip: usize) -> Result<(), String> {
+        let instructions = &function.instructions;
+        let opcode = Opcode::from_byte(instructions[ip])
+            .ok_or_else(|| format!("unknown opcode: {}", instructions[ip]))?;
+
+        let operand = match opcode.operand_widths() {
+            [2] => read_u16(instructions, ip + 1),
+            [1] => read_u8(instructions, ip + 1),
+            _ => 0,
+        };
+        let width: usize = opcode.operand_widths().iter().sum();
+        self.current_frame().ip = ip + 1 + width;
+
+        match opcode {
+            Opcode::Constant => {
+                let constant = self.constants[operand].clone();
+                self.push(constant)?;
+            }
+            Opcode::Pop => {
+                self.last_popped = self.pop();
+            }
+            Opcode::Add
+            | Opcode::Sub
+            | Opcode::Mul
+            | Opcode::Div
+            | Opcode::Mod
+            | Opcode::Pow
+            | Opcode::BitAnd
+            | Opcode::BitOr
+            | Opcode::BitXor
+            | Opcode::ShiftLeft
+            | Opcode::ShiftRight
+            | Opcode::Equal
+            | Opcode::NotEqual
+            | Opcode::LessThan
+            | Opcode::GreaterThan
+            | Opcode::LessEqual
+            | Opcode::GreaterEqual => {
+                let right = self.pop();
+                let left = self.pop();
+                let operator = match opcode {
+                    Opcode::Add => "+",
+                    Opcode::Sub => "-",
+                    Opcode::Mul => "*",
+                    Opcode::Div => "/",
+                    Opcode::Mod => "%",
+                    Opcode::Pow => "**",
+                    Opcode::BitAnd => "&",
+                    Opcode::BitOr => "|",
+                    Opcode::BitXor => "^",
+                    Opcode::ShiftLeft => "<<",
+                    Opcode::ShiftRight => ">>",
+                    Opcode::Equal => "==",
+                    Opcode::NotEqual => "!=",
+                    Opcode::LessThan => "<",
+                    Opcode::GreaterThan => ">",
+                    Opcode::LessEqual => "<=",
+                    _ => ">=",
+                };
+                let result = Evaluator::eval_infix_expression(operator, &left, &right, self.integer_overflow);
+                self.push_result(result)?;
+            }
+            Opcode::Minus | Opcode::Bang | Opcode::BitNot => {
+                let right = self.pop();
+                let operator = match opcode {
+                    Opcode::Minus => "-",
+                    Opcode::Bang => "!",
+                    _ => "~",
+                };
+                let result = Evaluator::eval_prefix_expression(operator, right, self.integer_overflow);
+                self.push_result(result)?;
+            }
+            Opcode::True => self.push(Object::Boolean(true))?,
+            Opcode::False => self.push(Object::Boolean(false))?,
+            Opcode::Null => self.push(Object::Null)?,
+            Opcode::JumpNotTruthy => {
+                let condition = self.pop();
+                if !Evaluator::is_truthy(condition) {
+                    self.current_frame().ip = operand;
+                }
+            }
+            Opcode::Jump => {
+                self.current_frame().ip = operand;
+            }
+            Opcode::GetIter => {
+                let items = match self.pop() {
+                    Object::Array(elements) => elements.borrow().clone(),
+                    Object::StringObject(string) => string
+                        .chars()
+                        .map(|ch| Object::StringObject(ch.to_string()))
+                        .collect(),
+                    other => return Err(format!("not iterable: {}", other.object_type())),
+                };
+                self.push(Object::new_array(items))?;
+                self.push(Object::Integer(0))?;
+            }
+            Opcode::ForIter => {
+                let len = self.stack.len();
+                let next = match (&self.stack[len - 2], &self.stack[len - 1]) {
+                    (Object::Array(items), Object::Integer(idx)) => {
+                        items.borrow().get(*idx as usize).cloned()
+                    }
+                    _ => None,
+                };
+
+                match next {
+                    Some(item) => {
+                        if let Object::Integer(idx) = &mut self.stack[len - 1] {
+                            *idx += 1;
+                        }
+                        self.push(item)?;
+                    }
+                    None => self.current_frame().ip = operand,
+                }
+            }
+            Opcode::GetGlobal => {
+                let value = self.globals[operand].clone().ok_or_else(|| {
+                    format!("Identifier not found: {}", self.global_names[operand])
+                })?;
+                self.push(value)?;
+            }
+            Opcode::SetGlobal => {
+                self.globals[operand] = Some(self.peek().clone());
+            }
+            Opcode::GetLocal => {
+                let frame = self.frames.last().unwrap();
+                let value = frame.locals[operand].borrow().clone().ok_or_else(|| {
+                    format!("Identifier not found: {}", function.local_names[operand])
+                })?;
+                self.push(value)?;
+            }
+            Opcode::SetLocal => {
+                let value = self.peek().clone();
+                let frame = self.frames.last().unwrap();
+                *frame.locals[operand].borrow_mut() = Some(value);
+            }
+            Opcode::GetFree => {
+                let frame = self.frames.last().unwrap();
+                let value = frame.closure.free[operand].borrow().clone().ok_or_else(|| {
+                    format!("Identifier not found: {}", function.free_names[operand])
+                })?;
+                self.push(value)?;
+            }
+            Opcode::AssignGlobal => {
+                if self.globals[operand].is_none() {
+                    return Err(format!("cannot assign to undeclared name: {}", self.global_names[operand]));
+                }
+                self.globals[operand] = Some(self.peek().clone());
+            }
+            // whether a parameter was left for its default
+            Opcode::IsUnset => {
+                let frame = self.frames.last().unwrap();
+                let unset = frame.locals[operand].borrow().is_none();
+                self.push(Object::Boolean(unset))?;
+            }
+            Opcode::AssignLocal | Opcode::AssignFree => {
+                let value = self.peek().clone();
+                let frame = self.frames.last().unwrap();
+                let (cell, name) = match opcode {
+                    Opcode::AssignLocal => (&frame.locals[operand], &function.local_names[operand]),
+                    _ => (&frame.closure.free[operand], &function.free_names[operand]),
+                };
+
+                if cell.borrow().is_none() {
+                    return Err(format!("cannot assign to undeclared name: {}", name));
+                }
+                *cell.borrow_mut() = Some(value);
+            }
+            Opcode::GetBuiltin => {
+                let builtin = self.builtins[operand].clone();
+                self.push(builtin)?;
+            }
+            Opcode::Array => {
+                let elements = self.stack.split_off(self.stack.len() - operand);
+                self.push(Object::new_array(elements))?;
+            }
+            Opcode::Interpolate => {
+                let parts = self.stack.split_off(self.stack.len() - operand);
+                let string: String = parts.iter().map(|part| part.to_string()).collect();
+                self.push(Object::StringObject(string))?;
+            }
+            Opcode::Dict => {
+                let items = self.stack.split_off(self.stack.len() - operand);
+                let mut pairs = HashMap::new();
+                let mut items = items.into_iter();
+
+                while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                    let dict_key = key.dict_key()?;
+                    pairs.insert(dict_key, DictPair { key, value });
+                }
+                self.push(Object::new_dict(pairs))?;
+            }
+            Opcode::Index => {
+                let index = self.pop();
+                let left = self.pop();
+                let result = Evaluator::eval_index_expression(left, index);
+                self.push_result(result)?;
+            }
+            Opcode::SetIndex => {
+                let value = self.pop();
+                let index = self.pop();
+                let left = self.pop();
+                let result = Evaluator::eval_index_assignment(&left, index, value);
+                self.push_result(result)?;
+            }
+            Opcode::Dup2 => {
+                let len = self.stack.len();
+                let left = self.stack[len - 2].clone();
+                let right = self.stack[len - 1].clone();
+                self.push(left)?;
+                self.push(right)?;
+            }
+            // the pattern tests of `match`, each leaves a boolean
+            Opcode::MatchArray => {
+                let matches = matches!(self.pop(), Object::Array(elements) if elements.borrow().len() == operand);
+                self.push(Object::Boolean(matches))?;
+            }
+            Opcode::MatchDict => {
+                let matches = matches!(self.pop(), Object::DictObject(_));
+                self.push(Object::Boolean(matches))?;
+            }
+            Opcode::MatchKey => {
+                let key = self.pop();
+                let matches = match (self.pop(), key.dict_key()) {
+                    (Object::DictObject(dictionary), Ok(key)) => dictionary.borrow().pairs.contains_key(&key),
+                    _ => false,
+                };
+                self.push(Object::Boolean(matches))?;
+            }
+            Opcode::NoMatch => return Err(format!("no match arm matches: {}", self.pop())),
+            Opcode::Call => self.call(operand, vec![])?,
+            Opcode::CallNamed => {
+                let names = match self.pop() {
+                    Object::Array(names) => names.borrow().iter().map(|name| name.to_string()).collect(),
+                    other => unreachable!("argument names should be an array, got {}", other.object_type()),
+                };
+                self.call(operand, names)?
+            }
+            Opcode::ReturnValue => {
+                let value = self.pop();
+                self.return_from_frame(value)?;
+            }
+            Opcode::Return => self.return_from_frame(Object::Null)?,
+            Opcode::Closure => {
+                let function = match &self.constants[operand] {
+                    Object::CompiledFunction(function) => Rc::clone(function),
+                    other => return Err(format!("not a function: {}", other.object_type())),
+                };
+
+                let frame = self.frames.last().unwrap();
+                let free = function
+                    .captures
+                    .iter()
+                    .map(|(scope, idx)| match scope {
+                        SymbolScope::Free => Rc::clone(&frame.closure.free[*idx]),
+                        _ => Rc::clone(&frame.locals[*idx]),
+                    })
+                    .collect();
+                self.push(Object::Closure(Closure { function, free }))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // the stack holds the callee, the positional arguments, then one value for each name
+    fn call(&mut self, num_positional: usize, names: Vec<String>) -> Result<(), String> {
+        let base_pointer = self.stack.len() - num_positional - names.len() - 1;
+        let callee = self.stack[base_pointer].clone();
+
+        let mut arguments = self.stack.split_off(base_pointer + 1);
+        let named: Vec<(String, Object)> = names.into_iter().zip(arguments.split_off(num_positional)).collect();
+
+        match callee {
+            Object::Closure(closure) => {
+                let function = &closure.function;
+                let values = Evaluator::bind_arguments(&function.parameters, arguments, named)?;
+                if self.frames.len() >= MAX_FRAMES {
+                    return Err(String::from("stack overflow"));
+                }
+                self.stack.truncate(base_pointer);
+
+                // parameters left as `None` are filled in by the function's own default code
+                let mut values = values.into_iter();
+                let locals = (0..function.num_locals)
+                    .map(|_| Rc::new(RefCell::new(values.next().flatten())))
+                    .collect();
+
+                self.frames.push(Frame::new(closure, locals, base_pointer));
+                Ok(())
+            }
+            Object::Builtin(_) if !named.is_empty() => {
+                Err(String::from("named arguments are not supported by builtin functions"))
+            }
+            Object::Builtin(builtin) => {
+                self.stack.truncate(base_pointer);
+                let result = builtin.call(self, arguments);
+                self.push_result(result)
+            }
+            other => Err(format!("not a function: {}", other.object_type())),
+        }
+    }
+
+    fn return_from_frame(&mut self, value: Object) -> Result<(), String> {
+        let frame = self.frames.pop().expect("a frame to return from");
+
+        // a `return` at the top level ends the program with its value
+        if self.frames.is_empty() {
+            self.last_popped = value;
+            return Ok(());
+        }
+
+        self.stack.truncate(frame.base_pointer);
+        self.push(value)
+    }
+
+    fn current_frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("a frame to execute")
+    }
+
+    fn push(&mut self, object: Object) -> Result<(), String> {
+        if self.stack.len() >= STACK_SIZE {
+            return Err(String::from("stack overflow"));
+        }
+        self.stack.push(object);
+        Ok(())
+    }
+
+    // results of the evaluator's helpers carry their errors as `Object::Error`
+    fn push_result(&mut self, object: Object) -> Result<(), String> {
+        match object {
+            Object::Error(message) => Err(message),
+            object => self.push(object),
+        }
+    }
+
+    fn pop(&mut self) -> Object {
+        self.stack.pop().expect("stack underflow")
+    }
+
+    fn peek(&self) -> &Object {
+        self.stack.last().expect("stack underflow")
+    }
+}
+
+impl CallContext for VM {
+    fn apply(&mut self, function: &Object, arguments: Vec<Object>) -> Object {
+        match self.call_value(function, arguments) {
+            Ok(value) => value,
+            Err(message) => Object::Error(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        compiler::Compiler,
+        evaluator::{Evaluator, IntegerOverflow},
+        lexer::Lexer,
+        object::Object,
+        parser::Parser,
+    };
+
+    use super::VM;
+
+    fn run_vm(input: &str) -> (Object, Option<crate::token::Span>) {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+
+        let mut compiler = Compiler::new();
+        compiler.compile(&program).expect("program should compile");
+
+        let mut vm = VM::new();
+        let result = vm.run(compiler.bytecode());
+        (result, vm.error_span())
+    }
+
+    fn run_tree_walker(input: &str) -> (Object, Option<crate::token::Span>) {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+
+        let mut evaluator = Evaluator::new();
+        let result = evaluator.eval_program(program);
+        (result, evaluator.error_span())
+    }
+
+    // dictionaries are compared by their pairs, their display order is not stable
+    fn describe(object: &Object) -> String {
+        match object {
+            Object::DictObject(dict) => {
+                let mut pairs: Vec<String> = dict
+                    .borrow()
+                    .pairs
+                    .values()
+                    .map(|pair| format!("{}: {}", describe(&pair.key), describe(&pair.value)))
+                    .collect();
+                pairs.sort();
+                format!("{{{}}}", pairs.join(", "))
+            }
+            Object::Array(elements) => {
+                let elements: Vec<String> = elements.borrow().iter().map(describe).collect();
+                format!("[{}]", elements.join(", "))
+            }
+            other => format!("{} {}", other.object_type(), other),
+        }
+    }
+
+    fn assert_same_as_tree_walker(inputs: &[&str]) {
+        for input in inputs {
+            let (expected, expected_span) = run_tree_walker(input);
+            let (got, span) = run_vm(input);
+
+            assert_eq!(describe(&got), describe(&expected), "different result for {:?}", input);
+            if let Object::Error(_) = expected {
+                assert_eq!(span, expected_span, "different error span for {:?}", input);
+            }
+        }
+    }
+
+    #[test]
+    fn test_integer_and_boolean_expressions() {
+        assert_same_as_tree_walker(&[
+            "5", "10", "-5", "-10", "5 + 5 + 5 + 5 - 10", "2 * 2 * 2 * 2 * 2", "-50 + 100 + -50",
+            "5 * 2 + 10", "5 + 2 * 10", "20 + 2 * -10", "50 / 2 * 2 + 10", "2 * (5 + 10)",
+            "3 * 3 * 3 + 10", "3 * (3 * 3) + 10", "(5 + 10 * 2 + 15 / 3) * 2 + -10",
+            "true", "false", "1 < 2", "1 > 2", "1 < 1", "1 == 1", "1 != 2", "true == true",
+            "true != false", "(1 < 2) == true", "(1 > 2) == false", "!true", "!!5", "!5",
+        ]);
+    }
+
+    #[test]
+    fn test_conditionals_and_returns() {
+        assert_same_as_tree_walker(&[
+            "if (true) { 10 }", "if (false) { 10 }", "if (1) { 10 }", "if (1 < 2) { 10 }",
+            "if (1 > 2) { 10 } else { 20 }", "if (1 < 2) { 10 } else { 20 }", "if (true) {}",
+            "return 10;", "return 10; 9;", "return 2 * 5; 9;", "9; return 2 * 5; 9;",
+            "if (10 > 1) { if (10 > 1) { return 10; } return 1; }",
+            "say f = function(x) { return x; x + 10; }; f(10);",
+            "say f = function(x) { say result = x + 10; return result; return 10; }; f(10);",
+            "say a = 5; a;", "say a = 5 * 5; a;", "say a = 5; say b = a; b;",
+            "say a = 5; say b = a; say c = a + b + 5; c;",
+            "if (1 > 2) { 10 } else if (2 > 1) { 20 } else { 30 }", "if (1 > 2) { 10 } else if (2 > 3) { 20 }",
+        ]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_same_as_tree_walker(&[
+            "5 + true;", "5 + true; 5;", "-true", "true + false;", "5; true + false; 5",
+            "if (10 > 1) { true + false; }", "foobar", r#""Hello" - "World""#,
+            r#"{"name": "TwoT"}[function(x) { x }];"#, "say a = 1;\n  a + true;", "5(1)",
+            "say f = function() { y }; f()",
+        ]);
+    }
+
+    #[test]
+    fn test_functions_and_closures() {
+        assert_same_as_tree_walker(&[
+            "say identity = function(x) { x; }; identity(5);",
+            "say double = function(x) { x * 2; }; double(5);",
+            "say add = function(x, y) { x + y; }; add(5 + 5, add(5, 5));",
+            "function(x) { x; }(5)", "function(x) { x + 2; };", "function() { }()",
+            "say newAdder = function(x) { function(y) { x + y }; }; say addTwo = newAdder(2); addTwo(2);",
+            "say fact = function(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }; fact(10);",
+            "say isEven = function(n) { if (n == 0) { true } else { isOdd(n - 1) } };
+             say isOdd = function(n) { if (n == 0) { false } else { isEven(n - 1) } };
+             isOdd(7);",
+            "say wrapper = function() {
+                say fib = function(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } };
+                fib(15);
+             };
+             wrapper();",
+            "say f = function() { x }; say x = 1; say x = 2; f();",
+            "say outer = function() { say inner = function() { later }; say later = 7; inner() }; outer();",
+            "say a = function(x) { function(y) { function(z) { x + y + z } } }; a(1)(2)(3);",
+            "say len = function(x) { 42 }; len([1, 2, 3]);",
+        ]);
+    }
+
+    #[test]
+    fn test_strings_arrays_and_dictionaries() {
+        assert_same_as_tree_walker(&[
+            r#""hello""#, r#""Hello" + " " + "World!""#, "[1, 2 * 2, 3 + 3]", "[1, 2, 3][0]",
+            "[1, 2, 3][1 + 1]", "say i = 0; [1][i];", "[1, 2, 3][3]", "[1, 2, 3][-1]",
+            "say myArray = [1, 2, 3]; myArray[0] + myArray[1] + myArray[2];",
+            r#"{"one": 10 - 9, "two": 1 + 1, "thr" + "ee": 6 / 2, 4: 4, true: 5, false: 6}"#,
+            r#"{"foo": 5}["foo"]"#, r#"{"foo": 5}["bar"]"#, r#"say key = "foo"; {"foo": 5}[key]"#,
+            r#"{}["foo"]"#, "{5: 5}[5]", "{true: 5}[true]",
+            r#"say n = "x"; "a {n} b {1 + 2} {[n]}""#, r#""outer {"inner {n}"}""#,
+            r#"say f = function(v) { "<{v}>" }; f(1)"#, r#""{1} {1 + true}""#,
+        ]);
+    }
+
+    #[test]
+    fn test_floats() {
+        assert_same_as_tree_walker(&[
+            "1.5", "-.5", "1e-3", "0.1 + 0.2", "1 + 0.5", "3 / 2.0", "2.5 * 4", "1 / 0.0",
+            "1.5 < 2", "2 > 2.5", "1 == 1.0", "say x = 1; x += 0.25; x", "1.5 + true", "-\"a\"",
+        ]);
+    }
+
+    #[test]
+    fn test_integer_arithmetic_errors() {
+        assert_same_as_tree_walker(&[
+            "1 / 0", "say zero = 0; 10 / zero", "9223372036854775807 + 1", "4611686018427387904 * 2",
+            "say min = -9223372036854775807 - 1; -min", "say x = 9223372036854775807; x += 1; x",
+        ]);
+    }
+
+    #[test]
+    fn test_comparison_logical_and_bitwise_operators() {
+        assert_same_as_tree_walker(&[
+            "7 % 3", "7.5 % 2", "2 ** 3 ** 2", "2 ** -1", "2 ** 64", "6 & 3", "6 | 3", "6 ^ 3", "~5",
+            "1 << 64", "-16 >> 2", "3 <= 3", "3 >= 4", "true ^ true", "5 % 0", "1 << -1",
+            "true && 1 < 2", "1 && 0", "0 || false", "false || 2", "false && missing", "true || missing",
+            "true && missing", "say n = 0; false && (n = 1); n", "say n = 0; true || (n = 1); n",
+            "say f = function(x) { x > 0 && x < 10 || x == 42 }; [f(5), f(42), f(11), f(-1)]",
+        ]);
+    }
+
+    #[test]
+    fn test_structural_equality_and_ordering() {
+        assert_same_as_tree_walker(&[
+            r#""a" == "a""#, r#""apple" < "banana""#, r#""ab" <= "a""#, "[1, [2, 3]] == [1, [2, 3]]",
+            "[1, 2.0] == [1.0, 2]", "[1, 2] < [1, 2, 0]", r#"{"a": 1, "b": [2]} == {"b": [2], "a": 1}"#,
+            r#"{"a": 1} != {"a": 1, "b": 2}"#, "if (false) { 1 } == if (false) { 2 }", r#"1 == "1""#,
+            "say f = function(x) { x }; f == f", "function(x) { x } == function(y) { y }", "len == len",
+            r#"1 < "1""#, r#"[1] < ["a"]"#, "true > false",
+        ]);
+    }
+
+    #[test]
+    fn test_match_expressions() {
+        assert_same_as_tree_walker(&[
+            "match 1 { 1 => \"one\", _ => \"other\" }", "match -3 { -3 => true, _ => false }",
+            "match 2.0 { 2 => \"two\" }", "match 5 { n => n * 2 }", "match [] { [] => \"empty\", _ => 1 }",
+            "match [1, 2, 3] { [a, b] => a + b, _ => 0 }", "match [1, [2, 3]] { [x, [y, z]] => x + y + z }",
+            "match [3, 2] { [1, x] => x, _ => 0 }", "match \"ab\" { [a, b] => 1, _ => 2 }",
+            "match {\"x\": 1, \"z\": 2} { {\"x\": x, \"y\": y} => x + y, {\"x\": x} => x * 10 }",
+            "match {1: [true]} { {1: [flag]} => flag }", "match [] { {} => 1, _ => 2 }",
+            "match 15 { n if n < 10 => \"small\", n if n < 100 => \"medium\", _ => \"large\" }",
+            "say x = 1; match 2 { x => x }; x", "say x = 1; match [2, 3] { [x, 4] => 0, _ => 1 }; x",
+            "say f = function(v) { match v { [h, t] => h + f(t), _ => 0 } }; f([1, [2, [3, 0]]])",
+            "say x = 5; match [1] { [x] if false => 0, _ => x }", "say x = 5; say y = match [1] { [x] => x + 1 }; [x, y]",
+            "say f = function(x) { match [1] { [x] => 0 }; x }; f(5)", "say x = 5; match 1 { x => x = 9 }; x",
+            "say f = function(v) { match v { [a, b] => function() { a + b }, _ => 0 } }; f([1, 2])()",
+            "say f = function(v) { say g = function() { w }; match v { w => g() } }; f(7)",
+            "match match 1 { 1 => 2 } { 2 => 3 }", "match 1 { a if match a { 1 => false, _ => true } => 1, _ => 2 }",
+            "match 5 { 1 => 2 }", "match [1] { [a] if a > 1 => a }", "match 1 { n if n + true => 1 }",
+        ]);
+    }
+
+    #[test]
+    fn test_function_declarations() {
+        assert_same_as_tree_walker(&[
+            "function double(x) { x * 2 }; double(4)", "say y = double(4); function double(x) { x * 2 }; y",
+            "function isEven(n) { if (n == 0) { true } else { isOdd(n - 1) } }
+             function isOdd(n) { if (n == 0) { false } else { isEven(n - 1) } }
+             [isEven(10), isOdd(7), isEven(3)]",
+            "say f = function(x) { say r = g(x); function g(y) { h(y) + 1 } function h(z) { z * 10 } r }; f(2)",
+            "say f = function() { function count(n) { if (n == 0) { 0 } else { 1 + count(n - 1) } } count(5) }; f()",
+            "if (true) { say v = inner(); function inner() { 5 } v }",
+            "say total = 0; for (i in [1, 2, 3]) { total += sq(i); function sq(v) { v * v } } total",
+            "function f() { 1 } function f() { 2 } f()", "function noop() {}",
+            "say g = function() { function noop() {} }; g()",
+        ]);
+    }
+
+    #[test]
+    fn test_function_parameters() {
+        assert_same_as_tree_walker(&[
+            "say f = function(x, y = 10) { x + y }; [f(1), f(1, 2)]",
+            "say f = function(x, y = x * 2, z = y + 1) { [x, y, z] }; f(1)",
+            "say f = function(first, ...rest) { [first, rest] }; [f(1), f(1, 2, 3)]",
+            "say f = function(a, b = 2, c = 3) { [a, b, c] }; f(1, c: 30)",
+            "say f = function(a, b) { a - b }; f(b: 1, a: 10)",
+            "function greet(name, greeting = \"hi\") { \"{greeting} {name}\" } greet(greeting: \"hey\", name: \"arash\")",
+            "say x = 1; say f = function(y = x) { y }; say x = 5; f()",
+            "say b = 5; say f = function(a = b, b = 1) { a }; f()", "say a = 10; say f = function(a = a + 1) { a }; f()",
+            "say g = function(a) { function(a = a * 2, b = a) { [a, b] } }; [g(3)(), g(3)(1)]",
+            "say make = function(n = 2) { function(x, k = n) { x * k } }; make()(5, k: 3) + make(4)(5)",
+            "say f = function(a) { a }; f()", "say f = function(a, b = 1) { a }; f(1, 2, 3)",
+            "say f = function(a, b, ...c) { a }; f(1)", "say f = function(a) { a }; f(1, b: 2)",
+            "say f = function(a) { a }; f(1, a: 2)", "say f = function(a = boom) { a }; f()",
+            "len(\"abc\", x: 1)",
+        ]);
+    }
+
+    #[test]
+    fn test_arrow_functions() {
+        assert_same_as_tree_walker(&[
+            "say double = x => x * 2; double(21)",
+            "say add = (a, b = 10) => { say sum = a + b; sum }; [add(1), add(1, 2)]",
+            "say twice = f => x => f(f(x)); twice(x => x + 3)(1)",
+            "say n = 5; say get = () => n; say n = 6; get()",
+            "((first, ...rest) => rest)(1, 2, 3)",
+            "say counter = () => { say count = 0; () => { count += 1 } }; say c = counter(); c(); c()",
+        ]);
+    }
+
+    #[test]
+    fn test_builtins_calling_functions() {
+        assert_same_as_tree_walker(&[
+            "map([1, 2, 3], x => x * 2)", "map([[1], [2, 3]], len)", "filter([1, 2, 3, 4], x => x % 2 == 0)",
+            "reduce([1, 2, 3, 4], (sum, x) => sum + x)", "reduce([], (sum, x) => sum + x, 0)",
+            "sort_by([\"ccc\", \"a\", \"bb\"], len)", "sort_by([[2, \"x\"], [1, \"y\"], [2, \"z\"]], pair => pair[0])",
+            "say calls = 0; map([1, 2], x => { calls += 1 }); calls",
+            "say f = function(n) { map([n], x => filter([x, x + 1], y => y > 1)) }; map([1, 2], f)",
+            "map([1, 2], x => x + true)", "map([1], x => map([x], y => y()))", "map([1], 5)",
+            "sort_by([1, \"a\"], x => x)",
+        ]);
+    }
+
+    #[test]
+    fn test_collection_builtins() {
+        assert_same_as_tree_walker(&[
+            "rest([1, 2, 3])", "sort([3, 1, 2])", "sort([3, 1, 2], (a, b) => b - a)",
+            "sort([[2, 1], [1, 2], [2, 0]], (a, b) => a[0] - b[0])", "reverse([1, 2, 3])",
+            "sort(range(50), (a, b) => (a * 31 + b * 17) % 7 - 3)", "sort_by(range(20), (x) => 0.0 / 0.0)",
+            "zip([1, 2, 3], [\"a\", \"b\"])", "enumerate([\"a\", \"b\"])", "range(10, 0, -3)",
+            "contains([1, [2], \"x\"], [2])", "index_of([5, 6, 7], 8)", "slice([1, 2, 3, 4], -2)",
+            "concat([1], [], [2, 3])", "flatten([1, [2, [3]], []])", "unique([1, 2, 1, [3], [3], 1.0])",
+            "reduce([[1], [2, 3]], concat)", "map(zip([1, 2], [3, 4]), pair => pair[0] * pair[1])",
+            "say total = 0; for (pair in enumerate([5, 6])) { total += pair[0] * pair[1] } total",
+            "sort([1, 2], (a, b) => \"x\")", "sort([1, 2], (a, b) => a + true)", "range(1, 5, 0)",
+        ]);
+    }
+
+    #[test]
+    fn test_string_builtins() {
+        assert_same_as_tree_walker(&[
+            "len(\"héllo\")", "split(\"a,b,,c\", \",\")", "join(map([1, 2], x => x * 2), \", \")",
+            "upper(trim(\" hi \"))", "find(\"héllo\", \"l\")", "substr(\"hello\", -3, 2)",
+            "pad_left(to_string(7), 3, \"0\")", "to_int(\"100000000000000000000\")",
+            r#"format(r"{1}/{0}", "a", 2.5)"#, "repeat(\"a\", -1)", "to_int(\"x\")",
+        ]);
+    }
+
+    #[test]
+    fn test_bigints() {
+        assert_same_as_tree_walker(&[
+            "123456789012345678901234567890", "-9223372036854775808", "100000000000000000000 / 3",
+            "100000000000000000000 > 9223372036854775807", "100000000000000000000 == 1e20",
+            "say d = {100000000000000000000: 1}; d[10000000000 * 10000000000]",
+            "say d = {}; d[2 ** 70] = \"big\"; d[-3452502586987142673] = \"small\"; [d[2 ** 70], d[-3452502586987142673]]",
+            "say a = [1]; a[100000000000000000000] = 2", "100000000000000000000 + true",
+        ]);
+    }
+
+    #[test]
+    fn test_integer_overflow_promotion() {
+        let program = Parser::new(Lexer::new(
+            "say f = function(n) { if (n < 2) { 1 } else { n * f(n - 1) } }; f(25) / f(23)",
+        ))
+        .parse_program()
+        .unwrap();
+        let mut compiler = Compiler::new();
+        compiler.compile(&program).expect("program should compile");
+
+        let mut vm = VM::new();
+        vm.set_integer_overflow(IntegerOverflow::Promote);
+        assert_eq!(vm.run(compiler.bytecode()).to_string(), "600");
+    }
+
+    #[test]
+    fn test_loops() {
+        assert_same_as_tree_walker(&[
+            "say i = 0; while (i < 5) { say i = i + 1; } i",
+            "say sum = 0; for (x in [1, 2, 3, 4]) { say sum = sum + x; } sum",
+            "say i = 0; while (true) { say i = i + 1; if (i > 3) { break; } } i",
+            "say sum = 0; for (x in [1, 2, 3, 4]) { if (x == 2) { continue; } say sum = sum + x; } sum",
+            "say find = function(items) { for (x in items) { if (x > 2) { return x; } } 0 }; find([1, 5, 3])",
+            "say f = function() { say total = 0; for (x in [1, 2]) { for (y in [10, 20]) { say total = total + x * y; } } total }; f()",
+            "say fs = function() { say out = []; for (x in [1, 2]) { say out = push(out, function() { x }); } out }; fs()[0]()",
+            r#"say s = ""; for (ch in "abc") { say s = ch + s; } s"#,
+            "while (false) { 1 }", "for (x in []) { x }", "for (x in 5) { x }",
+            "say i = 0; while (i < 3) { say i = i + 1; i + true; }",
+        ]);
+    }
+
+    #[test]
+    fn test_jumps_inside_expressions() {
+        assert_same_as_tree_walker(&[
+            "say out = []; for (x in [1, 2, 3]) { say y = [1, 2, if (x == 2) { continue; } else { x }]; out = concat(out, [y]) } out",
+            "say out = []; for (i in [1, 2, 3]) { out = concat(out, [1 + if (i == 2) { continue; } else { i }]) } out",
+            "say d = [0, 0, 0]; for (i in range(3)) { d[i] += if (i == 2) { break; } else { i + 1 } } d",
+            "say n = 0; while (true) { n += 1; say s = \"{n}{if (n > 2) { break; } else { n }}\"; } n",
+            "say f = function(a, b) { a + b }; say t = 0; for (i in [1, 2, 3]) { t += f(i, b: if (i == 2) { continue; } else { i }) } t",
+            "say out = []; for (a in [1, 2]) { out = concat(out, [{a: 10 + if (true) { for (b in [1, 2]) { if (b == 2) { break; } } 1 } else { 0 }}]) } out",
+            "say f = function() { say y = [1, if (true) { return 5; } else { 2 }]; 7 }; f() + 1",
+            "say f = function(a = if (true) { return 3; } else { 1 }) { a }; [f(), f(2)]",
+        ]);
+    }
+
+    #[test]
+    fn test_cyclic_values() {
+        // `describe` would follow the cycles, so these compare the printed values
+        let tests = vec![
+            ("say a = [0]; a[0] = a; a", "[[...]]"),
+            ("say d = {\"me\": 0}; d[\"me\"] = d; d", "{me: {...}}"),
+            ("say a = [1, 0]; a[1] = a; say b = [1, 0]; b[1] = b; [a == b, a < b, unique([a, b])]", "[true, false, [[1, [...]]]]"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(run_vm(input).0.to_string(), expected, "wrong result for {:?}", input);
+            assert_eq!(run_tree_walker(input).0.to_string(), expected, "wrong result for {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_assignment() {
+        assert_same_as_tree_walker(&[
+            "say x = 1; x = 5; x", "say x = 10; x += 5; x -= 3; x *= 2; x /= 4; x",
+            "say a = 1; say b = 1; a = b = 7; a + b",
+            "say count = 0; say inc = function() { count += 1 }; inc(); inc(); count",
+            "say make = function() { say n = 0; function() { n = n + 10; n } }; say c = make(); c(); c()",
+            "say x = 1; say f = function() { say x = 2; x = 3; x }; f() + x",
+            "say f = function() { say total = 0; for (x in [1, 2, 3]) { total += x; } total }; f()",
+            "say f = function() { later = 1; say later = 2; later }; f()",
+            "len = 3; len", "say f = function() { len = 4 }; f(); len",
+            "y = 1;", "say f = function() { z = 1 }; f();", "y += 1;", "say x = 1;\n x += true;",
+        ]);
+    }
+
+    #[test]
+    fn test_index_assignment() {
+        assert_same_as_tree_walker(&[
+            "say a = [1, 2, 3]; a[1] = 20; a", "say a = [1, 2, 3]; say b = a; b[0] = 10; a[0]",
+            "say a = [1, 2, 3]; a[2] += 5; a[2]", r#"say d = {"k": 1}; d["k"] = 7; d"#,
+            r#"say d = {}; say alias = d; alias["new"] = 3; d["new"]"#,
+            r#"say d = {"n": 2}; d["n"] *= 21; d["n"]"#,
+            "say a = [1]; say set = function(arr) { arr[0] = 9 }; set(a); a[0]",
+            "say grid = [[0, 0], [0, 0]]; say row = grid[1]; row[0] = 4; grid",
+            "say a = [1, 2, 3]; for (x in a) { a[0] = a[0] + x; } a",
+            "say a = [1, 2]; a[2] = 0;", r#"say a = [1]; a["x"] = 0;"#, "say d = {}; d[[1]] = 0;",
+            "say s = 1;\n s[0] = 2;", "say d = {}; d[\"x\"] += 1;",
+        ]);
+    }
+
+    #[test]
+    fn test_builtin_functions() {
+        assert_same_as_tree_walker(&[
+            r#"len("")"#, r#"len("four")"#, r#"len("hello world")"#, "len(1)",
+            r#"len("one", "two")"#, "len([1, 2, 3])", "first([1, 2, 3])", "first([])",
+            "first(1)", "last([1, 2, 3])", "last([])", "push([], 1)", "push(1, 1)",
+        ]);
+    }
+
+    #[test]
+    fn test_vm_specific_errors() {
+        let (result, _) = run_vm("say f = function(a, b) { a + b }; f(1);");
+        assert_eq!(describe(&result), "ERROR ERROR: wrong number of arguments: want=2, got=1");
+
+        let (result, _) = run_vm("say f = function() { f() }; f();");
+        assert_eq!(describe(&result), "ERROR ERROR: stack overflow");
+    }
+}